@@ -0,0 +1,207 @@
+//! Load test for the print path: synthetic jobs are fetched from an
+//! in-process stand-in for the FLUX API and captured by the file printer.
+
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::CONTENT_TYPE;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing_subscriber::EnvFilter;
+
+use crate::cli::LogFilterHandle;
+use crate::models::Config;
+use crate::services::flux_client::FluxClient;
+use crate::services::print_job::{
+    FILE_PRINTER_NAME, fetch_and_print_job_by_id, was_completed_recently,
+};
+use crate::state::AppState;
+use crate::utils::config::set_config_dir;
+use crate::utils::pdf::text_page_pdf;
+
+/// Run `jobs` synthetic jobs with `concurrency` in parallel and print
+/// throughput and latency percentiles.
+///
+/// Config and state files go to a temporary directory, so the real ones are
+/// never touched. Only the file printer is supported; a real printer would
+/// receive every job.
+pub async fn run_bench(
+    jobs: u32,
+    concurrency: usize,
+    printer: &str,
+    verbose: u8,
+    log_filter: &LogFilterHandle,
+) -> std::io::Result<()> {
+    if printer != FILE_PRINTER_NAME {
+        return Err(std::io::Error::other(format!(
+            "bench only runs against the file printer '{}'",
+            FILE_PRINTER_NAME
+        )));
+    }
+    // One info line per job would drown the report
+    if verbose == 0 {
+        let _ = log_filter.reload(EnvFilter::new("warn"));
+    }
+
+    let work_dir = tempfile::tempdir()?;
+    set_config_dir(work_dir.path().join("config"));
+    let output_dir = work_dir.path().join("output");
+    std::fs::create_dir_all(&output_dir)?;
+
+    let config = Config {
+        flux_url: serve_stub_api().await?,
+        flux_api_token: Some("bench".to_string()),
+        default_printer: Some(FILE_PRINTER_NAME.to_string()),
+        file_printer_dir: Some(output_dir.to_string_lossy().into_owned()),
+        recent_completed_jobs: jobs as usize,
+        ..Config::default()
+    };
+    let api = FluxClient::new(reqwest::Client::new(), config);
+    let state = AppState::load();
+
+    println!(
+        "Running {} jobs with concurrency {} against the file printer",
+        jobs, concurrency
+    );
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    let started = Instant::now();
+
+    for job_id in 1..=jobs {
+        let semaphore = semaphore.clone();
+        let api = api.clone();
+        let state = state.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.ok()?;
+            let job_started = Instant::now();
+            let result = fetch_and_print_job_by_id(job_id, &api, &state).await;
+            Some((job_id, result.is_ok(), job_started.elapsed()))
+        });
+    }
+
+    // Deferred and skipped jobs also return Ok, so only count those that
+    // reached the file printer or were reported completed
+    let mut finished = Vec::with_capacity(jobs as usize);
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(Some(outcome)) = result {
+            finished.push(outcome);
+        }
+    }
+    let elapsed = started.elapsed();
+    let captured = captured_job_ids(&output_dir);
+    let mut latencies: Vec<Duration> = finished
+        .into_iter()
+        .filter(|&(job_id, ok, _)| {
+            ok && (captured.contains(&job_id) || was_completed_recently(job_id, &state))
+        })
+        .map(|(_, _, latency)| latency)
+        .collect();
+    let not_printed = jobs as usize - latencies.len();
+    latencies.sort();
+
+    println!(
+        "Printed {} jobs in {:.2}s ({:.1} jobs/s), {} failed or not printed",
+        latencies.len(),
+        elapsed.as_secs_f64(),
+        latencies.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        not_printed
+    );
+    if !latencies.is_empty() {
+        let millis = |p: f64| percentile(&latencies, p).as_secs_f64() * 1000.0;
+        println!(
+            "Latency p50 {:.1} ms, p90 {:.1} ms, p99 {:.1} ms, max {:.1} ms",
+            millis(50.0),
+            millis(90.0),
+            millis(99.0),
+            millis(100.0)
+        );
+    }
+    Ok(())
+}
+
+/// Nearest-rank percentile `p` (0-100) of `sorted`, which must not be empty
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Ids of the jobs the file printer wrote to `dir`, from their `job-<id>-` file names
+pub fn captured_job_ids(dir: &Path) -> HashSet<u32> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return HashSet::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let rest = name.to_str()?.strip_prefix("job-")?;
+            rest.split('-').next()?.parse().ok()
+        })
+        .collect()
+}
+
+/// Serve the API calls a job makes on a local port and return its base URL
+async fn serve_stub_api() -> std::io::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let document = Bytes::from(text_page_pdf(&["Benchmark".to_string()]));
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let document = document.clone();
+            let service = service_fn(move |request: Request<Incoming>| {
+                let response = stub_response(request.uri().path(), &document);
+                async move { Ok::<_, Infallible>(response) }
+            });
+            tokio::spawn(async move {
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    Ok(url)
+}
+
+/// Response for a stub API path: the job for `/api/print-jobs/<id>`, the
+/// document for media downloads and an empty object for status updates
+fn stub_response(path: &str, document: &Bytes) -> Response<Full<Bytes>> {
+    let (content_type, body) = if path.starts_with("/api/media/private/") {
+        ("application/pdf", document.clone())
+    } else {
+        ("application/json", Bytes::from(stub_json(path)))
+    };
+    Response::builder()
+        .header(CONTENT_TYPE, content_type)
+        .body(Full::new(body))
+        .expect("stub response parts are valid")
+}
+
+fn stub_json(path: &str) -> String {
+    let job_id = path
+        .strip_prefix("/api/print-jobs/")
+        .and_then(|rest| rest.split(['?', '/']).next())
+        .and_then(|id| id.parse::<u32>().ok());
+    match job_id {
+        Some(id) => serde_json::json!({
+            "status": 200,
+            "data": {
+                "id": id, "media_id": id, "printer_id": null, "user_id": null,
+                "quantity": 1, "size": "A4", "is_completed": false, "cups_job_id": null,
+                "status": null, "error_message": null, "printed_at": null,
+                "created_by": null, "updated_by": null, "printer": null
+            }
+        })
+        .to_string(),
+        None => "{}".to_string(),
+    }
+}
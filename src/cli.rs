@@ -1,8 +1,38 @@
-use clap::{ArgAction, Parser, Subcommand};
-use printers::common::base::job::PrinterJobOptions;
+use clap::{ArgAction, ArgGroup, Parser, Subcommand};
+use printers::common::base::job::{PrinterJobOptions, PrinterJobState};
 use printers::{get_printer_by_name, get_printers};
-use std::path::Path;
-use tracing_subscriber::EnvFilter;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use crate::models::{Config, PrintJob};
+use crate::services::flux_client::FluxClient;
+use crate::services::print_job::{
+    FILE_PRINTER_NAME, fetch_and_print_job_by_id, fetch_and_print_job_on_printer, format_unix_utc,
+    retry_dead_letter, validate_cups_job_id,
+};
+use crate::services::printer::{default_system_printer, get_local_printers};
+use crate::services::printer_consistency::check_printer_consistency;
+use crate::services::printer_sync::{SyncReport, sync_printers_with_api};
+use crate::services::update_check::check_for_update;
+use crate::state::AppState;
+use crate::utils::config::{
+    apply_hostname_instance_name, config_path, import_config, load_config, save_config,
+};
+use crate::utils::consumption::{CONSUMPTION_FILE, PrinterConsumption};
+use crate::utils::dead_letters::{DEAD_LETTERS_FILE, DeadLetter};
+use crate::utils::fetch_queue::PENDING_FETCH_FILE;
+use crate::utils::finished_jobs::FINISHED_JOBS_FILE;
+use crate::utils::http::build_http_client;
+use crate::utils::json_store::{remove_state_file, state_file_path};
+use crate::utils::printer_storage::{load_printers, printers_file_path, save_printers_if_changed};
+use crate::utils::recent_jobs::RECENT_JOBS_FILE;
+use crate::utils::skipped_jobs::{SKIPPED_JOBS_FILE, SkippedJob, save_skipped_jobs};
+use crate::utils::status_queue::PENDING_STATUS_FILE;
+use crate::utils::temp_files::sweep_stale_temp_files;
 
 /// Command line arguments for the application
 #[derive(Parser)]
@@ -14,6 +44,14 @@ pub struct Cli {
     /// Enable verbose debug logging
     #[arg(short = 'v', long = "verbose", action = ArgAction::Count)]
     pub verbose: u8,
+
+    /// Exit instead of falling back to (and saving) defaults when the config file is invalid
+    #[arg(long = "strict-config", global = true)]
+    pub strict_config: bool,
+
+    /// Number of runtime worker threads, overriding `worker_threads` in the config
+    #[arg(long = "worker-threads", global = true)]
+    pub worker_threads: Option<usize>,
 }
 
 #[derive(Subcommand)]
@@ -21,8 +59,11 @@ pub enum Commands {
     /// Start the server normally
     Run,
 
-    /// Configure application settings using a text-based UI
-    Config,
+    /// Configure application settings using a text-based UI, or export/import them
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigCommand>,
+    },
 
     /// Print a file to a specified printer, or fetch and print a job from the API
     Print {
@@ -45,9 +86,167 @@ pub enum Commands {
 
     /// List available printers
     Printers,
+
+    /// List pending print jobs from the API, or the jobs this bridge skipped
+    ListJobs {
+        /// Include completed jobs
+        #[arg(short, long)]
+        all: bool,
+
+        /// List jobs left pending by quiet hours, max_job_age_hours or a deferral, with the reason
+        #[arg(long, conflicts_with = "all")]
+        skipped: bool,
+
+        /// List jobs reported to the API as failed (the dead-letter store), with the error
+        #[arg(long, conflicts_with_all = ["all", "skipped"])]
+        dead_letters: bool,
+
+        /// Output the jobs as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check GitHub for a newer release
+    CheckUpdate,
+
+    /// Print jobs again, ignoring quiet hours and max_job_age_hours
+    #[command(group(ArgGroup::new("target").required(true).args(["skipped", "job", "dead_letter"])))]
+    Reprint {
+        /// Re-run every job in the skipped list (see `list-jobs --skipped`)
+        #[arg(long)]
+        skipped: bool,
+
+        /// Retry one failed job from the dead-letter store (see `list-jobs --dead-letters`)
+        #[arg(long, value_name = "JOB_ID")]
+        dead_letter: Option<u32>,
+
+        /// Fetch and print one job by ID
+        #[arg(short, long)]
+        job: Option<u32>,
+
+        /// Print the job on this printer instead of the one it names (CUPS name or `__file__`)
+        #[arg(short, long, requires = "job")]
+        printer: Option<String>,
+    },
+
+    /// Release a job held in CUPS by `hold_jobs` so it prints
+    Release {
+        /// CUPS job id, as reported to the API in `cups_job_id`
+        cups_job_id: u64,
+
+        /// CUPS name of the printer holding the job (searches every printer if not specified)
+        #[arg(short, long)]
+        printer: Option<String>,
+    },
+
+    /// Load-test the print path with synthetic jobs against the file printer
+    #[command(hide = true)]
+    Bench {
+        /// Number of jobs to run
+        #[arg(long, default_value_t = 100)]
+        jobs: u32,
+
+        /// Jobs processed in parallel
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
+
+        /// Printer to use; only the file printer `__file__` is supported
+        #[arg(long, default_value = "__file__")]
+        printer: String,
+    },
+
+    /// Delete the local printer cache, job queues and temp files (keeps config.json)
+    Reset {
+        /// Also reset config.json to the default configuration
+        #[arg(long)]
+        all: bool,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Sync local printers with the API once and report the changes
+    SyncPrinters {
+        /// Only show which printers would be created, updated or deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compare printers.json, CUPS and the API, repair differences and report them
+    ReconcilePrinters,
+
+    /// Show the estimated pages printed per printer, from consumption.json
+    Consumption {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Subcommands of `config`; without one the text-based UI is started
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Write the configuration as JSON to stdout (API token and Reverb secret omitted)
+    Export {
+        /// Include the API token and the Reverb secret
+        #[arg(long)]
+        with_secrets: bool,
+    },
+
+    /// Validate a configuration exported with `config export` and save it
+    Import {
+        /// Path to the exported JSON file
+        file: String,
+    },
 }
 
 /// Build the tracing env filter based on verbosity level
+/// Build the tokio runtime that runs every task of the bridge.
+///
+/// `worker_threads` of `None` or 0 keeps the tokio default of one thread per CPU core.
+pub fn build_runtime(worker_threads: Option<usize>) -> std::io::Result<tokio::runtime::Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all();
+    if let Some(threads) = worker_threads.filter(|&n| n > 0) {
+        builder.worker_threads(threads);
+    }
+    builder.build()
+}
+
+/// Handle for replacing the log filter while the process is running
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Install the global subscriber with a reloadable filter for `verbose`
+pub fn init_logging(verbose: u8) -> LogFilterHandle {
+    let (filter, handle) = reload::Layer::new(build_env_filter(verbose));
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer());
+    #[cfg(feature = "otlp")]
+    let registry = registry.with(crate::utils::otlp::layer());
+    registry.init();
+    handle
+}
+
+/// Next verbosity when cycling the log level at runtime: info → debug → trace → info
+pub fn next_log_verbosity(verbose: u8) -> u8 {
+    match verbose {
+        0 | 1 => 2,
+        2 => 3,
+        _ => 1,
+    }
+}
+
+/// Name of the bridge's own log level for a verbosity
+pub fn log_level_name(verbose: u8) -> &'static str {
+    match verbose {
+        0 | 1 => "info",
+        2 => "debug",
+        _ => "trace",
+    }
+}
+
 pub fn build_env_filter(verbose: u8) -> EnvFilter {
     match verbose {
         0 => EnvFilter::from_default_env()
@@ -65,11 +264,43 @@ pub fn build_env_filter(verbose: u8) -> EnvFilter {
     }
 }
 
+/// Load the configuration for commands that talk to the API, exiting if no token is set
+pub fn require_api_config() -> Config {
+    let mut config = load_config();
+    apply_hostname_instance_name(&mut config);
+    if config.api_token().is_none() {
+        eprintln!(
+            "Error: No API token available from token_source '{}'. Run 'nuxbe-printer-bridge config' first.",
+            config.token_source
+        );
+        std::process::exit(1);
+    }
+    config
+}
+
+/// Build the shared HTTP client for CLI commands, exiting on invalid TLS settings
+pub fn require_http_client(config: &Config) -> reqwest::Client {
+    match build_http_client(config) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Build an API client for CLI commands from a configuration with a token
+pub fn require_flux_client(config: Config) -> FluxClient {
+    let http_client = require_http_client(&config);
+    FluxClient::new(http_client, config)
+}
+
 /// Print a local file to a printer
 pub fn print_local_file(
     file_path: &str,
     printer_name: Option<&str>,
     job_name: Option<&str>,
+    config: &Config,
 ) -> bool {
     if !Path::new(file_path).exists() {
         eprintln!("Error: File '{}' not found", file_path);
@@ -89,8 +320,7 @@ pub fn print_local_file(
             }
         }
     } else {
-        let mut printers = get_printers();
-        match printers.pop() {
+        match default_system_printer(config) {
             Some(p) => p,
             None => {
                 eprintln!("Error: No printers available");
@@ -99,13 +329,27 @@ pub fn print_local_file(
         }
     };
 
-    let job_name_str = job_name.unwrap_or("CLI Print Job");
+    let job_name_str = match job_name {
+        Some(name) => name.to_string(),
+        None => config.render_job_name(&[
+            ("job_id", "CLI"),
+            ("printer", &printer.name),
+            ("size", ""),
+            ("created_by", ""),
+            ("updated_by", ""),
+        ]),
+    };
     let job_options = PrinterJobOptions {
-        name: Some(job_name_str),
+        name: Some(&job_name_str),
         ..PrinterJobOptions::none()
     };
 
-    match printer.print_file(file_path, job_options) {
+    let result = printer
+        .print_file(file_path, job_options)
+        .map_err(|e| format!("{:?}", e))
+        .and_then(|job_id| validate_cups_job_id(job_id, &printer.name).map_err(|e| e.to_string()));
+
+    match result {
         Ok(job_id) => {
             println!("Print job submitted successfully");
             println!("  Printer: {}", printer.name);
@@ -114,7 +358,7 @@ pub fn print_local_file(
             true
         }
         Err(e) => {
-            eprintln!("Error: Failed to print file: {:?}", e);
+            eprintln!("Error: Failed to print file: {}", e);
             std::process::exit(1);
         }
     }
@@ -133,3 +377,600 @@ pub fn list_printers() {
         println!("  {} - {}", printer.name, printer.system_name);
     }
 }
+
+/// Release a held CUPS job, looking for it on `printer_name` or every printer
+pub fn release_held_job(cups_job_id: u64, printer_name: Option<&str>) {
+    let printers = match printer_name {
+        Some(name) => match get_printer_by_name(name) {
+            Some(p) => vec![p],
+            None => {
+                eprintln!("Error: Printer '{}' not found", name);
+                std::process::exit(1);
+            }
+        },
+        None => get_printers(),
+    };
+
+    let found = printers.into_iter().find_map(|printer| {
+        let state = printer
+            .get_active_jobs()
+            .into_iter()
+            .find(|job| job.id == cups_job_id)?
+            .state;
+        Some((printer, state))
+    });
+    let Some((printer, state)) = found else {
+        eprintln!("Error: No active CUPS job {} found", cups_job_id);
+        std::process::exit(1);
+    };
+    if state != PrinterJobState::PAUSED {
+        eprintln!(
+            "Error: CUPS job {} on '{}' is not held ({:?})",
+            cups_job_id, printer.name, state
+        );
+        std::process::exit(1);
+    }
+
+    match printer.resume_job(cups_job_id) {
+        Ok(()) => println!("Released CUPS job {} on '{}'", cups_job_id, printer.name),
+        Err(e) => {
+            eprintln!("Error: Failed to release CUPS job {}: {:?}", cups_job_id, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print a list of API print jobs as a table or as JSON
+pub fn print_job_list(jobs: &[PrintJob], json: bool) {
+    if json {
+        match serde_json::to_string_pretty(jobs) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("Error: Failed to serialize print jobs: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if jobs.is_empty() {
+        println!("No print jobs found");
+        return;
+    }
+
+    println!(
+        "{:<8} {:<30} {:<12} {:>8}  CREATED AT",
+        "ID", "PRINTER", "SIZE", "QUANTITY"
+    );
+    for job in jobs {
+        let printer = job
+            .printer
+            .as_ref()
+            .and_then(|p| p.name.clone())
+            .or_else(|| job.printer_id.map(|id| format!("#{}", id)))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<8} {:<30} {:<12} {:>8}  {}",
+            job.id, printer, job.size, job.quantity, job.created_at
+        );
+    }
+}
+
+/// Print the skipped jobs list as a table or as JSON
+pub fn print_skipped_jobs(skipped: &[SkippedJob], json: bool) {
+    if json {
+        match serde_json::to_string_pretty(skipped) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("Error: Failed to serialize skipped jobs: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if skipped.is_empty() {
+        println!("No skipped jobs");
+        return;
+    }
+
+    println!("{:<8} {:<20} REASON", "ID", "SKIPPED AT (UTC)");
+    for job in skipped {
+        println!(
+            "{:<8} {:<20} {}",
+            job.job_id,
+            format_unix_utc(job.skipped_at),
+            job.reason
+        );
+    }
+}
+
+/// Print the dead-letter store as a table or as JSON
+pub fn print_dead_letters(dead_letters: &[DeadLetter], json: bool) {
+    if json {
+        match serde_json::to_string_pretty(dead_letters) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("Error: Failed to serialize dead letters: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if dead_letters.is_empty() {
+        println!("No failed jobs");
+        return;
+    }
+
+    println!(
+        "{:<8} {:<20} {:<8} {:<10} ERROR",
+        "ID", "FAILED AT (UTC)", "ATTEMPTS", "CUPS JOB"
+    );
+    for letter in dead_letters {
+        println!(
+            "{:<8} {:<20} {:<8} {:<10} {}",
+            letter.job_id,
+            format_unix_utc(letter.failed_at),
+            letter.attempts,
+            letter
+                .cups_job_id
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            letter.error
+        );
+        if let Some(job) = &letter.job {
+            println!("{:<8} {}", "", dead_letter_job_summary(job));
+        }
+    }
+}
+
+/// Printer, media, size, quantity and print options of a failed job
+pub fn dead_letter_job_summary(job: &PrintJob) -> String {
+    let printer = job
+        .printer
+        .as_ref()
+        .and_then(|printer| printer.name.clone())
+        .or_else(|| job.printer_id.map(|id| format!("#{}", id)))
+        .unwrap_or_else(|| "default".to_string());
+    let media = if job.media_id == 0 {
+        "inline".to_string()
+    } else {
+        job.media_id.to_string()
+    };
+    let size = if job.size.is_empty() { "-" } else { &job.size };
+    let mut parts = vec![
+        format!("printer {}", printer),
+        format!("media {}", media),
+        format!("size {}", size),
+        format!("quantity {}", job.quantity.max(1)),
+    ];
+    let options = [
+        ("orientation", job.orientation.clone()),
+        ("scaling", job.scaling.clone()),
+        ("number-up", job.number_up.map(|n| n.to_string())),
+        ("media-type", job.media_type.clone()),
+        ("pages", job.page_ranges.clone()),
+    ];
+    for (name, value) in options {
+        if let Some(value) = value {
+            parts.push(format!("{} {}", name, value));
+        }
+    }
+    parts.join(", ")
+}
+
+/// Print the per-printer consumption totals as a table or as JSON
+pub fn print_consumption(totals: &HashMap<String, PrinterConsumption>, json: bool) {
+    if json {
+        match serde_json::to_string_pretty(totals) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("Error: Failed to serialize consumption totals: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if totals.is_empty() {
+        println!("No jobs recorded");
+        return;
+    }
+
+    let mut printers: Vec<_> = totals.iter().collect();
+    printers.sort_by(|a, b| a.0.cmp(b.0));
+    println!("{:<30} {:>8} {:>10}", "PRINTER", "JOBS", "PAGES");
+    for (system_name, consumption) in printers {
+        println!(
+            "{:<30} {:>8} {:>10}",
+            system_name, consumption.jobs, consumption.pages
+        );
+    }
+}
+
+/// Lift quiet hours and `max_job_age_hours` for jobs an operator re-runs by hand
+fn reprint_config(mut config: Config) -> Config {
+    config.quiet_hours = None;
+    config.max_job_age_hours = 0;
+    config
+}
+
+/// Force-run one job, optionally on `printer_name` instead of its own printer,
+/// and report where it went. Exits with a non-zero status if it failed.
+pub async fn reprint_job(config: Config, job_id: u32, printer_name: Option<&str>) {
+    let printer_name = printer_name.map(|name| {
+        if name == FILE_PRINTER_NAME {
+            return name.to_string();
+        }
+        match get_printer_by_name(name) {
+            Some(p) => p.system_name,
+            None => {
+                eprintln!("Error: Printer '{}' not found", name);
+                std::process::exit(1);
+            }
+        }
+    });
+    let api = require_flux_client(reprint_config(config));
+    let state = AppState::load();
+
+    let result = match &printer_name {
+        Some(name) => fetch_and_print_job_on_printer(job_id, name, &api, &state).await,
+        None => fetch_and_print_job_by_id(job_id, &api, &state).await,
+    };
+    if let Err(e) = result {
+        eprintln!("Job {}: {}", job_id, e);
+        std::process::exit(1);
+    }
+
+    let submitted = state
+        .in_flight_jobs
+        .lock()
+        .expect("Failed to acquire in_flight_jobs lock")
+        .iter()
+        .find(|job| job.api_job_id == job_id)
+        .map(|job| (job.printer_name.clone(), job.cups_job_id));
+    let skipped = state
+        .skipped_jobs
+        .lock()
+        .expect("Failed to acquire skipped_jobs lock")
+        .iter()
+        .find(|job| job.job_id == job_id)
+        .map(|job| job.reason.clone());
+    match (submitted, skipped) {
+        (Some((printer, cups_job_id)), _) => println!(
+            "Job {}: sent to '{}' (CUPS job {})",
+            job_id, printer, cups_job_id
+        ),
+        (None, Some(reason)) => println!("Job {}: not printed, {}", job_id, reason),
+        (None, None) if printer_name.as_deref() == Some(FILE_PRINTER_NAME) => {
+            println!("Job {}: captured by the file printer", job_id)
+        }
+        (None, None) => println!("Job {}: done", job_id),
+    }
+}
+
+/// Retry one job from the dead-letter store, with quiet hours and
+/// `max_job_age_hours` lifted.
+///
+/// Exits with a non-zero status if the job is not in the store or fails again.
+pub async fn retry_dead_letter_job(config: Config, job_id: u32) {
+    let api = require_flux_client(reprint_config(config));
+    let state = AppState::load();
+
+    let is_listed = state
+        .dead_letters
+        .lock()
+        .expect("Failed to acquire dead_letters lock")
+        .iter()
+        .any(|letter| letter.job_id == job_id);
+    if !is_listed {
+        eprintln!(
+            "Error: Job {} is not in the dead-letter store (see `list-jobs --dead-letters`)",
+            job_id
+        );
+        std::process::exit(1);
+    }
+
+    if let Err(e) = retry_dead_letter(job_id, &api, &state).await {
+        eprintln!("Job {}: {}", job_id, e);
+        std::process::exit(1);
+    }
+
+    let submitted = state
+        .in_flight_jobs
+        .lock()
+        .expect("Failed to acquire in_flight_jobs lock")
+        .iter()
+        .find(|job| job.api_job_id == job_id)
+        .map(|job| (job.printer_name.clone(), job.cups_job_id));
+    match submitted {
+        Some((printer, cups_job_id)) => println!(
+            "Job {}: sent to '{}' (CUPS job {})",
+            job_id, printer, cups_job_id
+        ),
+        None => println!("Job {}: done", job_id),
+    }
+}
+
+/// Force-run every skipped job, with quiet hours and `max_job_age_hours` lifted.
+///
+/// Each job leaves the list before it runs; one that is deferred again is
+/// listed anew. Exits with a non-zero status if any job failed.
+pub async fn reprint_skipped_jobs(config: Config) {
+    let api = require_flux_client(reprint_config(config));
+    let state = AppState::load();
+
+    let job_ids: Vec<u32> = {
+        let mut skipped = state
+            .skipped_jobs
+            .lock()
+            .expect("Failed to acquire skipped_jobs lock");
+        let ids = skipped.iter().map(|job| job.job_id).collect();
+        skipped.clear();
+        save_skipped_jobs(&skipped);
+        ids
+    };
+    if job_ids.is_empty() {
+        println!("No skipped jobs");
+        return;
+    }
+
+    let mut failed = 0;
+    for job_id in &job_ids {
+        match fetch_and_print_job_by_id(*job_id, &api, &state).await {
+            Ok(()) => println!("Job {}: done", job_id),
+            Err(e) => {
+                failed += 1;
+                eprintln!("Job {}: {}", job_id, e);
+            }
+        }
+    }
+
+    let still_skipped = state
+        .skipped_jobs
+        .lock()
+        .expect("Failed to acquire skipped_jobs lock")
+        .len();
+    println!(
+        "Re-ran {} jobs ({} failed, {} skipped again)",
+        job_ids.len(),
+        failed,
+        still_skipped
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Run a single printer sync against the API and print what changed.
+///
+/// Exits with a non-zero status if the API rejected any printer write.
+pub async fn sync_printers(api: &FluxClient, dry_run: bool) {
+    let saved_printers = load_printers();
+    let local_printers = get_local_printers(&saved_printers, api.config(), false).await;
+
+    let report =
+        match sync_printers_with_api(&local_printers, &saved_printers, api, dry_run, false).await {
+            Ok((printers, report)) => {
+                if !dry_run {
+                    save_printers_if_changed(&printers, &saved_printers);
+                }
+                report
+            }
+            Err(e) => {
+                eprintln!("Error: Failed to sync printers: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+    print_sync_report(&report, dry_run);
+    if !report.failed.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Print the printers a sync created, updated and deleted
+/// Run the printer consistency check once and print what was repaired.
+///
+/// Exits with status 1 if the check failed or differences remain.
+pub async fn reconcile_printers(api: &FluxClient) {
+    let state = AppState::load();
+    let report = match check_printer_consistency(&state, api, false).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if report.fixed.is_empty() && report.unresolved.is_empty() {
+        println!("Printer state is consistent");
+        return;
+    }
+    if !report.fixed.is_empty() {
+        println!("Repaired:");
+        for discrepancy in &report.fixed {
+            println!("  - {}", discrepancy);
+        }
+    }
+    if !report.unresolved.is_empty() {
+        println!("Not repaired:");
+        for discrepancy in &report.unresolved {
+            println!("  - {}", discrepancy);
+        }
+        std::process::exit(1);
+    }
+}
+
+fn print_sync_report(report: &SyncReport, dry_run: bool) {
+    if report.is_empty() {
+        println!("Printers are already in sync");
+        return;
+    }
+
+    let sections = [
+        ("create", "Created", "+", &report.created),
+        ("update", "Updated", "~", &report.updated),
+        ("delete", "Deleted", "-", &report.deleted),
+    ];
+    for (verb, past, marker, names) in sections {
+        if names.is_empty() {
+            continue;
+        }
+        if dry_run {
+            println!("Would {} {} printer(s):", verb, names.len());
+        } else {
+            println!("{} {} printer(s):", past, names.len());
+        }
+        for name in names {
+            println!("  {} {}", marker, name);
+        }
+    }
+
+    if !report.failed.is_empty() {
+        eprintln!("Failed to sync {} printer(s):", report.failed.len());
+        for (name, reason) in &report.failed {
+            eprintln!("  ! {}: {}", name, reason);
+        }
+    }
+}
+
+/// Check for a newer release and print the result
+pub async fn check_update(http_client: &reqwest::Client) {
+    match check_for_update(http_client).await {
+        Ok(Some(release)) => {
+            println!(
+                "A newer version is available: {} (installed: {})",
+                release.tag_name,
+                env!("CARGO_PKG_VERSION")
+            );
+            println!("  {}", release.html_url);
+        }
+        Ok(None) => println!(
+            "nuxbe-printer-bridge {} is up to date",
+            env!("CARGO_PKG_VERSION")
+        ),
+        Err(e) => {
+            eprintln!("Error: Failed to check for updates: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write the configuration as JSON to stdout, omitting secrets unless `with_secrets`
+pub fn export_config(config: &Config, with_secrets: bool) {
+    let exported = if with_secrets {
+        config.clone()
+    } else {
+        config.without_secrets()
+    };
+    match serde_json::to_string_pretty(&exported) {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("Error: Failed to serialize config: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Validate an exported configuration file and save it as the current config.
+///
+/// Nothing is written if the file cannot be read, parsed or validated.
+pub fn import_config_file(path: &str) {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Error: Failed to read {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+
+    match import_config(&contents, &load_config()) {
+        Ok(config) => {
+            save_config(&config);
+            println!(
+                "Imported configuration for instance '{}' into {}",
+                config.instance_name,
+                config_path().display()
+            );
+        }
+        Err(e) => {
+            eprintln!("Error: Invalid configuration in {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// State files removed by `reset`, plus `config.json` with `include_config`
+pub fn local_state_files(include_config: bool) -> Vec<PathBuf> {
+    let mut files = vec![
+        printers_file_path(),
+        state_file_path(PENDING_STATUS_FILE),
+        state_file_path(PENDING_FETCH_FILE),
+        state_file_path(RECENT_JOBS_FILE),
+        state_file_path(SKIPPED_JOBS_FILE),
+        state_file_path(FINISHED_JOBS_FILE),
+        state_file_path(DEAD_LETTERS_FILE),
+        state_file_path(CONSUMPTION_FILE),
+    ];
+    if include_config {
+        files.push(config_path());
+    }
+    files
+}
+
+/// Delete the local state after confirmation, optionally resetting the config too.
+///
+/// Exits with a non-zero status if a file could not be removed.
+pub fn reset_local_state(all: bool, yes: bool) {
+    let files = local_state_files(all);
+
+    println!("This deletes:");
+    for file in &files {
+        println!("  {}", file.display());
+    }
+    println!(
+        "  bridge temp files older than an hour in {}",
+        std::env::temp_dir().display()
+    );
+    if all {
+        println!("and resets the configuration to the defaults.");
+    }
+
+    if !yes {
+        print!("Continue? [y/N] ");
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).is_err()
+            || !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+        {
+            println!("Aborted, nothing was deleted");
+            return;
+        }
+    }
+
+    let mut failed = false;
+    for file in &files {
+        match remove_state_file(file) {
+            Ok(true) => println!("Deleted {}", file.display()),
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Error: Failed to delete {}: {}", file.display(), e);
+                failed = true;
+            }
+        }
+    }
+    let removed = sweep_stale_temp_files();
+    if removed > 0 {
+        println!("Deleted {} temp file(s)", removed);
+    }
+    if all {
+        save_config(&Config::default());
+        println!("Configuration reset to defaults");
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
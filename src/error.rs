@@ -16,6 +16,10 @@ pub enum SpoolerError {
     Print(String),
     /// Configuration errors
     Config(String),
+    /// Job rejected for a permanent reason (reported to the API as failed)
+    Rejected(String),
+    /// Job postponed because the printer cannot take it right now (left pending)
+    Deferred(String),
 }
 
 impl fmt::Display for SpoolerError {
@@ -27,6 +31,8 @@ impl fmt::Display for SpoolerError {
             SpoolerError::Io(e) => write!(f, "I/O error: {}", e),
             SpoolerError::Print(msg) => write!(f, "Print error: {}", msg),
             SpoolerError::Config(msg) => write!(f, "Configuration error: {}", msg),
+            SpoolerError::Rejected(msg) => write!(f, "Job rejected: {}", msg),
+            SpoolerError::Deferred(msg) => write!(f, "Job deferred: {}", msg),
         }
     }
 }
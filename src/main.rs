@@ -1,30 +1,53 @@
 use clap::Parser;
 
+mod bench;
 mod cli;
 mod error;
 mod models;
 mod server;
 mod services;
+mod state;
 mod tests;
 mod utils;
 
-use cli::{Cli, Commands, build_env_filter, list_printers, print_local_file};
+use bench::run_bench;
+use cli::{
+    Cli, Commands, ConfigCommand, LogFilterHandle, build_runtime, check_update, export_config,
+    import_config_file, init_logging, list_printers, print_consumption, print_dead_letters,
+    print_job_list, print_local_file, print_skipped_jobs, reconcile_printers, release_held_job,
+    reprint_job, reprint_skipped_jobs, require_api_config, require_flux_client,
+    require_http_client, reset_local_state, retry_dead_letter_job, sync_printers,
+};
 use server::run_server;
-use services::print_job::{fetch_and_print_job_by_id, new_in_flight_jobs};
-use utils::config::load_config;
+use services::print_job::fetch_and_print_job_by_id;
+use state::AppState;
+use utils::config::{configured_worker_threads, load_config, set_strict_config};
 use utils::tui::run_tui;
 
-#[tokio::main]
-async fn main() -> std::io::Result<()> {
+fn main() -> std::io::Result<()> {
     let cli = Cli::parse();
 
-    tracing_subscriber::fmt()
-        .with_env_filter(build_env_filter(cli.verbose))
-        .init();
+    let log_filter = init_logging(cli.verbose);
 
+    set_strict_config(cli.strict_config);
+
+    let worker_threads = cli.worker_threads.or_else(configured_worker_threads);
+    let result = build_runtime(worker_threads)?.block_on(run(cli, log_filter));
+    #[cfg(feature = "otlp")]
+    utils::otlp::shutdown();
+    result
+}
+
+async fn run(cli: Cli, log_filter: LogFilterHandle) -> std::io::Result<()> {
     match cli.command {
-        Some(Commands::Config) => {
-            run_tui();
+        Some(Commands::Config { action }) => {
+            match action {
+                Some(ConfigCommand::Export { with_secrets }) => {
+                    export_config(&load_config(), with_secrets)
+                }
+                Some(ConfigCommand::Import { file }) => import_config_file(&file),
+                None => run_tui(),
+            }
             Ok(())
         }
         Some(Commands::Print {
@@ -35,19 +58,9 @@ async fn main() -> std::io::Result<()> {
         }) => {
             if let Some(job_id) = job {
                 // Fetch and print job from API
-                let config = load_config();
-                if config.flux_api_token.is_none() {
-                    eprintln!(
-                        "Error: No API token configured. Run 'nuxbe-printer-bridge config' first."
-                    );
-                    std::process::exit(1);
-                }
-
-                let http_client = reqwest::Client::new();
-                let in_flight_jobs = new_in_flight_jobs();
-                match fetch_and_print_job_by_id(job_id, &http_client, &config, &in_flight_jobs)
-                    .await
-                {
+                let api = require_flux_client(require_api_config());
+                let state = AppState::load();
+                match fetch_and_print_job_by_id(job_id, &api, &state).await {
                     Ok(_) => {}
                     Err(e) => {
                         eprintln!("Error: {}", e);
@@ -56,7 +69,8 @@ async fn main() -> std::io::Result<()> {
                 }
             } else if let Some(ref file_path) = file {
                 // Print local file
-                print_local_file(file_path, printer.as_deref(), job_name.as_deref());
+                let config = load_config();
+                print_local_file(file_path, printer.as_deref(), job_name.as_deref(), &config);
             }
             Ok(())
         }
@@ -64,6 +78,107 @@ async fn main() -> std::io::Result<()> {
             list_printers();
             Ok(())
         }
-        _ => run_server(cli.verbose >= 3).await,
+        Some(Commands::ListJobs {
+            dead_letters: true,
+            json,
+            ..
+        }) => {
+            let dead_letters: Vec<_> = AppState::load()
+                .dead_letters
+                .lock()
+                .expect("Failed to acquire dead_letters lock")
+                .iter()
+                .cloned()
+                .collect();
+            print_dead_letters(&dead_letters, json);
+            Ok(())
+        }
+        Some(Commands::ListJobs {
+            skipped: true,
+            json,
+            ..
+        }) => {
+            let skipped: Vec<_> = AppState::load()
+                .skipped_jobs
+                .lock()
+                .expect("Failed to acquire skipped_jobs lock")
+                .iter()
+                .cloned()
+                .collect();
+            print_skipped_jobs(&skipped, json);
+            Ok(())
+        }
+        Some(Commands::ListJobs { all, json, .. }) => {
+            let api = require_flux_client(require_api_config());
+            match api.list_jobs(all).await {
+                Ok(jobs) => print_job_list(&jobs, json),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            Ok(())
+        }
+        Some(Commands::CheckUpdate) => {
+            let config = load_config();
+            let http_client = require_http_client(&config);
+            check_update(&http_client).await;
+            Ok(())
+        }
+        Some(Commands::Reprint {
+            job: Some(job_id),
+            printer,
+            ..
+        }) => {
+            reprint_job(require_api_config(), job_id, printer.as_deref()).await;
+            Ok(())
+        }
+        Some(Commands::Reprint {
+            dead_letter: Some(job_id),
+            ..
+        }) => {
+            retry_dead_letter_job(require_api_config(), job_id).await;
+            Ok(())
+        }
+        Some(Commands::Reprint { .. }) => {
+            reprint_skipped_jobs(require_api_config()).await;
+            Ok(())
+        }
+        Some(Commands::Release {
+            cups_job_id,
+            printer,
+        }) => {
+            release_held_job(cups_job_id, printer.as_deref());
+            Ok(())
+        }
+        Some(Commands::Bench {
+            jobs,
+            concurrency,
+            printer,
+        }) => run_bench(jobs, concurrency, &printer, cli.verbose, &log_filter).await,
+        Some(Commands::Reset { all, yes }) => {
+            reset_local_state(all, yes);
+            Ok(())
+        }
+        Some(Commands::Consumption { json }) => {
+            let totals = AppState::load()
+                .consumption
+                .lock()
+                .expect("Failed to acquire consumption lock")
+                .clone();
+            print_consumption(&totals, json);
+            Ok(())
+        }
+        Some(Commands::ReconcilePrinters) => {
+            let api = require_flux_client(require_api_config());
+            reconcile_printers(&api).await;
+            Ok(())
+        }
+        Some(Commands::SyncPrinters { dry_run }) => {
+            let api = require_flux_client(require_api_config());
+            sync_printers(&api, dry_run).await;
+            Ok(())
+        }
+        _ => run_server(cli.verbose, log_filter).await,
     }
 }
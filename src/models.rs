@@ -1,7 +1,11 @@
+use std::collections::HashMap;
 use std::fmt;
 
+use chrono::{DateTime, FixedOffset, Timelike};
+use chrono_tz::Tz;
 use printers::common::base::job::PrinterJobState;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 pub mod api;
 
@@ -14,6 +18,8 @@ pub enum PrintJobStatus {
     Completed,
     Failed,
     Cancelled,
+    /// Submitted with `hold_jobs` and waiting in CUPS to be released
+    Held,
 }
 
 impl fmt::Display for PrintJobStatus {
@@ -24,6 +30,7 @@ impl fmt::Display for PrintJobStatus {
             PrintJobStatus::Completed => write!(f, "completed"),
             PrintJobStatus::Failed => write!(f, "failed"),
             PrintJobStatus::Cancelled => write!(f, "cancelled"),
+            PrintJobStatus::Held => write!(f, "held"),
         }
     }
 }
@@ -50,14 +57,25 @@ impl PrintJobStatus {
     }
 }
 
+/// `instance_name` of a fresh configuration
+pub const DEFAULT_INSTANCE_NAME: &str = "default-instance";
+
+/// Smallest `max_printer_name_length`, leaving room for a prefix besides the hash suffix
+pub const MIN_PRINTER_NAME_LENGTH: usize = 16;
+
 /// Configuration structure for the application
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct Config {
     pub instance_name: String,
+    /// Use the machine's hostname while `instance_name` is left at `default-instance`
+    pub instance_name_from_hostname: bool,
     pub printer_check_interval: u64,
     pub job_check_interval: u64,
     pub flux_url: String,
     pub flux_api_token: Option<String>,
+    /// Where the API token comes from: `inline` (`flux_api_token`), `file:<path>` or `env:<VAR>`
+    pub token_source: String,
     pub api_port: u16,
     pub reverb_disabled: bool,
     pub reverb_app_id: String,
@@ -65,17 +83,439 @@ pub struct Config {
     pub reverb_app_secret: String,
     pub reverb_use_tls: bool,
     pub reverb_host: Option<String>,
+    /// Port of a self-hosted Reverb server; by default derived from `reverb_use_tls` (443/80)
+    pub reverb_port: Option<u16>,
     pub reverb_auth_endpoint: String,
+    /// PEM client certificate presented to the API for mutual TLS
+    pub client_cert_path: Option<String>,
+    /// PKCS#8 PEM private key belonging to `client_cert_path`
+    pub client_key_path: Option<String>,
+    /// PEM CA certificate trusted in addition to the system roots (e.g. for self-signed servers)
+    pub tls_ca_cert: Option<String>,
+    /// Skip TLS certificate validation entirely (dangerous, explicit opt-in)
+    pub tls_accept_invalid_certs: bool,
+    /// Maximum size of a downloaded document in megabytes (0 = unlimited)
+    pub max_media_size_mb: u64,
+    /// Maximum size of an API response body read for parsing, in megabytes (0 = unlimited)
+    pub max_api_response_mb: u64,
+    /// Name given to CUPS jobs; supports `{job_id}`, `{printer}`, `{size}`, `{instance}`,
+    /// `{created_by}` and `{updated_by}`
+    pub job_name_template: String,
+    /// Name CUPS jobs after the downloaded file's `Content-Disposition` file name when it has one
+    pub job_name_from_filename: bool,
+    /// Number of printers drained in parallel when catching up on jobs after reconnecting
+    pub catch_up_concurrency: usize,
+    /// Tenant identifier sent with every API request for multi-tenant instances
+    pub tenant: Option<String>,
+    /// Header carrying `tenant`
+    pub tenant_header: String,
+    /// Query parameter that additionally carries `tenant`, for APIs that expect it there
+    pub tenant_query_param: Option<String>,
+    /// Print a banner page (job id, printer, timestamp) before every API job
+    pub separator_page: bool,
+    /// API filter that scopes job lists to `instance_name` (empty = no filter)
+    pub job_spooler_filter: String,
+    /// Periodically check GitHub for a newer release and log it (never installs)
+    pub update_check_enabled: bool,
+    /// Physical passes per logical copy for sizes with multi-pass sheet layouts
+    pub size_copy_multipliers: HashMap<String, u32>,
+    /// Defer jobs while the target printer has this many active CUPS jobs (0 = no limit)
+    pub max_queue_depth: usize,
+    /// Per-printer print option defaults keyed by CUPS `system_name`
+    pub printer_overrides: HashMap<String, PrinterOverrides>,
+    /// Location of each user (by API user id) for jobs without a printer or location
+    pub user_locations: HashMap<u32, String>,
+    /// How a job location is compared with printer locations
+    pub location_match: LocationMatch,
+    /// Whether the API token is sent along when a download redirects to another host
+    pub media_redirect_auth: MediaRedirectAuth,
+    /// Longest polling interval (minutes) reached by backing off after empty polls
+    pub job_check_max_interval: u64,
+    /// Print a header page with the job's user, quantity and creation time before each API job
+    pub stamp_metadata: bool,
+    /// Only apply status updates to jobs the API still has as not completed
+    pub conditional_status_updates: bool,
+    /// Runtime worker threads (`None` = one per CPU core); `--worker-threads` takes precedence
+    pub worker_threads: Option<usize>,
+    /// Printer (CUPS name or `system_name`) used when a job or CLI call names none
+    pub default_printer: Option<String>,
+    /// Printers tried in order when a job's printer is missing or not ready (empty = `default_printer`)
+    pub fallback_printers: Vec<String>,
+    /// OTLP/HTTP collector receiving the bridge's spans, e.g. `http://collector:4318`
+    /// (None = off; needs the `otlp` cargo feature)
+    pub otlp_endpoint: Option<String>,
+    /// Run `pre_print_command` on every document before it is printed
+    pub pre_print_command_enabled: bool,
+    /// Command transforming a document, e.g. `rotate-pdf {input} {output}` (stdout is used without `{output}`)
+    pub pre_print_command: Option<String>,
+    /// Seconds `pre_print_command` may run before the job fails
+    pub pre_print_timeout_secs: u64,
+    /// Send the CUPS printer description to the API when creating and updating printers
+    pub sync_printer_description: bool,
+    /// Field names and values used when reporting a job's status to the API
+    pub status_fields: StatusFieldMapping,
+    /// API requests in flight at once across all tasks (0 = unlimited); read at startup
+    pub max_concurrent_api_requests: usize,
+    /// `media-type` values a job or printer override may request (empty = any)
+    pub allowed_media_types: Vec<String>,
+    /// Completed job ids remembered across restarts so they are never printed twice (0 = off)
+    pub recent_completed_jobs: usize,
+    /// Directory receiving the documents of jobs sent to the `__file__` printer (None = off)
+    pub file_printer_dir: Option<String>,
+    /// Smallest document accepted for printing in bytes; smaller ones fail the job (0 = no minimum)
+    pub min_media_size_bytes: u64,
+    /// Daily window in which only urgent jobs are printed; others wait until it ends (None = off)
+    pub quiet_hours: Option<QuietHours>,
+    /// Seconds to wait for a known but offline printer before falling back or deferring (0 = no wait)
+    pub printer_ready_wait_secs: u64,
+    /// Jobs created more than this many hours ago are not printed (0 = no limit)
+    pub max_job_age_hours: u64,
+    /// What happens to a job older than `max_job_age_hours`
+    pub stale_job_action: StaleJobAction,
+    /// Minutes between passes that compare finished jobs with the CUPS history (0 = off)
+    pub reconcile_interval_mins: u64,
+    /// What happens to a job whose `printer_id` is not in `printers.json`
+    pub unknown_printer_id_behavior: UnknownPrinterIdBehavior,
+    /// Longest printer name the API accepts; longer names get a hash suffix (0 = unlimited)
+    pub max_printer_name_length: usize,
+    /// Submit jobs held in CUPS until released with `release` and report them as `held`
+    pub hold_jobs: bool,
+    /// Create FLUX printers for local printers the API does not have yet
+    pub auto_create_printers: bool,
+    /// Delete the FLUX printer when its local printer is removed from CUPS
+    pub auto_delete_printers: bool,
+    /// Printers reached over IPP instead of CUPS, by name, with their `ipp://` or `ipps://` URI
+    pub ipp_printers: HashMap<String, String>,
+}
+
+/// Handling of jobs for a printer id this bridge does not know
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownPrinterIdBehavior {
+    /// Use the printer name from the job, or the default printer
+    #[default]
+    Fallback,
+    /// Mark the job failed instead of risking the wrong printer
+    Fail,
+    /// Sync printers with the API and resolve again; fail if the id is still unknown
+    #[serde(alias = "resync-then-retry")]
+    ResyncThenRetry,
+}
+
+impl fmt::Display for UnknownPrinterIdBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnknownPrinterIdBehavior::Fallback => write!(f, "fallback"),
+            UnknownPrinterIdBehavior::Fail => write!(f, "fail"),
+            UnknownPrinterIdBehavior::ResyncThenRetry => write!(f, "resync_then_retry"),
+        }
+    }
+}
+
+/// Handling of jobs older than `max_job_age_hours`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleJobAction {
+    /// Leave the job pending in the API without printing it
+    #[default]
+    Skip,
+    /// Mark the job failed in the API with an explanation
+    Fail,
+}
+
+/// Handling of the API token when a request is redirected to a different host
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaRedirectAuth {
+    /// Follow the redirect without the `Authorization` header (signed URLs)
+    #[default]
+    Strip,
+    /// Re-issue media downloads to the new host with the `Authorization` header
+    Forward,
+}
+
+/// Strategy for matching a job location against `Printer::location`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LocationMatch {
+    /// Locations are equal, ignoring case and surrounding whitespace
+    #[default]
+    Exact,
+    /// The printer location contains the job location, ignoring case
+    Contains,
+}
+
+impl LocationMatch {
+    /// Whether a printer at `printer_location` serves `location`
+    pub fn matches(self, printer_location: &str, location: &str) -> bool {
+        let printer_location = printer_location.trim().to_lowercase();
+        let location = location.trim().to_lowercase();
+        if location.is_empty() {
+            return false;
+        }
+        match self {
+            LocationMatch::Exact => printer_location == location,
+            LocationMatch::Contains => printer_location.contains(&location),
+        }
+    }
+}
+
+/// How job status updates are encoded for the API
+///
+/// Defaults match current FLUX versions: `{"is_completed": true, "status": "completed"}`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct StatusFieldMapping {
+    /// Boolean field set once the job reached a final status (empty = not sent)
+    pub completed_field: String,
+    /// Field carrying the status value (empty = not sent)
+    pub status_field: String,
+    /// Value sent per status (`queued`, `processing`, `completed`, `failed`,
+    /// `cancelled`); statuses not listed are sent under their own name
+    pub status_values: HashMap<String, String>,
+}
+
+impl Default for StatusFieldMapping {
+    fn default() -> Self {
+        StatusFieldMapping {
+            completed_field: "is_completed".to_string(),
+            status_field: "status".to_string(),
+            status_values: HashMap::new(),
+        }
+    }
+}
+
+/// Status names accepted as keys of [`StatusFieldMapping::status_values`]
+const STATUS_NAMES: [&str; 5] = ["queued", "processing", "completed", "failed", "cancelled"];
+
+/// Fields of a status update that a mapped field name must not replace
+const RESERVED_STATUS_FIELDS: [&str; 5] = [
+    "id",
+    "cups_job_id",
+    "error_message",
+    "printed_at",
+    "expected_is_completed",
+];
+
+impl StatusFieldMapping {
+    /// Value sent in `status_field` for `status`
+    pub fn value_for(&self, status: &PrintJobStatus) -> String {
+        let name = status.to_string();
+        self.status_values.get(&name).cloned().unwrap_or(name)
+    }
+
+    /// Problems with the mapping, prefixed with `status_fields.`
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let completed = self.completed_field.trim();
+        let status = self.status_field.trim();
+
+        if completed.is_empty() && status.is_empty() {
+            problems.push(
+                "status_fields: completed_field and status_field cannot both be empty".to_string(),
+            );
+        }
+        if !completed.is_empty() && completed == status {
+            problems
+                .push("status_fields: completed_field and status_field must differ".to_string());
+        }
+        for field in [completed, status] {
+            if RESERVED_STATUS_FIELDS.contains(&field) {
+                problems.push(format!(
+                    "status_fields: '{}' is already part of status updates",
+                    field
+                ));
+            }
+        }
+        for (name, value) in &self.status_values {
+            if !STATUS_NAMES.contains(&name.as_str()) {
+                problems.push(format!(
+                    "status_fields.status_values: unknown status '{}' (expected one of {})",
+                    name,
+                    STATUS_NAMES.join(", ")
+                ));
+            } else if value.trim().is_empty() {
+                problems.push(format!(
+                    "status_fields.status_values.{} must not be empty",
+                    name
+                ));
+            }
+        }
+        problems
+    }
+}
+
+/// Daily quiet-hours window, in local time given as an offset from UTC or an
+/// IANA time zone
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct QuietHours {
+    /// Start of the window, `HH:MM`
+    pub start: String,
+    /// End of the window, `HH:MM`; earlier than `start` for windows spanning midnight
+    pub end: String,
+    /// Local time of `start` and `end`; configs written before the rename use
+    /// `utc_offset`
+    #[serde(alias = "utc_offset")]
+    pub timezone: QuietHoursZone,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        QuietHours {
+            start: "22:00".to_string(),
+            end: "06:00".to_string(),
+            timezone: QuietHoursZone::from("+00:00"),
+        }
+    }
+}
+
+impl QuietHours {
+    /// Whether `unix_secs` falls inside the window; an invalid window never does
+    pub fn is_quiet_at(&self, unix_secs: u64) -> bool {
+        let (Some(start), Some(end), Some(local_minute)) = (
+            parse_clock_time(&self.start),
+            parse_clock_time(&self.end),
+            self.timezone.minute_of_day_at(unix_secs),
+        ) else {
+            return false;
+        };
+        if start <= end {
+            (start..end).contains(&local_minute)
+        } else {
+            local_minute >= start || local_minute < end
+        }
+    }
+
+    /// Problems with the window, prefixed with `quiet_hours.`
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (field, value) in [("start", &self.start), ("end", &self.end)] {
+            if parse_clock_time(value).is_none() {
+                problems.push(format!(
+                    "quiet_hours.{} must be a time like 22:00, got '{}'",
+                    field, value
+                ));
+            }
+        }
+        if self.timezone.zone.is_none() {
+            problems.push(format!(
+                "quiet_hours.timezone must be an offset like +01:00 or a time zone like Europe/Berlin, got '{}'",
+                self.timezone.name
+            ));
+        }
+        problems
+    }
+}
+
+/// Local time of a quiet-hours window: a fixed offset from UTC (`+HH:MM` or
+/// `-HH:MM`) or an IANA time zone such as `Europe/Berlin`, which follows
+/// daylight saving time.
+///
+/// Parsed once when the config is read; the text is kept as written so an
+/// invalid value is reported by `validate` and saved back unchanged.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "String", into = "String")]
+pub struct QuietHoursZone {
+    name: String,
+    /// `None` when `name` is neither an offset nor a known time zone
+    zone: Option<LocalZone>,
+}
+
+#[derive(Clone, Debug)]
+enum LocalZone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl QuietHoursZone {
+    /// Minutes after local midnight at `unix_secs`; `None` for an invalid zone
+    fn minute_of_day_at(&self, unix_secs: u64) -> Option<u32> {
+        let utc = DateTime::from_timestamp(i64::try_from(unix_secs).ok()?, 0)?;
+        let (hour, minute) = match self.zone.as_ref()? {
+            LocalZone::Fixed(offset) => {
+                let local = utc.with_timezone(offset);
+                (local.hour(), local.minute())
+            }
+            LocalZone::Named(tz) => {
+                let local = utc.with_timezone(tz);
+                (local.hour(), local.minute())
+            }
+        };
+        Some(hour * 60 + minute)
+    }
+}
+
+impl From<String> for QuietHoursZone {
+    fn from(name: String) -> Self {
+        let value = name.trim();
+        let zone = match parse_utc_offset(value) {
+            Some(secs) => FixedOffset::east_opt(secs).map(LocalZone::Fixed),
+            None => value.parse::<Tz>().ok().map(LocalZone::Named),
+        };
+        QuietHoursZone { name, zone }
+    }
+}
+
+impl From<&str> for QuietHoursZone {
+    fn from(name: &str) -> Self {
+        QuietHoursZone::from(name.to_string())
+    }
+}
+
+impl From<QuietHoursZone> for String {
+    fn from(zone: QuietHoursZone) -> Self {
+        zone.name
+    }
+}
+
+/// Minutes after midnight for `HH:MM`
+fn parse_clock_time(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Seconds east of UTC for `+HH:MM` / `-HH:MM`
+fn parse_utc_offset(value: &str) -> Option<i32> {
+    let (sign, rest) = match value.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours <= 14 && minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Print option defaults for one printer; values sent with a job take precedence
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct PrinterOverrides {
+    /// `portrait` or `landscape`
+    pub orientation: Option<String>,
+    /// `fit`, `fill`, `none` or a percentage such as `80%`
+    pub scaling: Option<String>,
+    /// Pages per sheet: 1, 2, 4, 6, 9 or 16
+    pub number_up: Option<u32>,
+    /// Stock loaded in the printer, e.g. `stationery` or `labels`
+    pub media_type: Option<String>,
+    /// Replaces `printer_ready_wait_secs` for this printer
+    pub ready_wait_secs: Option<u64>,
+    /// Replaces `hold_jobs` for this printer
+    pub hold_jobs: Option<bool>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
-            instance_name: "default-instance".to_string(),
+            instance_name: DEFAULT_INSTANCE_NAME.to_string(),
+            instance_name_from_hostname: false,
             printer_check_interval: 5,
             job_check_interval: 2,
             flux_url: "http://example.com".to_string(),
             flux_api_token: None,
+            token_source: "inline".to_string(),
             api_port: 8080,
             reverb_disabled: true,
             reverb_app_id: "default-app-id".to_string(),
@@ -83,8 +523,370 @@ impl Default for Config {
             reverb_app_secret: "default-app-secret".to_string(),
             reverb_use_tls: true,
             reverb_host: None,
+            reverb_port: None,
             reverb_auth_endpoint: "http://example.com/auth".to_string(),
+            client_cert_path: None,
+            client_key_path: None,
+            tls_ca_cert: None,
+            tls_accept_invalid_certs: false,
+            max_media_size_mb: 0,
+            max_api_response_mb: 16,
+            job_name_template: "Print Job {job_id}".to_string(),
+            job_name_from_filename: true,
+            catch_up_concurrency: 4,
+            tenant: None,
+            tenant_header: "X-Tenant".to_string(),
+            tenant_query_param: None,
+            separator_page: false,
+            job_spooler_filter: "printer.spooler_name".to_string(),
+            update_check_enabled: false,
+            size_copy_multipliers: HashMap::new(),
+            max_queue_depth: 0,
+            printer_overrides: HashMap::new(),
+            user_locations: HashMap::new(),
+            location_match: LocationMatch::Exact,
+            media_redirect_auth: MediaRedirectAuth::Strip,
+            job_check_max_interval: 10,
+            stamp_metadata: false,
+            conditional_status_updates: false,
+            worker_threads: None,
+            default_printer: None,
+            sync_printer_description: false,
+            status_fields: StatusFieldMapping::default(),
+            max_concurrent_api_requests: 8,
+            allowed_media_types: DEFAULT_MEDIA_TYPES.iter().map(|t| t.to_string()).collect(),
+            recent_completed_jobs: 200,
+            file_printer_dir: None,
+            min_media_size_bytes: 1,
+            quiet_hours: None,
+            printer_ready_wait_secs: 0,
+            max_job_age_hours: 0,
+            stale_job_action: StaleJobAction::Skip,
+            reconcile_interval_mins: 0,
+            unknown_printer_id_behavior: UnknownPrinterIdBehavior::Fallback,
+            max_printer_name_length: 0,
+            hold_jobs: false,
+            auto_create_printers: true,
+            auto_delete_printers: true,
+            ipp_printers: HashMap::new(),
+            fallback_printers: Vec::new(),
+            otlp_endpoint: None,
+            pre_print_command_enabled: false,
+            pre_print_command: None,
+            pre_print_timeout_secs: 30,
+        }
+    }
+}
+
+/// Standard IPP `media-type` keywords allowed by default
+const DEFAULT_MEDIA_TYPES: [&str; 9] = [
+    "stationery",
+    "stationery-letterhead",
+    "labels",
+    "envelope",
+    "cardstock",
+    "transparency",
+    "photographic",
+    "photographic-glossy",
+    "photographic-matte",
+];
+
+/// Where the API token is read from, parsed from `Config::token_source`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenSource {
+    /// `flux_api_token` in the config file
+    Inline,
+    /// First line of a file, re-read on every request so rotation works
+    File(String),
+    /// An environment variable
+    Env(String),
+}
+
+impl TokenSource {
+    /// Parse `inline`, `file:<path>` or `env:<VAR>`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let source = match value.split_once(':') {
+            _ if value.is_empty() || value == "inline" => TokenSource::Inline,
+            Some(("file", path)) if !path.trim().is_empty() => {
+                TokenSource::File(path.trim().to_string())
+            }
+            Some(("env", var)) if !var.trim().is_empty() => {
+                TokenSource::Env(var.trim().to_string())
+            }
+            _ => {
+                return Err(format!(
+                    "token_source must be 'inline', 'file:<path>' or 'env:<VAR>', got '{}'",
+                    value
+                ));
+            }
+        };
+        Ok(source)
+    }
+
+    /// The token from this source, with `inline` being `flux_api_token`
+    pub fn resolve(&self, inline: Option<&str>) -> Option<String> {
+        self.resolve_with(inline, |name| std::env::var(name).ok())
+    }
+
+    /// Like [`TokenSource::resolve`], looking environment variables up with `env`
+    pub fn resolve_with(
+        &self,
+        inline: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Option<String> {
+        let token = match self {
+            TokenSource::Inline => return inline.map(str::to_string),
+            TokenSource::File(path) => match std::fs::read_to_string(path) {
+                Ok(contents) => contents
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                Err(e) => {
+                    warn!(path = %path, error = %e, "Failed to read API token file");
+                    return None;
+                }
+            },
+            TokenSource::Env(var) => match env(var) {
+                Some(value) => value.trim().to_string(),
+                None => {
+                    warn!(variable = %var, "API token environment variable is not set");
+                    return None;
+                }
+            },
+        };
+        (!token.is_empty()).then_some(token)
+    }
+}
+
+/// Placeholder shown instead of secrets in [`Config::redacted`]
+pub const REDACTED: &str = "<redacted>";
+
+impl Config {
+    /// Copy of the configuration that is safe to log: the API token and the
+    /// Reverb secret are replaced by a placeholder when set.
+    pub fn redacted(&self) -> Config {
+        let mut config = self.clone();
+        if config.flux_api_token.is_some() {
+            config.flux_api_token = Some(REDACTED.to_string());
+        }
+        if !config.reverb_app_secret.is_empty() {
+            config.reverb_app_secret = REDACTED.to_string();
+        }
+        config
+    }
+
+    /// The API token from `token_source`, read fresh on every call.
+    ///
+    /// An unreadable file, unset variable or invalid `token_source` is
+    /// logged and treated as no token.
+    pub fn api_token(&self) -> Option<String> {
+        match TokenSource::parse(&self.token_source) {
+            Ok(source) => source.resolve(self.flux_api_token.as_deref()),
+            Err(problem) => {
+                warn!(problem = %problem, "Invalid token_source");
+                None
+            }
+        }
+    }
+
+    /// Copy of the configuration for sharing with other spoolers: the API
+    /// token and the Reverb secret are cleared.
+    pub fn without_secrets(&self) -> Config {
+        Config {
+            flux_api_token: None,
+            reverb_app_secret: String::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Check the configuration for values the bridge cannot run with.
+    ///
+    /// Returns every problem found, so an import can report them all at once.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if self.instance_name.trim().is_empty() {
+            problems.push("instance_name must not be empty".to_string());
+        }
+        match reqwest::Url::parse(&self.flux_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => problems.push(format!(
+                "flux_url must be an http(s) URL, got '{}'",
+                self.flux_url
+            )),
+        }
+        if let Err(problem) = TokenSource::parse(&self.token_source) {
+            problems.push(problem);
+        }
+        if self.printer_check_interval == 0 {
+            problems.push("printer_check_interval must be at least 1".to_string());
+        }
+        if self.worker_threads == Some(0) {
+            problems.push("worker_threads must be at least 1".to_string());
         }
+        if !self.reverb_disabled
+            && self
+                .reverb_host
+                .as_deref()
+                .is_none_or(|host| host.trim().is_empty())
+        {
+            problems.push("reverb_host is required unless reverb_disabled is set".to_string());
+        }
+        if self.reverb_port == Some(0) {
+            problems.push("reverb_port must be between 1 and 65535".to_string());
+        }
+        if let (Some(host), Some(port)) = (&self.reverb_host, self.reverb_port)
+            && let Some(host_port) = crate::services::websocket::host_port(host.trim())
+            && host_port != port
+        {
+            problems.push(format!(
+                "reverb_host already names port {} but reverb_port is {}",
+                host_port, port
+            ));
+        }
+        if self.client_cert_path.is_some() != self.client_key_path.is_some() {
+            problems.push("client_cert_path and client_key_path must be set together".to_string());
+        }
+        if self.tenant.is_some() && self.tenant_header.trim().is_empty() {
+            problems.push("tenant_header must not be empty when tenant is set".to_string());
+        }
+        let mut ipp_printers: Vec<(&String, &String)> = self.ipp_printers.iter().collect();
+        ipp_printers.sort();
+        for (name, uri) in ipp_printers {
+            if name == crate::services::print_job::FILE_PRINTER_NAME {
+                problems.push(format!("ipp_printers must not use the name '{}'", name));
+            }
+            if !uri.starts_with("ipp://") && !uri.starts_with("ipps://") {
+                problems.push(format!(
+                    "ipp_printers.{} must be an ipp:// or ipps:// URI, got '{}'",
+                    name, uri
+                ));
+            }
+        }
+        for (printer, overrides) in &self.printer_overrides {
+            if let Some(number_up) = overrides.number_up
+                && !crate::services::print_options::NUMBER_UP_VALUES.contains(&number_up)
+            {
+                problems.push(format!(
+                    "printer_overrides.{}.number_up must be one of 1, 2, 4, 6, 9 or 16",
+                    printer
+                ));
+            }
+        }
+
+        if (1..MIN_PRINTER_NAME_LENGTH).contains(&self.max_printer_name_length) {
+            problems.push(format!(
+                "max_printer_name_length must be 0 or at least {}",
+                MIN_PRINTER_NAME_LENGTH
+            ));
+        }
+        if self.pre_print_command_enabled {
+            match self.pre_print_command.as_deref().map(str::trim) {
+                None | Some("") => problems.push(
+                    "pre_print_command must be set when pre_print_command_enabled is on"
+                        .to_string(),
+                ),
+                Some(command) if !command.contains("{input}") => problems.push(
+                    "pre_print_command must contain {input} for the document path".to_string(),
+                ),
+                Some(_) => {}
+            }
+        }
+        problems.extend(self.status_fields.problems());
+        if let Some(quiet_hours) = &self.quiet_hours {
+            problems.extend(quiet_hours.problems());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Number of copies to send to CUPS for a job.
+    ///
+    /// `quantity` (at least 1) is multiplied by the `size_copy_multipliers`
+    /// entry for `size`; unlisted sizes use a multiplier of 1.
+    pub fn effective_copies(&self, quantity: u32, size: &str) -> u32 {
+        let multiplier = self.size_copy_multipliers.get(size).copied().unwrap_or(1);
+        quantity.max(1).saturating_mul(multiplier.max(1))
+    }
+
+    /// How long to wait for `system_name` to come back online, honouring its override
+    pub fn ready_wait_secs(&self, system_name: &str) -> u64 {
+        self.printer_overrides
+            .get(system_name)
+            .and_then(|overrides| overrides.ready_wait_secs)
+            .unwrap_or(self.printer_ready_wait_secs)
+    }
+
+    /// Copy the Reverb connection settings from `from`; returns whether any changed.
+    ///
+    /// `reverb_disabled` is left alone, since switching modes needs a restart.
+    pub fn take_reverb_settings(&mut self, from: &Config) -> bool {
+        let changed = self.reverb_app_id != from.reverb_app_id
+            || self.reverb_app_key != from.reverb_app_key
+            || self.reverb_app_secret != from.reverb_app_secret
+            || self.reverb_use_tls != from.reverb_use_tls
+            || self.reverb_host != from.reverb_host
+            || self.reverb_port != from.reverb_port
+            || self.reverb_auth_endpoint != from.reverb_auth_endpoint;
+        self.reverb_app_id = from.reverb_app_id.clone();
+        self.reverb_app_key = from.reverb_app_key.clone();
+        self.reverb_app_secret = from.reverb_app_secret.clone();
+        self.reverb_use_tls = from.reverb_use_tls;
+        self.reverb_host = from.reverb_host.clone();
+        self.reverb_port = from.reverb_port;
+        self.reverb_auth_endpoint = from.reverb_auth_endpoint.clone();
+        changed
+    }
+
+    /// Replace an unset `instance_name` with `hostname` when `instance_name_from_hostname`
+    /// is on; returns whether it was replaced.
+    ///
+    /// A name other than `default-instance` is always kept.
+    pub fn derive_instance_name(&mut self, hostname: &str) -> bool {
+        let name = self.instance_name.trim();
+        let hostname = hostname.trim();
+        if !self.instance_name_from_hostname
+            || !(name.is_empty() || name == DEFAULT_INSTANCE_NAME)
+            || hostname.is_empty()
+        {
+            return false;
+        }
+        self.instance_name = hostname.to_string();
+        true
+    }
+
+    /// Whether jobs for `system_name` are held in CUPS, honouring its override.
+    ///
+    /// Never true for `ipp_printers`: `release` only works against CUPS.
+    pub fn holds_jobs(&self, system_name: &str) -> bool {
+        if self.ipp_printers.contains_key(system_name) {
+            return false;
+        }
+        self.printer_overrides
+            .get(system_name)
+            .and_then(|overrides| overrides.hold_jobs)
+            .unwrap_or(self.hold_jobs)
+    }
+
+    /// Build a CUPS job name from `job_name_template`.
+    ///
+    /// Each `(key, value)` pair replaces a `{key}` placeholder; `{instance}` is
+    /// always filled from `instance_name`. Unknown placeholders are left as-is.
+    pub fn render_job_name(&self, values: &[(&str, &str)]) -> String {
+        let mut name = self
+            .job_name_template
+            .replace("{instance}", &self.instance_name);
+        for (key, value) in values {
+            name = name.replace(&format!("{{{}}}", key), value);
+        }
+        name
     }
 }
 
@@ -98,6 +900,19 @@ pub struct Printer {
     pub make_and_model: String,
     pub media_sizes: Vec<String>,
     pub printer_id: Option<u32>,
+    /// Administratively disabled printers are kept in sync but never printed to
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Whether CUPS reports a color mode; `false` if detection fails
+    #[serde(default)]
+    pub supports_color: bool,
+    /// CUPS `printer-is-accepting-jobs`; a rejecting queue is treated like a disabled printer
+    #[serde(default = "default_true")]
+    pub accepting_jobs: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -113,49 +928,122 @@ pub struct PrintJobResponse {
     pub data: PrintJobPaginatedData,
 }
 
+/// Deserialize `null` (or a missing field) as the type's default value
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Paginated print job list.
+///
+/// Pagination fields tolerate `null`, which some API versions send for empty results.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PrintJobPaginatedData {
+    #[serde(default, deserialize_with = "null_as_default")]
     pub current_page: u32,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub data: Vec<PrintJob>,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub first_page_url: String,
+    #[serde(default)]
     pub from: Option<u32>,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub last_page: u32,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub last_page_url: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub links: Vec<PageLink>,
+    #[serde(default)]
     pub next_page_url: Option<String>,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub path: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub per_page: u32,
+    #[serde(default)]
     pub prev_page_url: Option<String>,
+    #[serde(default)]
     pub to: Option<u32>,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub total: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PrintJob {
     pub id: u32,
+    /// Media to download; may be absent when the document is sent inline
+    #[serde(default)]
     pub media_id: u32,
     pub printer_id: Option<u32>,
     pub user_id: Option<u32>,
+    #[serde(default)]
     pub quantity: u32,
+    #[serde(default)]
     pub size: String,
     pub is_completed: bool,
     pub cups_job_id: Option<u32>,
     pub status: Option<PrintJobStatus>,
     pub error_message: Option<String>,
     pub printed_at: Option<String>,
+    #[serde(default)]
     pub created_at: String,
     pub created_by: Option<u32>,
+    #[serde(default)]
     pub updated_at: String,
     pub updated_by: Option<u32>,
     /// Included printer relationship (when using ?include=printer)
     pub printer: Option<PrintJobPrinter>,
+    /// Small documents embedded in the job as base64, used instead of `media_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_base64: Option<String>,
+    /// MIME type of `content_base64`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// `portrait` or `landscape`, overriding the printer default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<String>,
+    /// `fit`, `fill`, `none` or a percentage, overriding the printer default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scaling: Option<String>,
+    /// Pages per sheet, overriding the printer default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number_up: Option<u32>,
+    /// CUPS `media-type` such as `labels` or `photographic`, overriding the printer default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// Pages to print, e.g. `1,3-5` (all pages when absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_ranges: Option<String>,
+    /// `high` or `urgent` jobs are printed during quiet hours
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    /// Where to print when no printer is given, matched against printer locations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Printer chosen with `reprint --printer`, used instead of resolving one for this print
+    #[serde(skip)]
+    pub target_printer: Option<String>,
+}
+
+impl PrintJob {
+    /// Whether the job is marked `high` or `urgent` and bypasses quiet hours
+    pub fn is_urgent(&self) -> bool {
+        self.priority.as_deref().is_some_and(|priority| {
+            priority.eq_ignore_ascii_case("high") || priority.eq_ignore_ascii_case("urgent")
+        })
+    }
 }
 
 /// Printer data included in print job response
-#[derive(Serialize, Deserialize, Debug)]
+///
+/// Every field is optional so a partially populated relation (restricted
+/// permissions, deleted printer) does not fail the whole job parse.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PrintJobPrinter {
-    pub id: u32,
-    pub name: String,
-    pub spooler_name: String,
-    pub is_active: bool,
+    pub id: Option<u32>,
+    pub name: Option<String>,
+    pub spooler_name: Option<String>,
+    pub is_active: Option<bool>,
 }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use tracing::warn;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -13,6 +14,10 @@ pub struct ApiPrinter {
     pub media_sizes: Vec<String>, // Changed from Option<Vec<String>>
     pub is_active: Option<bool>,
     pub is_visible: Option<bool>,
+    pub supports_color: Option<bool>,
+    /// CUPS description; only sent when `sync_printer_description` is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -27,6 +32,86 @@ pub struct ApiPrinterData {
 }
 
 // Convert from local Printer to ApiPrinter for sending to server
+/// Characters a shortened name spends on its hash suffix (`-` and 8 hex digits)
+const NAME_HASH_SUFFIX_LEN: usize = 9;
+
+/// Printer name as sent to the API, at most `max_chars` characters (0 = unlimited).
+///
+/// Longer names are cut and given a suffix derived from the full name, so two
+/// long names sharing a prefix stay distinct and the same name always maps to
+/// the same API name.
+pub fn api_printer_name(name: &str, max_chars: usize) -> String {
+    if max_chars == 0 || name.chars().count() <= max_chars {
+        return name.to_string();
+    }
+    // FNV-1a, stable across builds and platforms
+    let hash = name.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    let prefix: String = name
+        .chars()
+        .take(max_chars.saturating_sub(NAME_HASH_SUFFIX_LEN))
+        .collect();
+    format!("{}-{:08x}", prefix, hash)
+}
+
+impl ApiPrinter {
+    /// Shorten `name` and `system_name` to the API's `max_printer_name_length`
+    pub fn shorten_names(&mut self, max_chars: usize) {
+        self.name = api_printer_name(&self.name, max_chars);
+        if let Some(system_name) = &self.system_name {
+            self.system_name = Some(api_printer_name(system_name, max_chars));
+        }
+    }
+}
+
+/// Error envelope FLUX returns with failed requests, e.g. a 422 validation error:
+/// `{"message": "...", "errors": {"field": ["..."]}}`
+#[derive(Deserialize, Debug, Default)]
+pub struct FluxApiError {
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Validation messages per field; each entry is a string or a list of strings
+    #[serde(default)]
+    pub errors: BTreeMap<String, serde_json::Value>,
+}
+
+impl FluxApiError {
+    /// Parse an error body, or `None` when it is not a recognisable envelope
+    pub fn parse(body: &str) -> Option<Self> {
+        let error: FluxApiError = serde_json::from_str(body).ok()?;
+        (error.message.is_some() || !error.errors.is_empty()).then_some(error)
+    }
+
+    /// Validation messages as `field: message` lines, in field order
+    pub fn field_messages(&self) -> Vec<String> {
+        let mut messages = Vec::new();
+        for (field, value) in &self.errors {
+            let texts = match value {
+                serde_json::Value::Array(items) => items.iter().collect(),
+                other => vec![other],
+            };
+            for text in texts {
+                let text = text
+                    .as_str()
+                    .map_or_else(|| text.to_string(), str::to_string);
+                messages.push(format!("{}: {}", field, text));
+            }
+        }
+        messages
+    }
+
+    /// One-line summary: the message followed by the field messages
+    pub fn summary(&self) -> String {
+        let fields = self.field_messages();
+        match (&self.message, fields.is_empty()) {
+            (Some(message), true) => message.clone(),
+            (Some(message), false) => format!("{} ({})", message, fields.join("; ")),
+            (None, _) => fields.join("; "),
+        }
+    }
+}
+
 impl From<&crate::models::Printer> for ApiPrinter {
     fn from(printer: &crate::models::Printer) -> Self {
         ApiPrinter {
@@ -46,8 +131,10 @@ impl From<&crate::models::Printer> for ApiPrinter {
             } else {
                 printer.media_sizes.clone()
             },
-            is_active: Some(true),
+            is_active: Some(printer.enabled && printer.accepting_jobs),
             is_visible: Some(true),
+            supports_color: Some(printer.supports_color),
+            description: None, // Set before sending if sync_printer_description is enabled
         }
     }
 }
@@ -62,11 +149,14 @@ impl From<&ApiPrinter> for crate::models::Printer {
                 .clone()
                 .unwrap_or_else(|| api_printer.name.clone()),
             uri: api_printer.uri.clone(),
-            description: "".to_string(), // No longer used in API
+            description: api_printer.description.clone().unwrap_or_default(),
             location: api_printer.location.clone().unwrap_or_default(),
             make_and_model: api_printer.make_and_model.clone().unwrap_or_default(),
             media_sizes: api_printer.media_sizes.clone(),
             printer_id: api_printer.id,
+            enabled: api_printer.is_active.unwrap_or(true),
+            supports_color: api_printer.supports_color.unwrap_or(false),
+            accepting_jobs: true,
         }
     }
 }
@@ -1,36 +1,68 @@
-use std::collections::HashSet;
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::Arc;
 
 use reqwest::Client;
+use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
-use tracing::info;
+use tracing::{error, info, warn};
 
+use crate::cli::{LogFilterHandle, build_env_filter, log_level_name, next_log_verbosity};
+use crate::models::Config;
 use crate::services::print_job::{
-    InFlightJobs, job_checker_task, job_status_checker_task, new_in_flight_jobs,
+    job_checker_task, job_status_checker_task, quiet_hours_task, reconciliation_task,
 };
 use crate::services::printer::{get_all_printers, printer_checker_task};
+use crate::services::update_check::update_check_task;
 use crate::services::websocket::websocket_task;
-use crate::utils::config::load_config;
-use crate::utils::printer_storage::{load_printers, save_printers_if_changed};
+use crate::state::AppState;
+use crate::utils::config::{
+    SharedConfig, apply_hostname_instance_name, config_path, load_config, read_config,
+    read_config_file,
+};
+use crate::utils::http::build_http_client;
+use crate::utils::printer_storage::{SharedPrinters, store_printers};
+use crate::utils::temp_files::sweep_stale_temp_files;
 
 /// Run the main server application
-pub async fn run_server(verbose_debug: bool) -> std::io::Result<()> {
-    let config = Arc::new(RwLock::new(load_config()));
-    let http_client = Client::new();
-    let printers_set = Arc::new(Mutex::new(HashSet::new()));
+pub async fn run_server(verbose: u8, log_filter: LogFilterHandle) -> std::io::Result<()> {
+    let verbose_debug = verbose >= 3;
+    let mut config_snapshot = load_config();
+    apply_hostname_instance_name(&mut config_snapshot);
+    if let Err(problems) = config_snapshot.validate() {
+        for problem in &problems {
+            error!(problem = %problem, "Invalid configuration");
+        }
+        return Err(std::io::Error::other(format!(
+            "Invalid configuration: {}",
+            problems.join("; ")
+        )));
+    }
+    let http_client =
+        build_http_client(&config_snapshot).map_err(|e| std::io::Error::other(e.to_string()))?;
+    let config: SharedConfig = Arc::new(RwLock::new(config_snapshot));
     let cancel_token = CancellationToken::new();
-    let in_flight_jobs = new_in_flight_jobs();
-
-    initialize_printers(&printers_set, verbose_debug).await;
-    let handles = spawn_background_tasks(
-        &config,
-        &http_client,
-        &printers_set,
-        &cancel_token,
-        &in_flight_jobs,
-        verbose_debug,
-    );
+    let state = AppState::load();
+
+    sweep_stale_temp_files();
+    initialize_printers(&state.printers, verbose_debug).await;
+    log_startup_report(&read_config(&config).await, &state);
+    let mut handles =
+        spawn_background_tasks(&config, &http_client, &cancel_token, &state, verbose_debug);
+    log_otlp_export(&read_config(&config).await);
+    #[cfg(unix)]
+    handles.push(tokio::spawn(log_level_signal_task(
+        log_filter,
+        verbose,
+        cancel_token.clone(),
+    )));
+    #[cfg(unix)]
+    handles.push(tokio::spawn(reverb_reconnect_signal_task(
+        config.clone(),
+        state.clone(),
+        cancel_token.clone(),
+    )));
+    #[cfg(not(unix))]
+    drop(log_filter);
 
     info!("Print server started");
 
@@ -46,26 +78,169 @@ pub async fn run_server(verbose_debug: bool) -> std::io::Result<()> {
         let _ = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await;
     }
 
+    state.stats.log_summary();
+
     info!("Shutdown complete");
     Ok(())
 }
 
+/// Report where spans go; the exporter itself is set up with logging, from
+/// the `otlp_endpoint` in `config.json` at startup
+fn log_otlp_export(config: &Config) {
+    let Some(endpoint) = config
+        .otlp_endpoint
+        .as_deref()
+        .filter(|e| !e.trim().is_empty())
+    else {
+        return;
+    };
+    if cfg!(feature = "otlp") {
+        info!(endpoint = %endpoint, "Exporting spans to OTLP collector");
+    } else {
+        warn!("otlp_endpoint is set but this build has no OTLP support (cargo feature `otlp`)");
+    }
+}
+
+/// Cycle the log level (info → debug → trace → info) on every SIGUSR1
+#[cfg(unix)]
+async fn log_level_signal_task(
+    log_filter: LogFilterHandle,
+    mut verbose: u8,
+    cancel_token: CancellationToken,
+) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigusr1 = match signal(SignalKind::user_defined1()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            warn!(error = %e, "Failed to install SIGUSR1 handler, log level toggling disabled");
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => return,
+            received = sigusr1.recv() => {
+                if received.is_none() {
+                    return;
+                }
+            }
+        }
+
+        verbose = next_log_verbosity(verbose);
+        match log_filter.reload(build_env_filter(verbose)) {
+            Ok(()) => info!(
+                level = log_level_name(verbose),
+                "Log level changed (SIGUSR1)"
+            ),
+            Err(e) => warn!(error = %e, "Failed to change log level"),
+        }
+    }
+}
+
+/// Reload the Reverb settings from `config.json` and reconnect the WebSocket on every SIGUSR2
+#[cfg(unix)]
+async fn reverb_reconnect_signal_task(
+    config: SharedConfig,
+    state: AppState,
+    cancel_token: CancellationToken,
+) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigusr2 = match signal(SignalKind::user_defined2()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            warn!(error = %e, "Failed to install SIGUSR2 handler, Reverb reconnect disabled");
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => return,
+            received = sigusr2.recv() => {
+                if received.is_none() {
+                    return;
+                }
+            }
+        }
+
+        if config.read().await.reverb_disabled {
+            warn!("Reverb is disabled, ignoring reconnect request (SIGUSR2)");
+            continue;
+        }
+        match read_config_file() {
+            Ok(on_disk) => match on_disk.validate() {
+                Ok(()) => {
+                    let changed = config.write().await.take_reverb_settings(&on_disk);
+                    info!(
+                        settings_changed = changed,
+                        "Reconnecting to Reverb (SIGUSR2)"
+                    );
+                }
+                Err(problems) => warn!(
+                    problems = %problems.join("; "),
+                    "config.json is invalid, reconnecting with the current Reverb settings"
+                ),
+            },
+            Err(e) => warn!(
+                error = %e,
+                "Failed to read config.json, reconnecting with the current Reverb settings"
+            ),
+        }
+        state.reverb_reconnect.notify_one();
+    }
+}
+
+/// Log the effective configuration and runtime mode in one place
+fn log_startup_report(config: &Config, state: &AppState) {
+    let printers = state
+        .printers
+        .lock()
+        .expect("Failed to acquire printers lock");
+    let enabled = printers.values().filter(|p| p.enabled).count();
+    let mode = if config.reverb_disabled {
+        "polling"
+    } else {
+        "websocket"
+    };
+
+    info!(
+        version = env!("CARGO_PKG_VERSION"),
+        config_path = %config_path().display(),
+        instance = %config.instance_name,
+        flux_url = %config.flux_url,
+        api_token_set = config.api_token().is_some(),
+        token_source = %config.token_source,
+        mode,
+        printers = printers.len(),
+        enabled_printers = enabled,
+        pending_status_updates = state
+            .pending_status_updates
+            .lock()
+            .expect("Failed to acquire pending_status_updates lock")
+            .len(),
+        "Startup report"
+    );
+    info!(config = ?config.redacted(), "Effective configuration");
+}
+
 /// Initialize printers from system and sync with saved state
-async fn initialize_printers(printers_set: &Arc<Mutex<HashSet<String>>>, verbose_debug: bool) {
+async fn initialize_printers(printers: &SharedPrinters, verbose_debug: bool) {
     let system_printers = get_all_printers(verbose_debug).await;
-    let mut set = printers_set
-        .lock()
-        .expect("Failed to acquire printers_set lock");
 
-    let original_saved_printers = load_printers();
+    let original_saved_printers = printers
+        .lock()
+        .expect("Failed to acquire printers lock")
+        .clone();
     let mut updated_printers = original_saved_printers.clone();
 
     for printer in system_printers {
-        set.insert(printer.system_name.clone());
-
         if let Some(saved_printer) = original_saved_printers.get(&printer.system_name) {
             let mut updated_printer = printer.clone();
             updated_printer.printer_id = saved_printer.printer_id;
+            updated_printer.enabled = saved_printer.enabled;
             updated_printers.insert(printer.system_name.clone(), updated_printer);
         } else {
             updated_printers.insert(printer.system_name.clone(), printer);
@@ -73,7 +248,7 @@ async fn initialize_printers(printers_set: &Arc<Mutex<HashSet<String>>>, verbose
     }
 
     let printers_were_updated =
-        save_printers_if_changed(&updated_printers, &original_saved_printers);
+        store_printers(printers, &updated_printers, &original_saved_printers);
     if printers_were_updated {
         info!(
             printers_count = updated_printers.len(),
@@ -84,24 +259,23 @@ async fn initialize_printers(printers_set: &Arc<Mutex<HashSet<String>>>, verbose
 
 /// Spawn all background tasks
 fn spawn_background_tasks(
-    config: &Arc<RwLock<crate::models::Config>>,
+    config: &SharedConfig,
     http_client: &Client,
-    printers_set: &Arc<Mutex<HashSet<String>>>,
     cancel_token: &CancellationToken,
-    in_flight_jobs: &InFlightJobs,
+    state: &AppState,
     verbose_debug: bool,
 ) -> Vec<JoinHandle<()>> {
     let mut handles = Vec::new();
 
     // Printer checker task
-    let printers_set_clone = printers_set.clone();
+    let state_printers = state.clone();
     let config_checker = config.clone();
     let http_client_checker = http_client.clone();
     let token_checker = cancel_token.clone();
 
     handles.push(tokio::spawn(async move {
         printer_checker_task(
-            printers_set_clone,
+            state_printers,
             config_checker,
             http_client_checker,
             token_checker,
@@ -114,43 +288,72 @@ fn spawn_background_tasks(
     let config_jobs = config.clone();
     let http_client_jobs = http_client.clone();
     let token_jobs = cancel_token.clone();
-    let in_flight_jobs_polling = in_flight_jobs.clone();
+    let state_polling = state.clone();
 
     handles.push(tokio::spawn(async move {
-        job_checker_task(
-            config_jobs,
-            http_client_jobs,
-            token_jobs,
-            in_flight_jobs_polling,
-        )
-        .await;
+        job_checker_task(config_jobs, http_client_jobs, token_jobs, state_polling).await;
     }));
 
     // WebSocket listener task
     let config_ws = config.clone();
     let http_client_ws = http_client.clone();
     let token_ws = cancel_token.clone();
-    let in_flight_jobs_ws = in_flight_jobs.clone();
+    let state_ws = state.clone();
 
     handles.push(tokio::spawn(async move {
-        websocket_task(config_ws, http_client_ws, token_ws, in_flight_jobs_ws).await;
+        websocket_task(config_ws, http_client_ws, token_ws, state_ws).await;
     }));
 
     // Job status checker task (polls CUPS for final job status)
     let config_status = config.clone();
     let http_client_status = http_client.clone();
     let token_status = cancel_token.clone();
-    let in_flight_jobs_status = in_flight_jobs.clone();
+    let state_status = state.clone();
 
     handles.push(tokio::spawn(async move {
         job_status_checker_task(
             config_status,
             http_client_status,
             token_status,
-            in_flight_jobs_status,
+            state_status,
+        )
+        .await;
+    }));
+
+    // Quiet hours task (prints held-back jobs when the window ends)
+    let config_quiet = config.clone();
+    let http_client_quiet = http_client.clone();
+    let token_quiet = cancel_token.clone();
+    let state_quiet = state.clone();
+
+    handles.push(tokio::spawn(async move {
+        quiet_hours_task(config_quiet, http_client_quiet, token_quiet, state_quiet).await;
+    }));
+
+    // Reconciliation task (opt-in, corrects reported statuses from the CUPS history)
+    let config_reconcile = config.clone();
+    let http_client_reconcile = http_client.clone();
+    let token_reconcile = cancel_token.clone();
+    let state_reconcile = state.clone();
+
+    handles.push(tokio::spawn(async move {
+        reconciliation_task(
+            config_reconcile,
+            http_client_reconcile,
+            token_reconcile,
+            state_reconcile,
         )
         .await;
     }));
 
+    // Release update check task (opt-in)
+    let config_update = config.clone();
+    let http_client_update = http_client.clone();
+    let token_update = cancel_token.clone();
+
+    handles.push(tokio::spawn(async move {
+        update_check_task(config_update, http_client_update, token_update).await;
+    }));
+
     handles
 }
@@ -0,0 +1,521 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use tokio::sync::Semaphore;
+use tracing::{Instrument, Level, debug, info_span, trace, warn};
+
+use crate::error::{SpoolerError, SpoolerResult};
+use crate::models::api::{ApiPrinter, ApiPrinterResponse, FluxApiError};
+use crate::models::{Config, PrintJob, StatusFieldMapping};
+use crate::services::print_job::{PendingJobRef, parse_pending_job_refs, parse_print_jobs};
+use crate::utils::http::{
+    MAX_REDIRECTS, read_text_limited, redacted_headers, truncate_for_log, with_auth_header,
+};
+use crate::utils::status_queue::PendingStatusUpdate;
+
+/// Attempts made for idempotent GET requests before giving up
+const GET_ATTEMPTS: u32 = 3;
+
+/// Delay before the first GET retry; doubled for each further attempt
+const GET_RETRY_BASE: Duration = Duration::from_millis(500);
+
+/// Maximum number of body characters in trace-level request and response logs
+const TRACE_BODY_LIMIT: usize = 4000;
+
+/// Permits shared by every [`FluxClient`], sized by `max_concurrent_api_requests`
+/// of the first client that sends a request
+static API_PERMITS: OnceLock<Semaphore> = OnceLock::new();
+
+/// Single print job response from API (when fetching by ID)
+#[derive(serde::Deserialize, Debug)]
+struct SinglePrintJobResponse {
+    #[allow(dead_code)]
+    status: u16,
+    data: PrintJob,
+}
+
+/// Build the print job list URL, scoped to this spooler via `job_spooler_filter`.
+///
+/// Only jobs that are not completed are listed unless `include_completed` is set.
+pub fn print_jobs_url(config: &Config, include_completed: bool) -> String {
+    let mut url = format!("{}/api/print-jobs?include=printer", base_url(config));
+    if !include_completed {
+        url.push_str("&filter[is_completed]=false");
+    }
+    if !config.job_spooler_filter.is_empty() {
+        url.push_str(&format!(
+            "&filter[{}]={}",
+            config.job_spooler_filter,
+            urlencoding::encode(&config.instance_name)
+        ));
+    }
+    url
+}
+
+/// Build the URL listing active printers registered for this spooler.
+///
+/// `instance_name` is percent-encoded, so CUPS-style names with spaces or `/`
+/// stay inside the query parameter.
+pub fn printers_url(config: &Config) -> String {
+    format!(
+        "{}/api/printers?filter[is_active]=true&filter[spooler_name]={}",
+        base_url(config),
+        urlencoding::encode(&config.instance_name)
+    )
+}
+
+/// Build the body of a job status update (PUT `/api/print-jobs`).
+///
+/// The completion flag and status are written under the field names and
+/// values configured in `status_fields`. With `conditional` set (see
+/// [`PendingStatusUpdate::is_conditional`]),
+/// `expected_is_completed: false` asks the API to only apply the update while
+/// the job is not completed yet, so a status set by an admin or another
+/// spooler is not overwritten.
+pub fn status_update_payload(
+    update: &PendingStatusUpdate,
+    fields: &StatusFieldMapping,
+    conditional: bool,
+) -> serde_json::Value {
+    let mut payload = serde_json::json!({ "id": update.job_id });
+
+    if !fields.completed_field.is_empty() {
+        payload[fields.completed_field.as_str()] = serde_json::json!(update.status.is_terminal());
+    }
+
+    if !fields.status_field.is_empty() {
+        payload[fields.status_field.as_str()] = serde_json::json!(fields.value_for(&update.status));
+    }
+
+    if let Some(cups_id) = update.cups_job_id {
+        payload["cups_job_id"] = serde_json::json!(cups_id);
+    }
+
+    if let Some(msg) = &update.error_message {
+        payload["error_message"] = serde_json::json!(msg);
+    }
+
+    if let Some(printed_at) = &update.printed_at {
+        payload["printed_at"] = serde_json::json!(printed_at);
+    }
+
+    if conditional {
+        payload["expected_is_completed"] = serde_json::json!(false);
+    }
+
+    payload
+}
+
+/// Result of [`FluxClient::create_printer`]
+#[derive(Debug, PartialEq)]
+pub enum CreatePrinterOutcome {
+    /// The printer was created with this id
+    Created(u32),
+    /// The API rejected the create as a conflict or duplicate, with its error message
+    Conflict(String),
+}
+
+/// `flux_url` without a trailing slash, so paths can always start with `/`
+fn base_url(config: &Config) -> &str {
+    config.flux_url.trim_end_matches('/')
+}
+
+/// Client for the FLUX API.
+///
+/// Wraps the shared HTTP client and a configuration snapshot so URL building,
+/// authentication, retries of idempotent reads and error mapping live in one
+/// place instead of every service function.
+#[derive(Clone)]
+pub struct FluxClient {
+    http: Client,
+    config: Config,
+}
+
+impl FluxClient {
+    pub fn new(http: Client, config: Config) -> Self {
+        FluxClient { http, config }
+    }
+
+    /// The configuration snapshot this client was built with
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The shared HTTP client, for requests that do not go to the API
+    pub fn http_client(&self) -> &Client {
+        &self.http
+    }
+
+    /// Absolute URL for an API path such as `/api/printers`
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", base_url(&self.config), path)
+    }
+
+    /// Start an authenticated JSON request to an absolute URL
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        with_auth_header(self.http.request(method, url), &self.config)
+            .header("Accept", "application/json")
+    }
+
+    /// Send a request once one of the process-wide API permits is free.
+    ///
+    /// The permit is held until the response headers arrive; bodies are read
+    /// without it so a slow download does not block other API calls. At trace
+    /// level the request and the response headers are logged, with
+    /// credentials redacted. With the `otlp` feature the request carries the
+    /// `api_request` span as a `traceparent` header.
+    async fn execute(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let permits = API_PERMITS.get_or_init(|| {
+            let limit = match self.config.max_concurrent_api_requests {
+                0 => Semaphore::MAX_PERMITS,
+                limit => limit,
+            };
+            Semaphore::new(limit)
+        });
+        let (client, request) = request.build_split();
+        let request = request?;
+        let span = info_span!(
+            "api_request",
+            method = %request.method(),
+            url = %request.url(),
+            status = tracing::field::Empty
+        );
+        async {
+            if tracing::enabled!(Level::TRACE) {
+                let body = request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .map(|bytes| {
+                        truncate_for_log(&String::from_utf8_lossy(bytes), TRACE_BODY_LIMIT)
+                    })
+                    .unwrap_or_default();
+                trace!(
+                    headers = ?redacted_headers(request.headers()),
+                    body = %body,
+                    "API request"
+                );
+            }
+            let _permit = permits
+                .acquire()
+                .await
+                .expect("API request semaphore is never closed");
+            #[cfg(feature = "otlp")]
+            let request = {
+                let mut request = request;
+                crate::utils::otlp::inject_trace_context(request.headers_mut());
+                request
+            };
+            let response = client.execute(request).await?;
+            tracing::Span::current().record("status", response.status().as_u16());
+            trace!(
+                status = response.status().as_u16(),
+                headers = ?redacted_headers(response.headers()),
+                "API response"
+            );
+            Ok(response)
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Send a request, turning non-success responses into [`SpoolerError::Api`]
+    async fn send(&self, request: RequestBuilder, context: &str) -> SpoolerResult<Response> {
+        let response = self.execute(request).await?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        Err(self.error_from_response(response, context).await)
+    }
+
+    /// GET a URL, retrying network errors, rate limiting and server errors.
+    ///
+    /// A redirect the client's policy did not follow is returned as-is.
+    async fn get_with_retry(
+        &self,
+        url: &str,
+        accept: &str,
+        context: &str,
+    ) -> SpoolerResult<Response> {
+        let mut attempt = 1;
+        loop {
+            let request =
+                with_auth_header(self.http.get(url), &self.config).header("Accept", accept);
+            let result = self.execute(request).await;
+
+            let retryable = match &result {
+                Ok(response) => {
+                    let status = response.status();
+                    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+
+            if !retryable || attempt >= GET_ATTEMPTS {
+                let response = result?;
+                if response.status().is_success() || response.status().is_redirection() {
+                    return Ok(response);
+                }
+                return Err(self.error_from_response(response, context).await);
+            }
+
+            let delay = GET_RETRY_BASE * 2u32.pow(attempt - 1);
+            warn!(
+                url = %url,
+                attempt,
+                retry_in_ms = delay.as_millis() as u64,
+                "API request failed, retrying"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// GET a URL and return the response body as text
+    async fn get_text(&self, url: &str, context: &str) -> SpoolerResult<String> {
+        debug!(url = %url, "{}", context);
+        let response = self
+            .get_with_retry(url, "application/json", context)
+            .await?;
+        if response.status().is_redirection() {
+            return Err(SpoolerError::Api(format!(
+                "{}: redirected to another host ({})",
+                context,
+                response.status()
+            )));
+        }
+        self.response_text(response).await
+    }
+
+    // ── Print jobs ──────────────────────────────────────────────────────────
+
+    /// List this spooler's print jobs, optionally including completed ones
+    pub async fn list_jobs(&self, include_completed: bool) -> SpoolerResult<Vec<PrintJob>> {
+        let url = print_jobs_url(&self.config, include_completed);
+        let body = self.get_text(&url, "Failed to fetch print jobs").await?;
+        Ok(parse_print_jobs(&body)?)
+    }
+
+    /// List the IDs and printers of this spooler's pending jobs
+    pub async fn list_pending_job_refs(&self) -> SpoolerResult<Vec<PendingJobRef>> {
+        let url = print_jobs_url(&self.config, false);
+        let body = self
+            .get_text(&url, "Failed to fetch pending print jobs")
+            .await?;
+        Ok(parse_pending_job_refs(&body)?)
+    }
+
+    /// Fetch a single print job including its printer relation
+    pub async fn get_job(&self, job_id: u32) -> SpoolerResult<PrintJob> {
+        let url = self.url(&format!("/api/print-jobs/{}?include=printer", job_id));
+        let context = format!("Failed to fetch print job {}", job_id);
+        let body = self.get_text(&url, &context).await?;
+        let parsed: SinglePrintJobResponse = serde_json::from_str(&body)
+            .map_err(|e| format!("Failed to parse job response: {}", e))?;
+        Ok(parsed.data)
+    }
+
+    /// Report a job's status (PUT `/api/print-jobs` with the id in the body).
+    ///
+    /// For a conditional update, a `409 Conflict` or `412 Precondition Failed`
+    /// means the job was already finished elsewhere; the update is dropped with
+    /// a warning, since retrying it cannot succeed. Other updates treat these
+    /// statuses as errors.
+    pub async fn update_job_status(&self, update: &PendingStatusUpdate) -> SpoolerResult<()> {
+        let conditional = update.is_conditional(self.config.conditional_status_updates);
+        let payload = status_update_payload(update, &self.config.status_fields, conditional);
+
+        let request = self
+            .request(Method::PUT, &self.url("/api/print-jobs"))
+            .json(&payload);
+        let response = self.execute(request).await?;
+        let status = response.status();
+
+        if conditional
+            && (status == StatusCode::CONFLICT || status == StatusCode::PRECONDITION_FAILED)
+        {
+            let error = self
+                .error_from_response(response, "Conditional status update refused")
+                .await;
+            warn!(
+                job_id = update.job_id,
+                status = %update.status,
+                error = %error,
+                "Job was already completed in the API, status update not applied"
+            );
+            return Ok(());
+        }
+
+        if !status.is_success() {
+            return Err(self
+                .error_from_response(response, "Failed to update print job status")
+                .await);
+        }
+        Ok(())
+    }
+
+    /// Start downloading a private media file; the caller streams the body.
+    ///
+    /// Redirects to another host are only returned by the client when
+    /// `media_redirect_auth` is `forward`; they are re-issued here with the token.
+    pub async fn download_media(&self, media_id: u32) -> SpoolerResult<Response> {
+        let url = self.url(&format!("/api/media/private/{}", media_id));
+        let context = format!("Failed to download file for media ID {}", media_id);
+        let mut response = self
+            .get_with_retry(&url, "application/octet-stream", &context)
+            .await?;
+
+        let mut hops = 0;
+        while response.status().is_redirection() {
+            hops += 1;
+            let target = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok());
+            let Some(target) = target.filter(|_| hops <= MAX_REDIRECTS) else {
+                return Err(self.error_from_response(response, &context).await);
+            };
+
+            debug!(media_id, to = %target, "Re-issuing media download with authorization");
+            response = self
+                .get_with_retry(target.as_str(), "application/octet-stream", &context)
+                .await?;
+        }
+
+        Ok(response)
+    }
+
+    // ── Printers ────────────────────────────────────────────────────────────
+
+    /// List the active printers registered for this spooler
+    pub async fn list_printers(&self) -> SpoolerResult<Vec<ApiPrinter>> {
+        let url = printers_url(&self.config);
+        let body = self
+            .get_text(&url, "Failed to fetch printers from API")
+            .await?;
+        let parsed: ApiPrinterResponse = serde_json::from_str(&body)?;
+        Ok(parsed.data.data)
+    }
+
+    /// Create a printer and return the id assigned by the API.
+    ///
+    /// A `409 Conflict` or `422 Unprocessable Entity` is returned as
+    /// [`CreatePrinterOutcome::Conflict`] so the caller can look for a printer
+    /// another spooler created at the same time.
+    pub async fn create_printer(
+        &self,
+        printer: &ApiPrinter,
+    ) -> SpoolerResult<CreatePrinterOutcome> {
+        let request = self
+            .request(Method::POST, &self.url("/api/printers"))
+            .json(printer);
+        let response = self.execute(request).await?;
+        let status = response.status();
+
+        if status == StatusCode::CONFLICT || status == StatusCode::UNPROCESSABLE_ENTITY {
+            let error = self
+                .error_from_response(response, "Failed to create printer")
+                .await;
+            return Ok(CreatePrinterOutcome::Conflict(error.to_string()));
+        }
+        if !status.is_success() {
+            return Err(self
+                .error_from_response(response, "Failed to create printer")
+                .await);
+        }
+
+        let response_data: serde_json::Value =
+            serde_json::from_str(&self.response_text(response).await?)?;
+        let printer_id = response_data["data"]["id"]
+            .as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or("Failed to get printer ID from response")?;
+        Ok(CreatePrinterOutcome::Created(printer_id))
+    }
+
+    /// Update a printer (PUT `/api/printers` with the id in the body)
+    pub async fn update_printer(&self, printer: &ApiPrinter) -> SpoolerResult<()> {
+        if printer.id.is_none() {
+            return Err("Cannot update printer without an ID".into());
+        }
+
+        let request = self
+            .request(Method::PUT, &self.url("/api/printers"))
+            .json(printer);
+        self.send(request, "Failed to update printer").await?;
+        Ok(())
+    }
+
+    /// Ask `reverb_auth_endpoint` to sign a private channel subscription.
+    ///
+    /// Returns the response status and body; the caller decides what a
+    /// rejection means.
+    pub async fn authorize_channel(
+        &self,
+        socket_id: &str,
+        channel_name: &str,
+    ) -> SpoolerResult<(StatusCode, String)> {
+        let request = self
+            .request(Method::POST, &self.config.reverb_auth_endpoint)
+            .form(&[("socket_id", socket_id), ("channel_name", channel_name)]);
+        let response = self.execute(request).await?;
+        let status = response.status();
+        // Not logged: the body is a signature for the channel subscription
+        let body = read_text_limited(response, self.config.max_api_response_mb).await?;
+        Ok((status, body))
+    }
+
+    /// Delete a printer; returns `false` if the API no longer knew it
+    pub async fn delete_printer(&self, printer_id: u32) -> SpoolerResult<bool> {
+        let request = self
+            .request(
+                Method::DELETE,
+                &self.url(&format!("/api/printers/{}", printer_id)),
+            )
+            .json(&serde_json::json!({
+                "spooler_name": self.config.instance_name
+            }));
+        let response = self.execute(request).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            return Err(self
+                .error_from_response(response, "Failed to delete printer")
+                .await);
+        }
+        Ok(true)
+    }
+
+    /// Read a response body as text, logging it at trace level.
+    ///
+    /// Bodies over `max_api_response_mb` are abandoned with an error. Media
+    /// downloads are streamed by the caller and never pass through here.
+    async fn response_text(&self, response: Response) -> SpoolerResult<String> {
+        let url = response.url().clone();
+        let status = response.status();
+        let body = read_text_limited(response, self.config.max_api_response_mb).await?;
+        trace!(
+            url = %url,
+            status = status.as_u16(),
+            body = %truncate_for_log(&body, TRACE_BODY_LIMIT),
+            "API response body"
+        );
+        Ok(body)
+    }
+
+    /// Build an API error from a non-success response, including its body.
+    ///
+    /// A FLUX error envelope is reduced to its message and per-field validation
+    /// errors; any other body is included as-is.
+    async fn error_from_response(&self, response: Response, context: &str) -> SpoolerError {
+        let status = response.status();
+        let body = self
+            .response_text(response)
+            .await
+            .unwrap_or_else(|e| e.to_string());
+        let detail = FluxApiError::parse(&body).map_or(body, |error| error.summary());
+        SpoolerError::Api(format!("{}: {} - {}", context, status, detail))
+    }
+}
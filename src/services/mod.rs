@@ -1,4 +1,10 @@
+pub mod flux_client;
+pub mod pre_print;
 pub mod print_job;
+pub mod print_options;
 pub mod printer;
+pub mod printer_backend;
+pub mod printer_consistency;
 pub mod printer_sync;
+pub mod update_check;
 pub mod websocket;
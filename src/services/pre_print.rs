@@ -0,0 +1,103 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tempfile::NamedTempFile;
+use tokio::process::Command;
+use tracing::{debug, info};
+
+use crate::error::{SpoolerError, SpoolerResult};
+use crate::models::Config;
+use crate::utils::temp_files::new_temp_file;
+
+/// Placeholder for the downloaded document in `pre_print_command`
+pub const INPUT_PLACEHOLDER: &str = "{input}";
+
+/// Placeholder for the file the command writes; without it stdout is printed
+pub const OUTPUT_PLACEHOLDER: &str = "{output}";
+
+/// Split a `pre_print_command` template into a program and its arguments.
+///
+/// The template is split on whitespace before the placeholders are filled in,
+/// so paths never need quoting and are never seen by a shell. Returns `None`
+/// for an empty template.
+pub fn pre_print_args(template: &str, input: &str, output: &str) -> Option<Vec<String>> {
+    let args: Vec<String> = template
+        .split_whitespace()
+        .map(|arg| {
+            arg.replace(INPUT_PLACEHOLDER, input)
+                .replace(OUTPUT_PLACEHOLDER, output)
+        })
+        .collect();
+    (!args.is_empty()).then_some(args)
+}
+
+/// Run the configured `pre_print_command` on `input` and return the file to print instead.
+///
+/// A command that cannot be started, exits non-zero, runs longer than
+/// `pre_print_timeout_secs` or produces an empty document rejects the job.
+pub async fn run_pre_print_command(
+    config: &Config,
+    job_id: u32,
+    input: &Path,
+) -> SpoolerResult<NamedTempFile> {
+    let template = config.pre_print_command.as_deref().unwrap_or_default();
+    let output = new_temp_file()?;
+    let writes_output_file = template.contains(OUTPUT_PLACEHOLDER);
+    let args = pre_print_args(
+        template,
+        &input.to_string_lossy(),
+        &output.path().to_string_lossy(),
+    )
+    .ok_or_else(|| SpoolerError::Config("pre_print_command is empty".to_string()))?;
+
+    debug!(job_id, command = ?args, "Running pre_print_command");
+    let mut command = Command::new(&args[0]);
+    command
+        .args(&args[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let child = command.spawn().map_err(|e| {
+        SpoolerError::Rejected(format!(
+            "pre_print_command '{}' failed to start: {}",
+            args[0], e
+        ))
+    })?;
+
+    let timeout = Duration::from_secs(config.pre_print_timeout_secs.max(1));
+    let result = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| {
+            SpoolerError::Rejected(format!(
+                "pre_print_command timed out after {} s",
+                timeout.as_secs()
+            ))
+        })??;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(SpoolerError::Rejected(format!(
+            "pre_print_command exited with {}: {}",
+            result.status,
+            stderr.trim()
+        )));
+    }
+
+    if !writes_output_file {
+        tokio::fs::write(output.path(), &result.stdout).await?;
+    }
+    let size = tokio::fs::metadata(output.path()).await?.len();
+    if size == 0 {
+        return Err(SpoolerError::Rejected(
+            "pre_print_command produced an empty document".to_string(),
+        ));
+    }
+
+    info!(
+        job_id,
+        bytes = size,
+        "Document transformed by pre_print_command"
+    );
+    Ok(output)
+}
@@ -1,19 +1,60 @@
-use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, NaiveDateTime};
 use printers::common::base::job::PrinterJobOptions;
-use printers::{get_printer_by_name, get_printers};
+use printers::common::base::job::PrinterJobState;
+use printers::common::base::printer::PrinterState;
+use printers::get_printer_by_name;
 use reqwest::Client;
-use std::io::Write;
+use reqwest::header::CONTENT_DISPOSITION;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
 use tempfile::NamedTempFile;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tokio::time;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info, trace, warn};
-
-use crate::error::SpoolerResult;
-use crate::models::{Config, PrintJob, PrintJobResponse, PrintJobStatus};
-use crate::utils::config::read_config;
-use crate::utils::http::with_auth_header;
+use tracing::{Instrument, debug, error, info, info_span, trace, warn};
+
+use crate::error::{SpoolerError, SpoolerResult};
+use crate::models::api::api_printer_name;
+use crate::models::{
+    Config, LocationMatch, PrintJob, PrintJobResponse, PrintJobStatus, Printer, StaleJobAction,
+    UnknownPrinterIdBehavior,
+};
+use crate::services::flux_client::FluxClient;
+use crate::services::pre_print::run_pre_print_command;
+use crate::services::print_options::job_print_properties;
+use crate::services::printer::{
+    default_system_printer, printer_accepting_jobs, sync_known_printers,
+};
+use crate::services::printer_backend::{CupsBackend, PrinterBackend, backend_for};
+use crate::state::AppState;
+use crate::utils::config::{SharedConfig, read_config};
+use crate::utils::consumption::{estimate_pages, record_consumption, save_consumption};
+use crate::utils::dead_letters::{
+    forget_dead_letter, job_snapshot, record_dead_letter, save_dead_letters,
+};
+use crate::utils::fetch_queue::{
+    PendingFetch, enqueue_pending_fetch, is_retryable_fetch_error, save_pending_fetches,
+};
+use crate::utils::finished_jobs::{
+    FinishedJob, prune_finished_jobs, record_finished_job, save_finished_jobs,
+};
+use crate::utils::http::{content_disposition_filename, truncate_for_log};
+use crate::utils::pdf::text_page_pdf;
+use crate::utils::printer_storage::SharedPrinters;
+use crate::utils::recent_jobs::{remember_completed_job, save_recent_jobs};
+use crate::utils::skipped_jobs::{forget_skipped_job, record_skipped_job, save_skipped_jobs};
+use crate::utils::status_queue::{
+    PendingStatusUpdate, enqueue_status_update, save_pending_status_updates, unix_now,
+};
+use crate::utils::temp_files::{new_temp_file, new_temp_file_for, remove_temp_file};
 
 /// A print job that has been submitted to CUPS and is awaiting final status.
 #[derive(Debug, Clone)]
@@ -24,6 +65,8 @@ pub struct InFlightJob {
     pub submitted_at: Instant,
     /// Last known status sent to the API (to avoid redundant updates)
     pub last_status: PrintJobStatus,
+    /// The job as fetched from the API, for the dead-letter store if it fails
+    pub job: PrintJob,
 }
 
 /// Shared in-flight job tracker accessible from multiple tasks.
@@ -34,207 +77,444 @@ pub fn new_in_flight_jobs() -> InFlightJobs {
     Arc::new(Mutex::new(Vec::new()))
 }
 
+/// IDs of jobs currently being downloaded and submitted, shared by the
+/// WebSocket, catch-up and polling paths so no job is printed twice.
+pub type ActiveJobs = Arc<Mutex<HashSet<u32>>>;
+
+/// Create a new empty active jobs set.
+pub fn new_active_jobs() -> ActiveJobs {
+    Arc::new(Mutex::new(HashSet::new()))
+}
+
+/// Claim on an active job, released when dropped so every exit path frees it.
+struct JobClaim<'a> {
+    active_jobs: &'a ActiveJobs,
+    job_id: u32,
+}
+
+impl Drop for JobClaim<'_> {
+    fn drop(&mut self) {
+        self.active_jobs
+            .lock()
+            .expect("Failed to acquire active_jobs lock")
+            .remove(&self.job_id);
+    }
+}
+
+/// Claim a job for processing unless it is already active or in flight.
+fn claim_job(job_id: u32, state: &AppState) -> Option<JobClaim<'_>> {
+    let in_flight = state
+        .in_flight_jobs
+        .lock()
+        .expect("Failed to acquire in_flight_jobs lock")
+        .iter()
+        .any(|j| j.api_job_id == job_id);
+    if in_flight {
+        return None;
+    }
+
+    let inserted = state
+        .active_jobs
+        .lock()
+        .expect("Failed to acquire active_jobs lock")
+        .insert(job_id);
+    inserted.then_some(JobClaim {
+        active_jobs: &state.active_jobs,
+        job_id,
+    })
+}
+
+/// A pending job as listed by the API, enough to schedule the catch-up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingJobRef {
+    pub id: u32,
+    pub printer_id: Option<u32>,
+}
+
+/// Printer name that writes jobs to `file_printer_dir` instead of sending them to CUPS
+pub const FILE_PRINTER_NAME: &str = "__file__";
+
+/// Optional response header carrying the hex SHA-256 of a media download
+pub const CONTENT_SHA256_HEADER: &str = "x-content-sha256";
+
 /// Maximum time (seconds) to wait for a CUPS job before marking it as failed.
 const CUPS_JOB_TIMEOUT_SECS: u64 = 300; // 5 minutes
 
+/// Maximum number of response characters included in parse-failure logs.
+const LOG_BODY_LIMIT: usize = 2000;
+
+/// Laravel's default timestamp format, always UTC here
+const API_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
 // ── API helpers ─────────────────────────────────────────────────────────────
 
 /// Update print job status in the API with full status tracking fields.
+///
+/// If the API cannot be reached the update is queued in
+/// `pending_status_updates.json` and retried by
+/// [`drain_pending_status_updates`], so a printed job is not picked up again
+/// just because its status PUT briefly failed.
 async fn update_print_job_status(
     job_id: u32,
+    job: Option<&PrintJob>,
     cups_job_id: Option<u64>,
     status: PrintJobStatus,
     error_message: Option<&str>,
-    http_client: &Client,
-    config: &Config,
+    api: &FluxClient,
+    state: &AppState,
 ) -> SpoolerResult<()> {
-    let url = format!("{}/api/print-jobs", config.flux_url);
-
-    let mut payload = serde_json::json!({
-        "id": job_id,
-        "is_completed": status.is_terminal(),
-        "status": status,
-    });
+    // A job retried from the dead-letter store was already reported as failed
+    let correction = state
+        .dead_letters
+        .lock()
+        .expect("Failed to acquire dead_letters lock")
+        .iter()
+        .any(|letter| letter.job_id == job_id);
+    send_job_status(
+        status_update(job_id, cups_job_id, status, error_message, correction),
+        job,
+        api,
+        state,
+    )
+    .await
+}
 
-    if let Some(cups_id) = cups_job_id {
-        payload["cups_job_id"] = serde_json::json!(cups_id);
+/// A status update not yet sent to the API.
+///
+/// A `correction` changes the status of a job the API already finished.
+fn status_update(
+    job_id: u32,
+    cups_job_id: Option<u64>,
+    status: PrintJobStatus,
+    error_message: Option<&str>,
+    correction: bool,
+) -> PendingStatusUpdate {
+    PendingStatusUpdate {
+        job_id,
+        cups_job_id,
+        printed_at: (status == PrintJobStatus::Completed).then(chrono_now_utc),
+        status,
+        error_message: error_message.map(str::to_string),
+        attempts: 0,
+        next_attempt_at: 0,
+        correction,
     }
+}
 
-    if let Some(msg) = error_message {
-        payload["error_message"] = serde_json::json!(msg);
-    }
+/// Report a job's status and keep the local job stores in step with it.
+///
+/// A failed job is kept in the dead-letter store together with `job`.
+async fn send_job_status(
+    mut update: PendingStatusUpdate,
+    job: Option<&PrintJob>,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    let job_id = update.job_id;
+
+    if update.status == PrintJobStatus::Completed {
+        let mut recent = state
+            .recent_jobs
+            .lock()
+            .expect("Failed to acquire recent_jobs lock");
+        remember_completed_job(&mut recent, job_id, api.config().recent_completed_jobs);
+        save_recent_jobs(&recent);
+
+        let mut skipped = state
+            .skipped_jobs
+            .lock()
+            .expect("Failed to acquire skipped_jobs lock");
+        if forget_skipped_job(&mut skipped, job_id) {
+            save_skipped_jobs(&skipped);
+        }
 
-    if status == PrintJobStatus::Completed {
-        payload["printed_at"] = serde_json::json!(chrono_now_utc());
+        let mut dead_letters = state
+            .dead_letters
+            .lock()
+            .expect("Failed to acquire dead_letters lock");
+        if forget_dead_letter(&mut dead_letters, job_id) {
+            save_dead_letters(&dead_letters);
+        }
+    } else if update.status == PrintJobStatus::Failed {
+        let mut dead_letters = state
+            .dead_letters
+            .lock()
+            .expect("Failed to acquire dead_letters lock");
+        record_dead_letter(
+            &mut dead_letters,
+            job_id,
+            job,
+            update.cups_job_id,
+            update.error_message.as_deref().unwrap_or("failed"),
+            unix_now(),
+        );
+        save_dead_letters(&dead_letters);
     }
 
-    let response = with_auth_header(http_client.put(&url), config)
-        .header("Accept", "application/json")
-        .json(&payload)
-        .send()
-        .await?;
+    let result = api.update_job_status(&update).await;
 
-    if !response.status().is_success() {
-        let status_code = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!(
-            "Failed to update print job status: {} - {}",
-            status_code, error_text
-        )
-        .into());
+    let mut queue = state
+        .pending_status_updates
+        .lock()
+        .expect("Failed to acquire pending_status_updates lock");
+    if result.is_err() {
+        update.schedule_retry(unix_now());
+        enqueue_status_update(&mut queue, update);
+        save_pending_status_updates(&queue);
+    } else if queue.iter().any(|u| u.job_id == job_id) {
+        // A newer status reached the API; an older queued one must not overwrite it
+        queue.retain(|u| u.job_id != job_id);
+        save_pending_status_updates(&queue);
     }
 
-    Ok(())
+    result
 }
 
-/// Return the current UTC time as an ISO 8601 string for the `printed_at` field.
-fn chrono_now_utc() -> String {
-    // Format: YYYY-MM-DD HH:MM:SS (Laravel-compatible)
-    let now = std::time::SystemTime::now();
-    let duration = now
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default();
-    let secs = duration.as_secs();
-
-    // Simple UTC formatting without pulling in the chrono crate
-    let days = secs / 86400;
-    let time_secs = secs % 86400;
-    let hours = time_secs / 3600;
-    let minutes = (time_secs % 3600) / 60;
-    let seconds = time_secs % 60;
-
-    // Days since epoch to Y-M-D (simplified Gregorian)
-    let mut y = 1970i64;
-    let mut remaining_days = days as i64;
-    loop {
-        let days_in_year = if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) {
-            366
-        } else {
-            365
-        };
-        if remaining_days < days_in_year {
-            break;
-        }
-        remaining_days -= days_in_year;
-        y += 1;
-    }
-    let leap = y % 4 == 0 && (y % 100 != 0 || y % 400 == 0);
-    let month_days: [i64; 12] = [
-        31,
-        if leap { 29 } else { 28 },
-        31,
-        30,
-        31,
-        30,
-        31,
-        31,
-        30,
-        31,
-        30,
-        31,
-    ];
-    let mut m = 0usize;
-    for (i, &md) in month_days.iter().enumerate() {
-        if remaining_days < md {
-            m = i;
-            break;
+/// Retry queued status updates whose backoff delay has elapsed.
+///
+/// Due updates are taken out of the queue while they are sent, so the polling
+/// and status checker tasks never deliver the same update twice.
+pub async fn drain_pending_status_updates(api: &FluxClient, state: &AppState) {
+    let now = unix_now();
+    let due: Vec<PendingStatusUpdate> = {
+        let mut queue = state
+            .pending_status_updates
+            .lock()
+            .expect("Failed to acquire pending_status_updates lock");
+        let (due, waiting) = queue.drain(..).partition(|u| u.is_due(now));
+        *queue = waiting;
+        due
+    };
+
+    if due.is_empty() {
+        return;
+    }
+
+    let mut failed = Vec::new();
+    for mut update in due {
+        match api.update_job_status(&update).await {
+            Ok(()) => info!(
+                job_id = update.job_id,
+                status = %update.status,
+                attempts = update.attempts,
+                "Delivered queued status update"
+            ),
+            Err(e) => {
+                update.schedule_retry(now);
+                warn!(
+                    job_id = update.job_id,
+                    status = %update.status,
+                    attempts = update.attempts,
+                    retry_in_secs = update.next_attempt_at - now,
+                    error = %e,
+                    "Queued status update failed again"
+                );
+                failed.push(update);
+            }
         }
-        remaining_days -= md;
     }
 
-    format!(
-        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
-        y,
-        m + 1,
-        remaining_days + 1,
-        hours,
-        minutes,
-        seconds
-    )
+    let mut queue = state
+        .pending_status_updates
+        .lock()
+        .expect("Failed to acquire pending_status_updates lock");
+    for update in failed {
+        // Keep a newer update queued for the same job while this one was in flight
+        if !queue.iter().any(|u| u.job_id == update.job_id) {
+            queue.push(update);
+        }
+    }
+    save_pending_status_updates(&queue);
 }
 
-/// Fetch pending print job IDs from the API (Send-safe version for tokio::spawn)
-pub async fn fetch_pending_job_ids(
-    http_client: &Client,
-    config: &Config,
-) -> Result<Vec<u32>, String> {
-    let jobs_url = format!(
-        "{}/api/print-jobs?filter[is_completed]=false&include=printer",
-        config.flux_url
-    );
+/// Whether a status update for this job is still waiting to reach the API
+fn has_pending_status_update(job_id: u32, state: &AppState) -> bool {
+    state
+        .pending_status_updates
+        .lock()
+        .expect("Failed to acquire pending_status_updates lock")
+        .iter()
+        .any(|u| u.job_id == job_id)
+}
 
-    debug!(url = %jobs_url, "Fetching pending print job IDs");
+/// Whether this spooler completed the job recently (see `recent_jobs.json`)
+pub fn was_completed_recently(job_id: u32, state: &AppState) -> bool {
+    state
+        .recent_jobs
+        .lock()
+        .expect("Failed to acquire recent_jobs lock")
+        .contains(&job_id)
+}
 
-    let response = match with_auth_header(http_client.get(&jobs_url), config)
-        .header("Accept", "application/json")
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(e) => return Err(format!("Failed to fetch print jobs: {}", e)),
+/// Return the current UTC time as an ISO 8601 string for the `printed_at` field.
+fn chrono_now_utc() -> String {
+    format_unix_utc(unix_now())
+}
+
+/// Format Unix seconds as `YYYY-MM-DD HH:MM:SS` UTC (Laravel-compatible)
+pub fn format_unix_utc(secs: u64) -> String {
+    i64::try_from(secs)
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .unwrap_or_default()
+        .format(API_TIMESTAMP_FORMAT)
+        .to_string()
+}
+
+/// Parse a paginated print job response, salvaging well-formed jobs on schema drift.
+///
+/// The strict parse is tried first. If it fails (a changed field type, an
+/// unexpected envelope), each entry of `data.data` is parsed on its own so a
+/// single malformed job doesn't stop the others from printing.
+pub fn parse_print_jobs(response_text: &str) -> Result<Vec<PrintJob>, String> {
+    let strict_error = match serde_json::from_str::<PrintJobResponse>(response_text) {
+        Ok(parsed) => return Ok(parsed.data.data),
+        Err(e) => e,
     };
 
-    if !response.status().is_success() {
-        return Err(format!("Failed to fetch print jobs: {}", response.status()));
+    let entries = lenient_job_entries(response_text, &strict_error)?;
+    let mut jobs = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        match serde_json::from_value::<PrintJob>(entry.clone()) {
+            Ok(job) => jobs.push(job),
+            Err(e) => warn!(
+                job_id = ?entry.get("id").and_then(|id| id.as_u64()),
+                error = %e,
+                "Skipping unparseable print job"
+            ),
+        }
     }
 
-    let response_text = match response.text().await {
-        Ok(t) => t,
-        Err(e) => return Err(format!("Failed to read response: {}", e)),
+    warn!(
+        parsed = jobs.len(),
+        total = entries.len(),
+        "Recovered print jobs with lenient parse"
+    );
+    Ok(jobs)
+}
+
+/// Parse job IDs together with their printer from a paginated print job response.
+///
+/// Falls back to reading the `id` of every `data.data` entry when the strict
+/// parse fails, so jobs can still be fetched individually. The printer is read
+/// from `printer_id` or the included `printer.id`.
+pub fn parse_pending_job_refs(response_text: &str) -> Result<Vec<PendingJobRef>, String> {
+    let strict_error = match serde_json::from_str::<PrintJobResponse>(response_text) {
+        Ok(parsed) => {
+            return Ok(parsed
+                .data
+                .data
+                .iter()
+                .map(|job| PendingJobRef {
+                    id: job.id,
+                    printer_id: job.printer_id.or(job.printer.as_ref().and_then(|p| p.id)),
+                })
+                .collect());
+        }
+        Err(e) => e,
     };
 
-    let parsed_response: PrintJobResponse = match serde_json::from_str(&response_text) {
-        Ok(parsed) => parsed,
-        Err(e) => return Err(format!("Failed to parse print jobs: {}", e)),
+    let as_u32 = |value: Option<&serde_json::Value>| {
+        value
+            .and_then(|v| v.as_u64())
+            .and_then(|v| u32::try_from(v).ok())
     };
 
-    Ok(parsed_response.data.data.iter().map(|job| job.id).collect())
+    let entries = lenient_job_entries(response_text, &strict_error)?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            Some(PendingJobRef {
+                id: as_u32(entry.get("id"))?,
+                printer_id: as_u32(entry.get("printer_id"))
+                    .or_else(|| as_u32(entry.pointer("/printer/id"))),
+            })
+        })
+        .collect())
 }
 
-/// Fetch in-flight jobs from the API (jobs with status queued/processing that have a cups_job_id).
-/// Used on startup to re-populate the in-flight tracker.
-pub async fn fetch_in_flight_jobs_from_api(
-    http_client: &Client,
-    config: &Config,
-) -> Result<Vec<PrintJob>, String> {
-    // Fetch jobs that are not completed — we'll filter for queued/processing client-side
-    let jobs_url = format!(
-        "{}/api/print-jobs?filter[is_completed]=false&include=printer",
-        config.flux_url
+/// Extract the raw `data.data` job entries after a failed strict parse.
+fn lenient_job_entries(
+    response_text: &str,
+    strict_error: &serde_json::Error,
+) -> Result<Vec<serde_json::Value>, String> {
+    debug!(
+        error = %strict_error,
+        body = %truncate_for_log(response_text, LOG_BODY_LIMIT),
+        "Strict print job parse failed, trying lenient parse"
     );
 
-    debug!(url = %jobs_url, "Fetching in-flight jobs from API for status recovery");
+    let value: serde_json::Value = serde_json::from_str(response_text)
+        .map_err(|e| format!("Failed to parse print jobs: {}", e))?;
 
-    let response = match with_auth_header(http_client.get(&jobs_url), config)
-        .header("Accept", "application/json")
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(e) => return Err(format!("Failed to fetch in-flight jobs: {}", e)),
+    match value.pointer("/data/data") {
+        Some(serde_json::Value::Array(entries)) => Ok(entries.clone()),
+        _ => Err(format!("Failed to parse print jobs: {}", strict_error)),
+    }
+}
+
+/// Process jobs created while the bridge was offline.
+///
+/// Jobs are grouped by printer and each printer is drained in order, with up
+/// to `catch_up_concurrency` printers running in parallel.
+pub async fn catch_up_pending_jobs(api: FluxClient, state: AppState) {
+    let jobs = match api.list_pending_job_refs().await {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            error!(error = %e, "Failed to fetch pending print jobs");
+            return;
+        }
     };
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "Failed to fetch in-flight jobs: {}",
-            response.status()
-        ));
+    if jobs.is_empty() {
+        info!("No pending print jobs found");
+        return;
     }
 
-    let response_text = match response.text().await {
-        Ok(t) => t,
-        Err(e) => return Err(format!("Failed to read response: {}", e)),
-    };
+    let mut by_printer: HashMap<Option<u32>, Vec<u32>> = HashMap::new();
+    for job in &jobs {
+        by_printer.entry(job.printer_id).or_default().push(job.id);
+    }
 
-    let parsed_response: PrintJobResponse = match serde_json::from_str(&response_text) {
-        Ok(parsed) => parsed,
-        Err(e) => return Err(format!("Failed to parse in-flight jobs: {}", e)),
-    };
+    info!(
+        count = jobs.len(),
+        printers = by_printer.len(),
+        concurrency = api.config().catch_up_concurrency,
+        "Found pending print jobs, processing..."
+    );
+
+    let semaphore = Arc::new(Semaphore::new(api.config().catch_up_concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+
+    for (printer_id, job_ids) in by_printer {
+        let semaphore = semaphore.clone();
+        let api = api.clone();
+        let state = state.clone();
+
+        tasks.spawn(async move {
+            let Ok(_permit) = semaphore.acquire_owned().await else {
+                return;
+            };
+            for job_id in job_ids {
+                info!(job_id, printer_id = ?printer_id, "Processing pending job");
+                if let Err(e) = fetch_and_print_job_by_id(job_id, &api, &state).await {
+                    error!(job_id, error = %e, "Failed to process pending job");
+                }
+            }
+        });
+    }
+
+    while tasks.join_next().await.is_some() {}
+}
+
+/// Fetch in-flight jobs from the API (jobs with status queued/processing that have a cups_job_id).
+/// Used on startup to re-populate the in-flight tracker.
+pub async fn fetch_in_flight_jobs_from_api(api: &FluxClient) -> SpoolerResult<Vec<PrintJob>> {
+    // Fetch jobs that are not completed — we'll filter for queued/processing client-side
+    let jobs = api.list_jobs(false).await?;
 
     // Filter to only jobs that have a cups_job_id and a queued/processing status
-    let in_flight: Vec<PrintJob> = parsed_response
-        .data
-        .data
+    let in_flight: Vec<PrintJob> = jobs
         .into_iter()
         .filter(|job| {
             job.cups_job_id.is_some()
@@ -250,193 +530,1251 @@ pub async fn fetch_in_flight_jobs_from_api(
 
 // ── Printer helpers ─────────────────────────────────────────────────────────
 
-/// Get the default system printer system_name
-fn get_default_printer_system_name() -> String {
-    let system_printers = get_printers();
-    if let Some(first) = system_printers.first() {
-        return first.system_name.clone();
+/// Get the default printer system_name
+fn get_default_printer_system_name(config: &Config) -> String {
+    if config.default_printer.as_deref() == Some(FILE_PRINTER_NAME) {
+        return FILE_PRINTER_NAME.to_string();
     }
-    "default".to_string()
+    default_system_printer(config)
+        .map(|printer| printer.system_name)
+        .unwrap_or_else(|| "default".to_string())
 }
 
-/// Resolve printer system_name from job data for stable CUPS addressing
-async fn resolve_printer_name(job: &PrintJob) -> String {
-    // Try to resolve via saved printers by ID for stable system_name
-    let printer_id = job.printer.as_ref().map(|p| p.id).or(job.printer_id);
-    if let Some(id) = printer_id {
-        let saved_printers = crate::utils::printer_storage::load_printers();
-        for (system_name, printer) in &saved_printers {
-            if printer.printer_id == Some(id) {
-                debug!(
+/// Find the enabled printer serving `location`, preferring the first by `system_name`
+pub fn printer_for_location(
+    printers: &HashMap<String, Printer>,
+    location: &str,
+    strategy: LocationMatch,
+) -> Option<String> {
+    printers
+        .iter()
+        .filter(|(_, printer)| printer.enabled && strategy.matches(&printer.location, location))
+        .map(|(system_name, _)| system_name)
+        .min()
+        .cloned()
+}
+
+/// Printer id a job targets, from the included relation or the plain field
+fn job_printer_id(job: &PrintJob) -> Option<u32> {
+    job.printer.as_ref().and_then(|p| p.id).or(job.printer_id)
+}
+
+/// `system_name` of the known printer with API id `printer_id`
+fn known_system_name(printers: &SharedPrinters, printer_id: u32) -> Option<String> {
+    printers
+        .lock()
+        .expect("Failed to acquire printers lock")
+        .iter()
+        .find(|(_, printer)| printer.printer_id == Some(printer_id))
+        .map(|(system_name, _)| system_name.clone())
+}
+
+/// `system_name` of the known printer whose name was shortened to `api_name`
+fn known_system_name_for_api_name(
+    printers: &SharedPrinters,
+    api_name: &str,
+    max_name_length: usize,
+) -> Option<String> {
+    if max_name_length == 0 {
+        return None;
+    }
+    printers
+        .lock()
+        .expect("Failed to acquire printers lock")
+        .iter()
+        .find(|(system_name, printer)| {
+            (printer.name != api_name
+                && api_printer_name(&printer.name, max_name_length) == api_name)
+                || (*system_name != api_name
+                    && api_printer_name(system_name, max_name_length) == api_name)
+        })
+        .map(|(system_name, _)| system_name.clone())
+}
+
+/// Apply `unknown_printer_id_behavior` to a job whose printer id is not in `printers.json`.
+///
+/// `Ok` lets resolution continue (falling back, or with the id now known);
+/// otherwise the job is [`SpoolerError::Rejected`].
+async fn handle_unknown_printer_id(
+    job: &PrintJob,
+    printer_id: u32,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    let behavior = api.config().unknown_printer_id_behavior;
+    warn!(
+        job_id = job.id,
+        printer_id,
+        behavior = %behavior,
+        "Job targets a printer id this bridge does not know"
+    );
+
+    match behavior {
+        UnknownPrinterIdBehavior::Fallback => Ok(()),
+        UnknownPrinterIdBehavior::Fail => Err(SpoolerError::Rejected(format!(
+            "Printer id {} is not known to this bridge (unknown_printer_id_behavior: fail)",
+            printer_id
+        ))),
+        UnknownPrinterIdBehavior::ResyncThenRetry => {
+            if let Err(e) = sync_known_printers(state, api, false).await {
+                warn!(job_id = job.id, error = %e, "Printer sync for an unknown printer id failed");
+            }
+            if known_system_name(&state.printers, printer_id).is_some() {
+                info!(
                     job_id = job.id,
-                    printer_name = %printer.name,
-                    system_name = %system_name,
-                    "Resolved printer system_name"
+                    printer_id, "Printer id resolved after a printer sync"
                 );
-                return system_name.clone();
+                return Ok(());
             }
+            Err(SpoolerError::Rejected(format!(
+                "Printer id {} is still unknown after a printer sync",
+                printer_id
+            )))
         }
     }
+}
+
+/// Resolve printer system_name from job data for stable CUPS addressing
+///
+/// Jobs without a printer are routed by their `location`, or the location of
+/// their user in `user_locations`, before falling back to the default printer.
+async fn resolve_printer_name(
+    job: &PrintJob,
+    printers: &SharedPrinters,
+    config: &Config,
+) -> String {
+    if let Some(name) = &job.target_printer {
+        debug!(job_id = job.id, printer_name = %name, "Using printer chosen for this print");
+        return name.clone();
+    }
+
+    // Try to resolve via known printers by ID for stable system_name
+    let printer_id = job_printer_id(job);
+    if let Some(system_name) = printer_id.and_then(|id| known_system_name(printers, id)) {
+        debug!(
+            job_id = job.id,
+            system_name = %system_name,
+            "Resolved printer system_name"
+        );
+        return system_name;
+    }
 
     // Fallback: use name from job data (get_printer_by_name matches both name and system_name)
-    if let Some(ref printer) = job.printer {
+    if let Some(name) = job.printer.as_ref().and_then(|p| p.name.clone()) {
+        // A name shortened for the API maps back to its CUPS queue
+        let name = known_system_name_for_api_name(printers, &name, config.max_printer_name_length)
+            .unwrap_or(name);
         debug!(
             job_id = job.id,
-            printer_name = %printer.name,
+            printer_name = %name,
             "Falling back to printer name from job data"
         );
-        printer.name.clone()
-    } else {
-        debug!(job_id = job.id, "Using default printer");
-        get_default_printer_system_name()
+        return name;
+    }
+
+    // Location routing only applies when the job names no printer at all
+    let location = job.location.as_ref().or_else(|| {
+        job.user_id
+            .and_then(|user_id| config.user_locations.get(&user_id))
+    });
+    if printer_id.is_none()
+        && let Some(location) = location
+    {
+        let known_printers = printers.lock().expect("Failed to acquire printers lock");
+        match printer_for_location(&known_printers, location, config.location_match) {
+            Some(system_name) => {
+                debug!(
+                    job_id = job.id,
+                    location = %location,
+                    system_name = %system_name,
+                    "Routed job to printer by location"
+                );
+                return system_name;
+            }
+            None => debug!(job_id = job.id, location = %location, "No printer found for location"),
+        }
     }
+
+    debug!(job_id = job.id, "Using default printer");
+    get_default_printer_system_name(config)
 }
 
 // ── Core print workflow ─────────────────────────────────────────────────────
 
+/// A downloaded document
+pub struct DownloadedMedia {
+    pub file: NamedTempFile,
+    /// File name from the response's `Content-Disposition` header
+    pub filename: Option<String>,
+}
+
 /// Download file from API and save to temp file
-async fn download_file(
-    http_client: &Client,
+///
+/// The bytes are checked against `Content-Length` and, when the API sends
+/// one, the [`CONTENT_SHA256_HEADER`] digest; a mismatch is
+/// [`SpoolerError::Rejected`] so a truncated document is never printed.
+/// The temp file keeps the extension of the `Content-Disposition` file name.
+pub async fn download_file(api: &FluxClient, media_id: u32) -> SpoolerResult<DownloadedMedia> {
+    debug!(media_id, "Downloading file");
+    let file_response = api.download_media(media_id).await?;
+    let config = api.config();
+    let filename = file_response
+        .headers()
+        .get(CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(content_disposition_filename);
+
+    // Reject oversized media up front, and again while streaming in case the header lies
+    let limit_bytes = config.max_media_size_mb.saturating_mul(1024 * 1024);
+    let expected_length = file_response.content_length();
+    if limit_bytes > 0
+        && let Some(length) = expected_length
+        && length > limit_bytes
+    {
+        return Err(SpoolerError::Rejected(format!(
+            "Media {} is {} bytes, exceeding the {} MB size limit",
+            media_id, length, config.max_media_size_mb
+        )));
+    }
+
+    let expected_sha256 = file_response
+        .headers()
+        .get(CONTENT_SHA256_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase());
+    let mut hasher = expected_sha256.as_ref().map(|_| Sha256::new());
+
+    let mut file_response = file_response;
+    let mut temp_file = match &filename {
+        Some(name) => new_temp_file_for(name)?,
+        None => new_temp_file()?,
+    };
+    let mut received: u64 = 0;
+
+    loop {
+        let chunk = match file_response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            // The body ended before the announced length; printing the rest would be corrupt
+            Err(e) if expected_length.is_some() => {
+                return Err(SpoolerError::Rejected(format!(
+                    "Download of media {} broke off after {} of {} bytes: {}",
+                    media_id,
+                    received,
+                    expected_length.unwrap_or_default(),
+                    e
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        received += chunk.len() as u64;
+        if limit_bytes > 0 && received > limit_bytes {
+            return Err(SpoolerError::Rejected(format!(
+                "Media {} exceeds the {} MB size limit",
+                media_id, config.max_media_size_mb
+            )));
+        }
+        if let Some(hasher) = &mut hasher {
+            hasher.update(&chunk);
+        }
+        temp_file.write_all(&chunk)?;
+    }
+
+    if let Some(expected) = expected_length
+        && received != expected
+    {
+        return Err(SpoolerError::Rejected(format!(
+            "Media {} is {} bytes, but the server announced {}",
+            media_id, received, expected
+        )));
+    }
+    if let (Some(hasher), Some(expected)) = (hasher, expected_sha256) {
+        let actual = format!("{:x}", hasher.finalize());
+        if actual != expected {
+            return Err(SpoolerError::Rejected(format!(
+                "Media {} has SHA-256 {}, but the server announced {}",
+                media_id, actual, expected
+            )));
+        }
+    }
+
+    check_min_media_size(
+        received,
+        config.min_media_size_bytes,
+        &format!("Media {}", media_id),
+    )?;
+    Ok(DownloadedMedia {
+        file: temp_file,
+        filename,
+    })
+}
+
+/// Reject a document smaller than `min_bytes` (0 = no minimum).
+///
+/// An empty or truncated download would print a blank page or label, so it is
+/// [`SpoolerError::Rejected`] and the job is marked failed.
+pub fn check_min_media_size(bytes: u64, min_bytes: u64, description: &str) -> SpoolerResult<()> {
+    if bytes < min_bytes {
+        return Err(SpoolerError::Rejected(format!(
+            "{} is {} bytes, below the {} byte minimum (min_media_size_bytes)",
+            description, bytes, min_bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Decode a job's inline base64 document into a temp file.
+///
+/// Invalid base64 or content over `max_media_size_mb` can never be printed,
+/// so both are reported as [`SpoolerError::Rejected`].
+fn inline_content_file(
+    job: &PrintJob,
+    encoded: &str,
     config: &Config,
-    media_id: u32,
 ) -> SpoolerResult<NamedTempFile> {
-    let file_url = format!("{}/api/media/private/{}", config.flux_url, media_id);
-    debug!(media_id, "Downloading file");
+    // Base64 needs 4 characters per 3 bytes, so check the encoded length before decoding
+    let limit_bytes = config.max_media_size_mb.saturating_mul(1024 * 1024);
+    if limit_bytes > 0 && (encoded.len() as u64 / 4).saturating_mul(3) > limit_bytes {
+        return Err(SpoolerError::Rejected(format!(
+            "Inline content of job {} exceeds the {} MB size limit",
+            job.id, config.max_media_size_mb
+        )));
+    }
+
+    // The engine rejects whitespace, so line-wrapped content is joined first
+    let encoded: String = encoded
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let content = STANDARD.decode(encoded).map_err(|e| {
+        SpoolerError::Rejected(format!("Invalid inline content for job {}: {}", job.id, e))
+    })?;
+    if content.is_empty() {
+        return Err(SpoolerError::Rejected(format!(
+            "Inline content of job {} is empty",
+            job.id
+        )));
+    }
+    check_min_media_size(
+        content.len() as u64,
+        config.min_media_size_bytes,
+        &format!("Inline content of job {}", job.id),
+    )?;
 
-    let file_response = with_auth_header(http_client.get(&file_url), config)
-        .header("Accept", "application/octet-stream")
-        .send()
-        .await?;
+    debug!(
+        job_id = job.id,
+        bytes = content.len(),
+        content_type = ?job.content_type,
+        "Using inline job content"
+    );
+
+    let mut temp_file = new_temp_file()?;
+    temp_file.write_all(&content)?;
+    Ok(temp_file)
+}
+
+/// Process a print job, reporting permanent rejections to the API as failed.
+///
+/// Transient errors (network, CUPS) are only returned so the job stays pending
+/// and is retried; a [`SpoolerError::Rejected`] job would never succeed, so it
+/// is marked failed with the rejection reason instead. A
+/// [`SpoolerError::Deferred`] job is left untouched for a later attempt.
+///
+/// Everything logged while the job is processed belongs to a `print_job` span
+/// carrying `job_id`, the `created_by`/`updated_by` user ids and a fresh
+/// `correlation_id`, which is also appended to the error message reported to
+/// the API.
+async fn process_print_job(
+    job: &PrintJob,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    let correlation_id = new_correlation_id();
+    let span = info_span!(
+        "print_job",
+        job_id = job.id,
+        correlation_id = %correlation_id,
+        created_by = job.created_by,
+        updated_by = job.updated_by
+    );
+    process_print_job_in_span(job, api, state, &correlation_id)
+        .instrument(span)
+        .await
+}
+
+async fn process_print_job_in_span(
+    job: &PrintJob,
+    api: &FluxClient,
+    state: &AppState,
+    correlation_id: &str,
+) -> SpoolerResult<()> {
+    let Some(_claim) = claim_job(job.id, state) else {
+        debug!(job_id = job.id, "Job is already being processed, skipping");
+        return Ok(());
+    };
+
+    if has_pending_status_update(job.id, state) {
+        info!(
+            job_id = job.id,
+            "Skipping job whose status update has not reached the API yet"
+        );
+        return Ok(());
+    }
+
+    if was_completed_recently(job.id, state) {
+        info!(
+            job_id = job.id,
+            "Skipping job this spooler already completed, although the API lists it as pending"
+        );
+        return Ok(());
+    }
+
+    let max_age_hours = api.config().max_job_age_hours;
+    if is_job_stale(&job.created_at, max_age_hours, unix_now()) {
+        return discard_stale_job(job, max_age_hours, api, state).await;
+    }
+
+    if !job.is_urgent()
+        && let Some(quiet_hours) = &api.config().quiet_hours
+        && quiet_hours.is_quiet_at(unix_now())
+    {
+        info!(
+            job_id = job.id,
+            "Quiet hours, leaving job pending until the window ends"
+        );
+        remember_skipped_job(job.id, "quiet hours", state);
+        return Ok(());
+    }
+
+    let result = match check_job_has_media(job) {
+        Ok(()) => submit_print_job(job, api, state).await,
+        Err(e) => Err(e),
+    };
+
+    // Deferred jobs stay pending in the API and are picked up on the next poll or reconnect
+    if let Err(SpoolerError::Deferred(reason)) = &result {
+        warn!(job_id = job.id, reason = %reason, "Print job deferred, not submitting");
+        remember_skipped_job(job.id, &format!("deferred: {}", reason), state);
+        return Ok(());
+    }
+
+    if let Err(SpoolerError::Rejected(reason)) = &result {
+        warn!(job_id = job.id, reason = %reason, "Print job rejected, reporting as failed");
+        state.stats.record_job_failed();
+        let message = format!("{} (correlation id {})", reason, correlation_id);
+        if let Err(e) = update_print_job_status(
+            job.id,
+            Some(job),
+            None,
+            PrintJobStatus::Failed,
+            Some(&message),
+            api,
+            state,
+        )
+        .await
+        {
+            error!(job_id = job.id, error = %e, "Failed to report rejected job as failed, queued for retry");
+        }
+    }
+
+    result
+}
+
+/// Add a job left pending to the skipped list (see `skipped_jobs.json`)
+fn remember_skipped_job(job_id: u32, reason: &str, state: &AppState) {
+    let mut skipped = state
+        .skipped_jobs
+        .lock()
+        .expect("Failed to acquire skipped_jobs lock");
+    record_skipped_job(&mut skipped, job_id, reason, unix_now());
+    save_skipped_jobs(&skipped);
+}
+
+/// Skip a job older than `max_job_age_hours`, or mark it failed with `stale_job_action: fail`
+async fn discard_stale_job(
+    job: &PrintJob,
+    max_age_hours: u64,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    match api.config().stale_job_action {
+        StaleJobAction::Skip => {
+            info!(
+                job_id = job.id,
+                created_at = %job.created_at,
+                "Skipping job older than max_job_age_hours"
+            );
+            remember_skipped_job(
+                job.id,
+                &format!("older than max_job_age_hours ({})", max_age_hours),
+                state,
+            );
+            Ok(())
+        }
+        StaleJobAction::Fail => {
+            warn!(
+                job_id = job.id,
+                created_at = %job.created_at,
+                "Job is older than max_job_age_hours, reporting as failed"
+            );
+            state.stats.record_job_failed();
+            let message = format!(
+                "Not printed: created {}, more than {} hours ago (max_job_age_hours)",
+                job.created_at, max_age_hours
+            );
+            update_print_job_status(
+                job.id,
+                Some(job),
+                None,
+                PrintJobStatus::Failed,
+                Some(&message),
+                api,
+                state,
+            )
+            .await
+        }
+    }
+}
+
+/// Whether a job created at `created_at` is more than `max_age_hours` old at `now`.
+///
+/// Always `false` without a limit or when `created_at` cannot be parsed, so a
+/// malformed timestamp never discards a job.
+pub fn is_job_stale(created_at: &str, max_age_hours: u64, now: u64) -> bool {
+    if max_age_hours == 0 {
+        return false;
+    }
+    parse_api_timestamp(created_at)
+        .is_some_and(|created| now.saturating_sub(created) > max_age_hours.saturating_mul(3600))
+}
+
+/// Parse an API timestamp into Unix seconds.
+///
+/// Accepts RFC 3339 (`2024-01-01T23:30:00.000000Z`, `...+01:00`) and
+/// `YYYY-MM-DD HH:MM:SS`, Laravel's default, read as UTC.
+pub fn parse_api_timestamp(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let secs = match DateTime::parse_from_rfc3339(value) {
+        Ok(timestamp) => timestamp.timestamp(),
+        Err(_) => NaiveDateTime::parse_from_str(value, API_TIMESTAMP_FORMAT)
+            .ok()?
+            .and_utc()
+            .timestamp(),
+    };
+    u64::try_from(secs).ok()
+}
+
+/// Short id tying together the log lines of one job's processing.
+///
+/// Combines the current time with a process-wide counter, so ids are unique
+/// within a process and unlikely to repeat across restarts.
+pub fn new_correlation_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:08x}{:04x}", unix_now() as u32, count as u16)
+}
+
+/// Download, print, and track job status — core print workflow.
+///
+/// Instead of immediately marking the job as completed, this submits to CUPS
+/// and registers the job as in-flight so the status checker can track it.
+async fn submit_print_job(job: &PrintJob, api: &FluxClient, state: &AppState) -> SpoolerResult<()> {
+    let config = api.config();
+    if job.target_printer.is_none()
+        && let Some(printer_id) = job_printer_id(job)
+        && known_system_name(&state.printers, printer_id).is_none()
+    {
+        handle_unknown_printer_id(job, printer_id, api, state).await?;
+    }
+    let printer_name = resolve_printer_name(job, &state.printers, config).await;
+
+    if printer_name == FILE_PRINTER_NAME {
+        return capture_to_file_printer(job, api, state).await;
+    }
+    if config.ipp_printers.contains_key(&printer_name) {
+        return submit_ipp_print_job(job, &printer_name, api, state).await;
+    }
+
+    let is_known = state
+        .printers
+        .lock()
+        .expect("Failed to acquire printers lock")
+        .contains_key(&printer_name);
+    let wait_secs = if is_known {
+        config.ready_wait_secs(&printer_name)
+    } else {
+        0
+    };
+
+    // Get printer with fallback
+    let printer = match wait_for_printer_ready(&printer_name, wait_secs).await {
+        // A printer picked by the operator is never swapped for another
+        Some(p) if job.target_printer.is_some() || config.fallback_printers.is_empty() => p,
+        Some(p) if printer_is_ready(&p) => p,
+        Some(_) => select_fallback_printer(job.id, &printer_name, config, state)?,
+        None => replacement_printer(job, &printer_name, config, state)?,
+    };
+
+    check_printer_enabled(&printer.system_name, &printer.name, state)?;
+    check_printer_can_accept(&printer, config)?;
+
+    let (temp_file, media_filename) = fetch_document(job, api).await?;
+
+    if config.separator_page {
+        print_separator_page(&printer, job);
+    }
+    if config.stamp_metadata {
+        print_metadata_page(&printer, job);
+    }
+
+    // Print file
+    let estimated_pages = estimate_document_pages(temp_file.path(), job, config);
+
+    let mut printer = printer;
+    let mut print_result = print_document(
+        &cups_backend(&printer),
+        &printer.name,
+        &printer.system_name,
+        job,
+        temp_file.path(),
+        media_filename.as_deref(),
+        config,
+    )
+    .await;
+
+    // The handle may be stale: the printer can be removed from CUPS between
+    // the lookup above and print_file. Resolve the job's printer once more.
+    let stale_handle_error = match &print_result {
+        Err(e) if get_printer_by_name(&printer.system_name).is_none() => Some(e.to_string()),
+        _ => None,
+    };
+    if let Some(error) = stale_handle_error {
+        warn!(
+            job_id = job.id,
+            printer = %printer.name,
+            error = %error,
+            "Printer was removed from CUPS while the job was processed, resolving the printer again"
+        );
+        // Re-run discovery so the replacement is picked from the printers installed now
+        if let Err(e) = sync_known_printers(state, api, false).await {
+            warn!(
+                job_id = job.id,
+                error = %e,
+                "Printer re-discovery failed, resolving from the known printers"
+            );
+        }
+        let replacement =
+            replacement_printer(job, &printer.system_name, config, state).and_then(|replacement| {
+                check_printer_enabled(&replacement.system_name, &replacement.name, state)?;
+                check_printer_can_accept(&replacement, config)?;
+                Ok(replacement)
+            });
+        match replacement {
+            Ok(replacement) => {
+                info!(
+                    job_id = job.id,
+                    removed_printer = %printer.name,
+                    printer = %replacement.name,
+                    "Retrying print on re-resolved printer"
+                );
+                printer = replacement;
+                print_result = print_document(
+                    &cups_backend(&printer),
+                    &printer.name,
+                    &printer.system_name,
+                    job,
+                    temp_file.path(),
+                    media_filename.as_deref(),
+                    config,
+                )
+                .await;
+            }
+            Err(e) => {
+                remove_temp_file(temp_file);
+                return Err(e);
+            }
+        }
+    }
+
+    // CUPS has read the file once print_file returns, so remove it right away
+    remove_temp_file(temp_file);
+    let cups_job_id = validate_cups_job_id(print_result?, &printer.name)?;
+
+    info!(
+        job_id = job.id,
+        cups_job_id,
+        printer = %printer.name,
+        "Print job submitted to CUPS"
+    );
+    track_submitted_job(
+        job,
+        cups_job_id,
+        &printer.system_name,
+        estimated_pages,
+        api,
+        state,
+    )
+    .await;
+    Ok(())
+}
+
+/// Send a job for a printer listed in `ipp_printers` straight to it over IPP,
+/// bypassing CUPS.
+///
+/// Separator and metadata pages are CUPS-only and not printed here.
+async fn submit_ipp_print_job(
+    job: &PrintJob,
+    system_name: &str,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    let config = api.config();
+    check_printer_enabled(system_name, system_name, state)?;
+
+    let (temp_file, media_filename) = fetch_document(job, api).await?;
+    let estimated_pages = estimate_document_pages(temp_file.path(), job, config);
+    let backend = backend_for(system_name, config, api.http_client());
+    let print_result = print_document(
+        backend.as_ref(),
+        system_name,
+        system_name,
+        job,
+        temp_file.path(),
+        media_filename.as_deref(),
+        config,
+    )
+    .await;
+    remove_temp_file(temp_file);
+    let printer_job_id = validate_cups_job_id(print_result?, system_name)?;
+
+    info!(
+        job_id = job.id,
+        cups_job_id = printer_job_id,
+        printer = %system_name,
+        "Print job submitted over IPP"
+    );
+    track_submitted_job(
+        job,
+        printer_job_id,
+        system_name,
+        estimated_pages,
+        api,
+        state,
+    )
+    .await;
+    Ok(())
+}
+
+/// Reject a job for a printer disabled in `printers.json`
+fn check_printer_enabled(
+    system_name: &str,
+    display_name: &str,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    let is_disabled = state
+        .printers
+        .lock()
+        .expect("Failed to acquire printers lock")
+        .get(system_name)
+        .is_some_and(|p| !p.enabled);
+    if is_disabled {
+        return Err(SpoolerError::Rejected(format!(
+            "Printer '{}' is disabled",
+            display_name
+        )));
+    }
+    Ok(())
+}
+
+/// The job's document in a temp file, with its `Content-Disposition` file name.
+///
+/// Inline content is used when the API embedded the document, otherwise it is
+/// downloaded; `pre_print_command` runs on it when enabled.
+async fn fetch_document(
+    job: &PrintJob,
+    api: &FluxClient,
+) -> SpoolerResult<(NamedTempFile, Option<String>)> {
+    let config = api.config();
+    let (temp_file, media_filename) = match &job.content_base64 {
+        Some(encoded) => (inline_content_file(job, encoded, config)?, None),
+        None => {
+            let media = download_file(api, job.media_id).await?;
+            (media.file, media.filename)
+        }
+    };
+    let temp_file = if config.pre_print_command_enabled {
+        let transformed = run_pre_print_command(config, job.id, temp_file.path()).await;
+        remove_temp_file(temp_file);
+        transformed?
+    } else {
+        temp_file
+    };
+    Ok((temp_file, media_filename))
+}
+
+/// Estimated pages of the document at `path` times the job's copies
+fn estimate_document_pages(path: &std::path::Path, job: &PrintJob, config: &Config) -> u64 {
+    let copies = config.effective_copies(job.quantity, &job.size);
+    match std::fs::read(path) {
+        Ok(document) => estimate_pages(&document, copies),
+        Err(_) => u64::from(copies.max(1)),
+    }
+}
+
+/// Record a submitted job's consumption, report it to the API as queued (or
+/// held) and register it as in-flight for the status checker to track
+async fn track_submitted_job(
+    job: &PrintJob,
+    cups_job_id: u64,
+    system_name: &str,
+    estimated_pages: u64,
+    api: &FluxClient,
+    state: &AppState,
+) {
+    let config = api.config();
+    info!(
+        job_id = job.id,
+        printer = %system_name,
+        pages = estimated_pages,
+        "Estimated media consumption"
+    );
+    {
+        let mut totals = state
+            .consumption
+            .lock()
+            .expect("Failed to acquire consumption lock");
+        record_consumption(&mut totals, system_name, estimated_pages);
+        save_consumption(&totals);
+    }
+
+    // Update API: mark as queued (or held) with cups_job_id
+    let submitted_status = if config.holds_jobs(system_name) {
+        PrintJobStatus::Held
+    } else {
+        PrintJobStatus::Queued
+    };
+    match update_print_job_status(
+        job.id,
+        Some(job),
+        Some(cups_job_id),
+        submitted_status.clone(),
+        None,
+        api,
+        state,
+    )
+    .await
+    {
+        Ok(_) => info!(job_id = job.id, cups_job_id, status = %submitted_status, "Status updated"),
+        Err(e) => warn!(
+            job_id = job.id,
+            status = %submitted_status,
+            error = %e,
+            "Failed to update job status, queued for retry"
+        ),
+    }
+
+    // Register as in-flight for the status checker to track
+    let in_flight = InFlightJob {
+        api_job_id: job.id,
+        cups_job_id,
+        printer_name: system_name.to_string(),
+        submitted_at: Instant::now(),
+        last_status: submitted_status,
+        job: job_snapshot(job),
+    };
+
+    state
+        .in_flight_jobs
+        .lock()
+        .expect("Failed to acquire in_flight_jobs lock")
+        .push(in_flight);
+}
+
+/// Pick the printer for a job whose requested printer is not installed.
+///
+/// A printer picked by the operator is never swapped, so the job is rejected;
+/// otherwise `fallback_printers` are tried, then the default printer.
+fn replacement_printer(
+    job: &PrintJob,
+    printer_name: &str,
+    config: &Config,
+    state: &AppState,
+) -> SpoolerResult<printers::common::base::printer::Printer> {
+    if job.target_printer.is_some() {
+        return Err(SpoolerError::Rejected(format!(
+            "Printer '{}' not found",
+            printer_name
+        )));
+    }
+    if !config.fallback_printers.is_empty() {
+        return select_fallback_printer(job.id, printer_name, config, state);
+    }
+    let default_printer = default_system_printer(config).ok_or("No printers available")?;
+    warn!(
+        job_id = job.id,
+        requested_printer = %printer_name,
+        fallback_printer = %default_printer.name,
+        "Printer not found, using default"
+    );
+    Ok(default_printer)
+}
 
-    if !file_response.status().is_success() {
-        return Err(format!(
-            "Failed to download file for media ID {}: {}",
-            media_id,
-            file_response.status()
-        )
-        .into());
+/// The CUPS backend for a printer found in CUPS
+fn cups_backend(printer: &printers::common::base::printer::Printer) -> CupsBackend {
+    CupsBackend {
+        system_name: printer.system_name.clone(),
     }
-
-    let file_content = file_response.bytes().await?;
-
-    let mut temp_file = NamedTempFile::new()?;
-    temp_file.write_all(&file_content)?;
-
-    Ok(temp_file)
 }
 
-/// Download, print, and track job status — core print workflow.
+/// Submit the job's document at `path` through `backend` with its name and options.
 ///
-/// Instead of immediately marking the job as completed, this submits to CUPS
-/// and registers the job as in-flight so the status checker can track it.
-async fn process_print_job(
+/// The job is named after `media_filename` when there is one and
+/// `job_name_from_filename` is on, otherwise by `job_name_template`.
+async fn print_document(
+    backend: &dyn PrinterBackend,
+    printer_name: &str,
+    system_name: &str,
     job: &PrintJob,
-    http_client: &Client,
+    path: &std::path::Path,
+    media_filename: Option<&str>,
     config: &Config,
-    in_flight_jobs: &InFlightJobs,
-) -> SpoolerResult<()> {
-    let printer_name = resolve_printer_name(job).await;
-
-    // Download file
-    let temp_file = download_file(http_client, config, job.media_id).await?;
-
-    // Get printer with fallback
-    let printer = match get_printer_by_name(&printer_name) {
-        Some(p) => p,
-        None => {
-            let mut printers = get_printers();
-            let default_printer = printers.pop().ok_or("No printers available")?;
-            warn!(
-                job_id = job.id,
-                requested_printer = %printer_name,
-                fallback_printer = %default_printer.name,
-                "Printer not found, using default"
-            );
-            default_printer
-        }
+) -> SpoolerResult<u64> {
+    let job_name = match media_filename {
+        Some(filename) if config.job_name_from_filename => filename.to_string(),
+        _ => config.render_job_name(&[
+            ("job_id", &job.id.to_string()),
+            ("printer", printer_name),
+            ("size", &job.size),
+            ("created_by", &user_id_label(job.created_by)),
+            ("updated_by", &user_id_label(job.updated_by)),
+        ]),
     };
+    let properties = job_print_properties(job, system_name, config);
+    backend.submit(path, &job_name, &properties).await
+}
 
-    // Print file
-    let temp_path = temp_file.path().to_str().ok_or("Invalid temp file path")?;
+/// A user id for the job name; empty when the API did not send one
+fn user_id_label(user_id: Option<u32>) -> String {
+    user_id.map(|id| id.to_string()).unwrap_or_default()
+}
 
-    let job_options = PrinterJobOptions {
-        name: Some(&format!("Print Job {}", job.id)),
-        ..PrinterJobOptions::none()
+/// Write the job's document to `file_printer_dir` instead of printing it,
+/// then report the job as completed.
+async fn capture_to_file_printer(
+    job: &PrintJob,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    let config = api.config();
+    let Some(dir) = &config.file_printer_dir else {
+        return Err(SpoolerError::Rejected(format!(
+            "Job targets the '{}' printer but file_printer_dir is not set",
+            FILE_PRINTER_NAME
+        )));
     };
 
-    let cups_job_id = printer
-        .print_file(temp_path, job_options)
-        .map_err(|e| format!("Failed to print: {:?}", e))?;
+    // Same document as a real printer gets, after checks and `pre_print_command`
+    let (temp_file, media_filename) = fetch_document(job, api).await?;
+    let media_filename = media_filename.filter(|_| config.job_name_from_filename);
+
+    let mut header = Vec::with_capacity(4);
+    std::fs::File::open(temp_file.path())?
+        .take(4)
+        .read_to_end(&mut header)?;
+    let path = std::path::Path::new(dir).join(file_printer_file_name(
+        job.id,
+        unix_now(),
+        &header,
+        media_filename.as_deref(),
+    ));
+    std::fs::create_dir_all(dir)?;
+    let copied = std::fs::copy(temp_file.path(), &path);
+    remove_temp_file(temp_file);
+    let bytes = copied?;
 
     info!(
         job_id = job.id,
-        cups_job_id,
-        printer = %printer.name,
-        "Print job submitted to CUPS"
+        path = %path.display(),
+        bytes,
+        "Print job written to file printer"
     );
 
-    // Update API: mark as queued with cups_job_id
-    match update_print_job_status(
+    state.stats.record_job_printed();
+    if let Err(e) = update_print_job_status(
         job.id,
-        Some(cups_job_id),
-        PrintJobStatus::Queued,
+        Some(job),
         None,
-        http_client,
-        config,
+        PrintJobStatus::Completed,
+        None,
+        api,
+        state,
     )
     .await
     {
-        Ok(_) => info!(job_id = job.id, cups_job_id, "Status updated to queued"),
-        Err(e) => warn!(job_id = job.id, error = %e, "Failed to update job status to queued"),
+        warn!(
+            job_id = job.id,
+            error = %e,
+            "Failed to update job status to completed, queued for retry"
+        );
     }
+    Ok(())
+}
 
-    // Register as in-flight for the status checker to track
-    let in_flight = InFlightJob {
-        api_job_id: job.id,
-        cups_job_id,
-        printer_name: printer.system_name.clone(),
-        submitted_at: Instant::now(),
-        last_status: PrintJobStatus::Queued,
+/// File name for a document captured by the file printer: `job-<id>-<unix time>`
+/// followed by the download's file name, or by `.pdf` for PDF content and `.bin` otherwise
+pub fn file_printer_file_name(
+    job_id: u32,
+    timestamp: u64,
+    header: &[u8],
+    media_filename: Option<&str>,
+) -> String {
+    if let Some(filename) = media_filename {
+        return format!("job-{}-{}-{}", job_id, timestamp, filename);
+    }
+    let extension = if header.starts_with(b"%PDF") {
+        "pdf"
+    } else {
+        "bin"
     };
+    format!("job-{}-{}.{}", job_id, timestamp, extension)
+}
 
-    in_flight_jobs
-        .lock()
-        .expect("Failed to acquire in_flight_jobs lock")
-        .push(in_flight);
-
+/// Check that a job has a document to print before anything is downloaded.
+///
+/// A job without inline content and with `media_id` 0 would only 404 on
+/// `/api/media/private/0`; it is [`SpoolerError::Rejected`] as missing media
+/// instead, so a data problem is not mistaken for a failed download.
+pub fn check_job_has_media(job: &PrintJob) -> SpoolerResult<()> {
+    if job.media_id == 0 && job.content_base64.is_none() {
+        return Err(SpoolerError::Rejected(
+            "Missing media: the job has no media_id and no inline content".to_string(),
+        ));
+    }
     Ok(())
 }
 
-/// Fetch print jobs from the API and process them
-pub async fn fetch_print_jobs(
-    http_client: &Client,
-    config: &mut Config,
-    in_flight_jobs: &InFlightJobs,
-) -> SpoolerResult<Vec<PrintJob>> {
-    let jobs_url = format!(
-        "{}/api/print-jobs?filter[is_completed]=false&include=printer",
-        config.flux_url
+/// Check the job id CUPS returned for a submission.
+///
+/// Some backends report success with job id 0 when nothing was queued; such a
+/// submission is [`SpoolerError::Rejected`] so the job is marked failed rather
+/// than tracked as if it were printing.
+pub fn validate_cups_job_id(cups_job_id: u64, printer_name: &str) -> SpoolerResult<u64> {
+    if cups_job_id == 0 {
+        return Err(SpoolerError::Rejected(format!(
+            "CUPS accepted the job for printer '{}' but returned no job id",
+            printer_name
+        )));
+    }
+    Ok(cups_job_id)
+}
+
+/// Look up a CUPS printer, giving it up to `wait_secs` to reappear, resume
+/// and accept jobs again after a transient USB or network blip.
+///
+/// Returns the printer as last seen, which may still be missing or not ready;
+/// the caller then falls back or defers as usual.
+async fn wait_for_printer_ready(
+    printer_name: &str,
+    wait_secs: u64,
+) -> Option<printers::common::base::printer::Printer> {
+    if wait_secs == 0 {
+        return get_printer_by_name(printer_name);
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(wait_secs);
+    loop {
+        let printer = get_printer_by_name(printer_name);
+        let is_ready = printer.as_ref().is_some_and(printer_is_ready);
+        let now = Instant::now();
+        if is_ready || now >= deadline {
+            return printer;
+        }
+        debug!(printer = %printer_name, "Printer not ready, waiting");
+        time::sleep(Duration::from_secs(1).min(deadline - now)).await;
+    }
+}
+
+/// Whether a printer is running and its CUPS queue accepts jobs
+fn printer_is_ready(printer: &printers::common::base::printer::Printer) -> bool {
+    printer.state != PrinterState::PAUSED && printer_accepting_jobs(&printer.system_name)
+}
+
+/// Walk `fallback_printers` in order and return the first one that is ready
+/// and not disabled.
+///
+/// Deferred while a listed printer exists but is not ready yet; rejected
+/// when none of them is installed.
+fn select_fallback_printer(
+    job_id: u32,
+    requested: &str,
+    config: &Config,
+    state: &AppState,
+) -> SpoolerResult<printers::common::base::printer::Printer> {
+    info!(
+        job_id,
+        requested_printer = %requested,
+        fallback_printers = ?config.fallback_printers,
+        "Printer unavailable, trying fallback printers"
+    );
+    let mut any_installed = false;
+    for name in &config.fallback_printers {
+        if name == requested {
+            continue;
+        }
+        let Some(printer) = get_printer_by_name(name) else {
+            info!(job_id, fallback_printer = %name, "Fallback printer not found, trying next");
+            continue;
+        };
+        any_installed = true;
+        let is_disabled = state
+            .printers
+            .lock()
+            .expect("Failed to acquire printers lock")
+            .get(&printer.system_name)
+            .is_some_and(|p| !p.enabled);
+        if is_disabled {
+            info!(job_id, fallback_printer = %name, "Fallback printer is disabled, trying next");
+        } else if !printer_is_ready(&printer) {
+            info!(job_id, fallback_printer = %name, "Fallback printer not ready, trying next");
+        } else {
+            warn!(
+                job_id,
+                requested_printer = %requested,
+                fallback_printer = %printer.name,
+                "Printer unavailable, using fallback printer"
+            );
+            return Ok(printer);
+        }
+    }
+
+    let message = format!(
+        "Printer '{}' and every fallback printer ({}) are unavailable",
+        requested,
+        config.fallback_printers.join(", ")
     );
+    if any_installed {
+        Err(SpoolerError::Deferred(message))
+    } else {
+        Err(SpoolerError::Rejected(message))
+    }
+}
 
-    debug!(url = %jobs_url, "Fetching print jobs");
+/// Defer jobs for a stopped or rejecting printer, or one whose CUPS queue is already too deep.
+fn check_printer_can_accept(
+    printer: &printers::common::base::printer::Printer,
+    config: &Config,
+) -> SpoolerResult<()> {
+    if printer.state == PrinterState::PAUSED {
+        return Err(SpoolerError::Deferred(format!(
+            "Printer '{}' is stopped ({})",
+            printer.name,
+            printer.state_reasons.join(", ")
+        )));
+    }
 
-    let response = with_auth_header(http_client.get(&jobs_url), config)
-        .header("Accept", "application/json")
-        .send()
-        .await?;
+    // CUPS would drop jobs sent to a queue that rejects them, so wait until it accepts again
+    if !printer_accepting_jobs(&printer.system_name) {
+        return Err(SpoolerError::Deferred(format!(
+            "Printer '{}' is not accepting jobs",
+            printer.name
+        )));
+    }
 
-    if !response.status().is_success() {
-        return Err(format!("Failed to fetch print jobs: {}", response.status()).into());
+    if config.max_queue_depth > 0 {
+        let depth = printer.get_active_jobs().len();
+        if depth >= config.max_queue_depth {
+            return Err(SpoolerError::Deferred(format!(
+                "Printer '{}' already has {} queued jobs (max_queue_depth {})",
+                printer.name, depth, config.max_queue_depth
+            )));
+        }
     }
 
-    let response_text = response.text().await?;
+    Ok(())
+}
 
-    let parsed_response: PrintJobResponse = serde_json::from_str(&response_text).map_err(|e| {
-        error!(error = %e, "Failed to parse print jobs response");
-        format!("Failed to parse API response: {}", e)
-    })?;
+/// Print a banner page identifying the job ahead of the document.
+///
+/// Failures are only logged so a broken banner never holds up the real job.
+fn print_separator_page(printer: &printers::common::base::printer::Printer, job: &PrintJob) {
+    let lines = [
+        format!("Print Job {}", job.id),
+        format!("Printer: {}", printer.name),
+        format!("Size: {}", job.size),
+        format!("Printed at: {} UTC", chrono_now_utc()),
+    ];
+    print_text_page(printer, job, "Separator", &lines);
+}
+
+/// One-line header identifying who ordered a job, how many copies and when
+pub fn metadata_header_line(job: &PrintJob) -> String {
+    let user = job
+        .user_id
+        .map_or_else(|| "-".to_string(), |id| id.to_string());
+    let created_at = if job.created_at.is_empty() {
+        "-"
+    } else {
+        job.created_at.as_str()
+    };
+    format!(
+        "Job {} | User {} | Quantity {} | Created {}",
+        job.id,
+        user,
+        job.quantity.max(1),
+        created_at
+    )
+}
+
+/// Print a header page with the job metadata ahead of the document.
+///
+/// Like the separator page, failures are only logged.
+fn print_metadata_page(printer: &printers::common::base::printer::Printer, job: &PrintJob) {
+    print_text_page(printer, job, "Header", &[metadata_header_line(job)]);
+}
+
+/// Submit a generated text page belonging to `job`, logging the outcome
+fn print_text_page(
+    printer: &printers::common::base::printer::Printer,
+    job: &PrintJob,
+    kind: &str,
+    lines: &[String],
+) {
+    let job_name = format!("{} for Print Job {}", kind, job.id);
+    let options = PrinterJobOptions {
+        name: Some(&job_name),
+        ..PrinterJobOptions::none()
+    };
 
-    let jobs = parsed_response.data.data;
+    // Not `Printer::print`: it leaves its own temp file behind for every page
+    let result = new_temp_file()
+        .map_err(|e| e.to_string())
+        .and_then(|mut temp_file| {
+            let submitted = match temp_file.write_all(&text_page_pdf(lines)) {
+                Ok(()) => match temp_file.path().to_str() {
+                    Some(path) => printer
+                        .print_file(path, options)
+                        .map_err(|e| format!("{:?}", e)),
+                    None => Err("Invalid temp file path".to_string()),
+                },
+                Err(e) => Err(e.to_string()),
+            };
+            // CUPS has read the file once print_file returns
+            remove_temp_file(temp_file);
+            submitted
+        });
+
+    match result {
+        Ok(cups_job_id) => debug!(job_id = job.id, cups_job_id, kind, "Text page submitted"),
+        Err(e) => warn!(job_id = job.id, error = %e, kind, "Failed to print text page"),
+    }
+}
+
+/// Fetch print jobs from the API and process them
+pub async fn fetch_print_jobs(api: &FluxClient, state: &AppState) -> SpoolerResult<Vec<PrintJob>> {
+    let jobs = api.list_jobs(false).await.map_err(|e| {
+        error!(error = %e, "Failed to fetch print jobs");
+        e
+    })?;
 
     if jobs.is_empty() {
         debug!("No print jobs found for this instance");
@@ -457,7 +1795,7 @@ pub async fn fetch_print_jobs(
             continue;
         }
 
-        if let Err(e) = process_print_job(job, http_client, config, in_flight_jobs).await {
+        if let Err(e) = process_print_job(job, api, state).await {
             error!(job_id = job.id, error = %e, "Failed to process print job");
         }
     }
@@ -465,49 +1803,151 @@ pub async fn fetch_print_jobs(
     Ok(jobs)
 }
 
-/// Single print job response from API (when fetching by ID)
-#[derive(serde::Deserialize, Debug)]
-struct SinglePrintJobResponse {
-    #[allow(dead_code)]
-    status: u16,
-    data: PrintJob,
-}
-
 /// Fetch a single print job by ID from the API and print it
 pub async fn fetch_and_print_job_by_id(
     job_id: u32,
-    http_client: &Client,
-    config: &Config,
-    in_flight_jobs: &InFlightJobs,
+    api: &FluxClient,
+    state: &AppState,
 ) -> SpoolerResult<()> {
-    let job_url = format!(
-        "{}/api/print-jobs/{}?include=printer",
-        config.flux_url, job_id
-    );
+    info!(job_id, "Fetching print job by ID");
 
-    info!(job_id, url = %job_url, "Fetching print job by ID");
+    let job = api.get_job(job_id).await?;
+    print_fetched_job(&job, api, state).await
+}
 
-    let response = with_auth_header(http_client.get(&job_url), config)
-        .header("Accept", "application/json")
-        .send()
-        .await?;
+/// Fetch a job from the dead-letter store and print it again.
+///
+/// The job is printed although the API lists it as completed (failed); its
+/// next status update reopens it. The job leaves the store once it completes.
+pub async fn retry_dead_letter(
+    job_id: u32,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    info!(job_id, "Fetching failed print job for a retry");
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!(
-            "Failed to fetch print job {}: {} - {}",
-            job_id, status, error_text
-        )
-        .into());
+    let job = api.get_job(job_id).await?;
+    process_print_job(&job, api, state).await
+}
+
+/// Fetch a job and print it on `printer` (a CUPS name or [`FILE_PRINTER_NAME`]),
+/// ignoring the printer the job names for this print only
+pub async fn fetch_and_print_job_on_printer(
+    job_id: u32,
+    printer: &str,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    info!(job_id, printer = %printer, "Fetching print job by ID for another printer");
+
+    let mut job = api.get_job(job_id).await?;
+    job.target_printer = Some(printer.to_string());
+    print_fetched_job(&job, api, state).await
+}
+
+/// Fetch and print a job announced over the WebSocket.
+///
+/// If the API cannot be reached the id is queued in `pending_fetch.json` and
+/// retried by [`drain_pending_fetches`], so the job is not lost when no
+/// further event arrives for it.
+pub async fn fetch_and_print_announced_job(
+    job_id: u32,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
+    info!(job_id, "Fetching print job by ID");
+
+    let job = match api.get_job(job_id).await {
+        Ok(job) => job,
+        Err(e) if is_retryable_fetch_error(&e) => {
+            let mut queue = state
+                .pending_fetches
+                .lock()
+                .expect("Failed to acquire pending_fetches lock");
+            enqueue_pending_fetch(&mut queue, job_id, unix_now());
+            save_pending_fetches(&queue);
+            warn!(job_id, error = %e, "Failed to fetch announced job, queued for retry");
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    print_fetched_job(&job, api, state).await
+}
+
+/// Retry fetching announced jobs whose backoff delay has elapsed
+pub async fn drain_pending_fetches(api: &FluxClient, state: &AppState) {
+    let now = unix_now();
+    let due: Vec<PendingFetch> = {
+        let mut queue = state
+            .pending_fetches
+            .lock()
+            .expect("Failed to acquire pending_fetches lock");
+        let (due, waiting) = queue.drain(..).partition(|f| f.is_due(now));
+        *queue = waiting;
+        due
+    };
+
+    if due.is_empty() {
+        return;
+    }
+
+    let mut failed = Vec::new();
+    let mut fetched = Vec::new();
+    for mut fetch in due {
+        match api.get_job(fetch.job_id).await {
+            Ok(job) => {
+                info!(
+                    job_id = fetch.job_id,
+                    attempts = fetch.attempts,
+                    "Fetched queued print job"
+                );
+                fetched.push(job);
+            }
+            Err(e) if is_retryable_fetch_error(&e) && fetch.schedule_retry(now) => {
+                warn!(
+                    job_id = fetch.job_id,
+                    attempts = fetch.attempts,
+                    retry_in_secs = fetch.next_attempt_at - now,
+                    error = %e,
+                    "Queued job fetch failed again"
+                );
+                failed.push(fetch);
+            }
+            Err(e) => error!(
+                job_id = fetch.job_id,
+                attempts = fetch.attempts,
+                error = %e,
+                "Giving up on fetching queued print job"
+            ),
+        }
     }
 
-    let response_text = response.text().await?;
-    let parsed: SinglePrintJobResponse = serde_json::from_str(&response_text)
-        .map_err(|e| format!("Failed to parse job response: {}", e))?;
+    {
+        let mut queue = state
+            .pending_fetches
+            .lock()
+            .expect("Failed to acquire pending_fetches lock");
+        for fetch in failed {
+            if !queue.iter().any(|f| f.job_id == fetch.job_id) {
+                queue.push(fetch);
+            }
+        }
+        save_pending_fetches(&queue);
+    }
 
-    let job = parsed.data;
+    for job in fetched {
+        if let Err(e) = print_fetched_job(&job, api, state).await {
+            error!(job_id = job.id, error = %e, "Error printing queued print job");
+        }
+    }
+}
 
+/// Print a job fetched by id unless the API already has it as completed
+async fn print_fetched_job(
+    job: &PrintJob,
+    api: &FluxClient,
+    state: &AppState,
+) -> SpoolerResult<()> {
     info!(
         job_id = job.id,
         media_id = job.media_id,
@@ -521,39 +1961,63 @@ pub async fn fetch_and_print_job_by_id(
         return Ok(());
     }
 
-    process_print_job(&job, http_client, config, in_flight_jobs).await
+    process_print_job(job, api, state).await
 }
 
 // ── Background tasks ────────────────────────────────────────────────────────
 
+/// Interval (minutes) until the next poll.
+///
+/// A poll that found jobs resets to `min`; an empty one doubles the current
+/// interval, capped at `max`. With `max <= min` the interval stays at `min`.
+pub fn next_poll_interval(current: u64, found_jobs: bool, min: u64, max: u64) -> u64 {
+    if found_jobs || max <= min {
+        return min;
+    }
+    current.max(1).saturating_mul(2).clamp(min, max)
+}
+
 /// Background task to periodically check for print jobs (polling mode)
+///
+/// The interval adapts between `job_check_interval` and `job_check_max_interval`.
 pub async fn job_checker_task(
-    config: Arc<RwLock<Config>>,
+    config: SharedConfig,
     http_client: Client,
     cancel_token: CancellationToken,
-    in_flight_jobs: InFlightJobs,
+    state: AppState,
 ) {
+    let mut interval = read_config(&config).await.job_check_interval;
+
     loop {
-        let mut config_clone = read_config(&config);
+        let config_clone = read_config(&config).await;
 
         if !config_clone.reverb_disabled {
             info!("Polling is disabled. Using Reverb WebSockets instead");
             return;
         }
 
-        let interval = config_clone.job_check_interval;
+        let min_interval = config_clone.job_check_interval;
+        let max_interval = config_clone.job_check_max_interval;
+
+        let api = FluxClient::new(http_client.clone(), config_clone);
 
-        match fetch_print_jobs(&http_client, &mut config_clone, &in_flight_jobs).await {
+        drain_pending_status_updates(&api, &state).await;
+        drain_pending_fetches(&api, &state).await;
+
+        match fetch_print_jobs(&api, &state).await {
             Ok(jobs) => {
                 if !jobs.is_empty() {
                     info!(job_count = jobs.len(), "Processed print jobs");
                 }
-
-                if let Ok(mut guard) = config.write() {
-                    guard.flux_api_token = config_clone.flux_api_token;
-                }
+                interval =
+                    next_poll_interval(interval, !jobs.is_empty(), min_interval, max_interval);
+                trace!(interval_minutes = interval, "Next poll scheduled");
+            }
+            Err(e) => {
+                error!(error = %e, "Error fetching print jobs");
+                // Keep the current interval, but respect a changed configuration
+                interval = interval.clamp(min_interval, max_interval.max(min_interval));
             }
-            Err(e) => error!(error = %e, "Error fetching print jobs"),
         }
 
         tokio::select! {
@@ -566,21 +2030,64 @@ pub async fn job_checker_task(
     }
 }
 
+/// Background task that prints the jobs held back during quiet hours once the window ends.
+///
+/// Polling mode would find them on its next poll anyway, but WebSocket mode
+/// only hears about new jobs, so the catch-up runs here for both.
+pub async fn quiet_hours_task(
+    config: SharedConfig,
+    http_client: Client,
+    cancel_token: CancellationToken,
+    state: AppState,
+) {
+    let mut was_quiet = false;
+
+    loop {
+        let config_clone = read_config(&config).await;
+        let is_quiet = config_clone
+            .quiet_hours
+            .as_ref()
+            .is_some_and(|quiet_hours| quiet_hours.is_quiet_at(unix_now()));
+
+        if is_quiet && !was_quiet {
+            info!("Quiet hours started, only urgent jobs are printed");
+        } else if was_quiet && !is_quiet {
+            info!("Quiet hours ended, printing pending jobs");
+            catch_up_pending_jobs(
+                FluxClient::new(http_client.clone(), config_clone),
+                state.clone(),
+            )
+            .await;
+        }
+        was_quiet = is_quiet;
+
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                info!("Quiet hours task shutting down");
+                return;
+            }
+            _ = time::sleep(Duration::from_secs(60)) => {}
+        }
+    }
+}
+
 /// Background task that polls CUPS for the final status of in-flight print jobs.
 ///
 /// Runs every 15 seconds and checks each in-flight job against CUPS job history.
 /// When a job reaches a terminal state (completed, cancelled, aborted) or times out,
 /// the API is updated and the job is removed from the in-flight tracker.
 pub async fn job_status_checker_task(
-    config: Arc<RwLock<Config>>,
+    config: SharedConfig,
     http_client: Client,
     cancel_token: CancellationToken,
-    in_flight_jobs: InFlightJobs,
+    state: AppState,
 ) {
+    let in_flight_jobs = &state.in_flight_jobs;
+
     // Re-populate in-flight jobs from the API on startup
     {
-        let config_snapshot = read_config(&config);
-        match fetch_in_flight_jobs_from_api(&http_client, &config_snapshot).await {
+        let api = FluxClient::new(http_client.clone(), read_config(&config).await);
+        match fetch_in_flight_jobs_from_api(&api).await {
             Ok(api_jobs) => {
                 if !api_jobs.is_empty() {
                     let mut tracker = in_flight_jobs
@@ -592,11 +2099,11 @@ pub async fn job_status_checker_task(
                         };
 
                         // Resolve printer name for this job
-                        let printer_name = if let Some(ref p) = job.printer {
-                            p.name.clone()
-                        } else {
-                            get_default_printer_system_name()
-                        };
+                        let printer_name = job
+                            .printer
+                            .as_ref()
+                            .and_then(|p| p.name.clone())
+                            .unwrap_or_else(|| get_default_printer_system_name(api.config()));
 
                         // Only add if not already tracked
                         let already_tracked = tracker.iter().any(|j| j.api_job_id == job.id);
@@ -607,6 +2114,7 @@ pub async fn job_status_checker_task(
                                 printer_name,
                                 submitted_at: Instant::now(),
                                 last_status: job.status.clone().unwrap_or(PrintJobStatus::Queued),
+                                job: job_snapshot(job),
                             });
                         }
                     }
@@ -633,6 +2141,12 @@ pub async fn job_status_checker_task(
             _ = time::sleep(check_interval) => {}
         }
 
+        let api = FluxClient::new(http_client.clone(), read_config(&config).await);
+
+        // The polling task is not running in WebSocket mode, so retry here too
+        drain_pending_status_updates(&api, &state).await;
+        drain_pending_fetches(&api, &state).await;
+
         // Take a snapshot of in-flight jobs to avoid holding the lock during async work
         let snapshot: Vec<InFlightJob> = {
             let tracker = in_flight_jobs
@@ -649,35 +2163,11 @@ pub async fn job_status_checker_task(
             "Checking CUPS status for in-flight jobs"
         );
 
-        let config_snapshot = read_config(&config);
         let mut completed_ids: Vec<u32> = Vec::new();
 
         for job in &snapshot {
-            let printer_name = job.printer_name.clone();
-            let cups_job_id = job.cups_job_id;
-
-            // Query CUPS in a blocking task (CUPS FFI is not async-safe)
-            let cups_state = tokio::task::spawn_blocking(move || {
-                let printer = match get_printer_by_name(&printer_name) {
-                    Some(p) => p,
-                    None => return None,
-                };
-
-                // Check active jobs first, then history
-                let active = printer.get_active_jobs();
-                if let Some(cups_job) = active.iter().find(|j| j.id == cups_job_id) {
-                    return Some(cups_job.state.clone());
-                }
-
-                let history = printer.get_job_history();
-                history
-                    .iter()
-                    .find(|j| j.id == cups_job_id)
-                    .map(|j| j.state.clone())
-            })
-            .await;
-
-            let cups_state = match cups_state {
+            let backend = backend_for(&job.printer_name, api.config(), api.http_client());
+            let cups_state = match backend.job_state(job.cups_job_id).await {
                 Ok(state) => state,
                 Err(e) => {
                     error!(
@@ -691,7 +2181,7 @@ pub async fn job_status_checker_task(
 
             match cups_state {
                 Some(cups_state) => {
-                    let new_status = PrintJobStatus::from(cups_state);
+                    let new_status = cups_job_status(cups_state, &job.last_status);
 
                     // Skip if status hasn't changed
                     if new_status == job.last_status {
@@ -719,11 +2209,12 @@ pub async fn job_status_checker_task(
 
                     match update_print_job_status(
                         job.api_job_id,
+                        Some(&job.job),
                         None,
                         new_status.clone(),
                         error_msg,
-                        &http_client,
-                        &config_snapshot,
+                        &api,
+                        &state,
                     )
                     .await
                     {
@@ -739,12 +2230,18 @@ pub async fn job_status_checker_task(
                                 job_id = job.api_job_id,
                                 status = %new_status,
                                 error = %e,
-                                "Failed to update status in API"
+                                "Failed to update status in API, queued for retry"
                             );
                         }
                     }
 
                     if new_status.is_terminal() {
+                        remember_finished_job(job, new_status.clone(), &api, &state);
+                        if new_status == PrintJobStatus::Completed {
+                            state.stats.record_job_printed();
+                        } else {
+                            state.stats.record_job_failed();
+                        }
                         completed_ids.push(job.api_job_id);
                     } else {
                         // Update last_status in the tracker for non-terminal transitions
@@ -768,13 +2265,16 @@ pub async fn job_status_checker_task(
                             elapsed_secs = elapsed,
                             "CUPS job disappeared from queue after timeout"
                         );
+                        state.stats.record_job_failed();
+                        remember_finished_job(job, PrintJobStatus::Failed, &api, &state);
                         match update_print_job_status(
                             job.api_job_id,
+                            Some(&job.job),
                             None,
                             PrintJobStatus::Failed,
                             Some("Job disappeared from CUPS queue"),
-                            &http_client,
-                            &config_snapshot,
+                            &api,
+                            &state,
                         )
                         .await
                         {
@@ -788,7 +2288,7 @@ pub async fn job_status_checker_task(
                                 error!(
                                     job_id = job.api_job_id,
                                     error = %e,
-                                    "Failed to update timeout status"
+                                    "Failed to update timeout status, queued for retry"
                                 );
                             }
                         }
@@ -819,3 +2319,152 @@ pub async fn job_status_checker_task(
         }
     }
 }
+
+/// Status to report for a CUPS job state.
+///
+/// CUPS shows both held and paused jobs as paused, which normally counts as
+/// queued; a job submitted with `hold_jobs` stays `held` until it is released.
+pub fn cups_job_status(
+    cups_state: PrinterJobState,
+    last_status: &PrintJobStatus,
+) -> PrintJobStatus {
+    match (cups_state, last_status) {
+        (PrinterJobState::PAUSED, PrintJobStatus::Held) => PrintJobStatus::Held,
+        (cups_state, _) => PrintJobStatus::from(cups_state),
+    }
+}
+
+/// Keep a finished job for the reconciliation pass, when it is enabled
+fn remember_finished_job(
+    job: &InFlightJob,
+    status: PrintJobStatus,
+    api: &FluxClient,
+    state: &AppState,
+) {
+    if api.config().reconcile_interval_mins == 0 {
+        return;
+    }
+    let mut finished = state
+        .finished_jobs
+        .lock()
+        .expect("Failed to acquire finished_jobs lock");
+    record_finished_job(
+        &mut finished,
+        FinishedJob {
+            api_job_id: job.api_job_id,
+            cups_job_id: job.cups_job_id,
+            printer_name: job.printer_name.clone(),
+            reported_status: status,
+            finished_at: unix_now(),
+        },
+    );
+    save_finished_jobs(&finished);
+}
+
+/// The status to report when CUPS's final state for a job differs from what was reported.
+///
+/// Only terminal CUPS states count; a job CUPS no longer knows keeps its status.
+pub fn reconciled_status(
+    reported: &PrintJobStatus,
+    cups_state: Option<PrinterJobState>,
+) -> Option<PrintJobStatus> {
+    let actual = PrintJobStatus::from(cups_state?);
+    (actual.is_terminal() && actual != *reported).then_some(actual)
+}
+
+/// Background task that compares recently finished jobs with the CUPS job
+/// history and corrects the status in the API (opt-in via `reconcile_interval_mins`).
+///
+/// This catches jobs reported failed after vanishing from the queue that CUPS
+/// later lists as completed, and ones aborted at the device after being reported.
+pub async fn reconciliation_task(
+    config: SharedConfig,
+    http_client: Client,
+    cancel_token: CancellationToken,
+    state: AppState,
+) {
+    loop {
+        let interval_mins = read_config(&config).await.reconcile_interval_mins;
+        // Re-check the setting every minute while disabled
+        let sleep_mins = if interval_mins == 0 { 1 } else { interval_mins };
+
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                info!("Reconciliation task shutting down");
+                return;
+            }
+            _ = time::sleep(Duration::from_secs(sleep_mins * 60)) => {}
+        }
+
+        let config_clone = read_config(&config).await;
+        if config_clone.reconcile_interval_mins == 0 {
+            continue;
+        }
+        let api = FluxClient::new(http_client.clone(), config_clone);
+        reconcile_finished_jobs(&api, &state).await;
+    }
+}
+
+/// One reconciliation pass over the finished jobs
+async fn reconcile_finished_jobs(api: &FluxClient, state: &AppState) {
+    let snapshot: Vec<FinishedJob> = {
+        let mut finished = state
+            .finished_jobs
+            .lock()
+            .expect("Failed to acquire finished_jobs lock");
+        if prune_finished_jobs(&mut finished, unix_now()) {
+            save_finished_jobs(&finished);
+        }
+        finished.clone()
+    };
+    if snapshot.is_empty() {
+        return;
+    }
+    trace!(
+        count = snapshot.len(),
+        "Reconciling finished jobs with CUPS"
+    );
+
+    for job in snapshot {
+        let backend = backend_for(&job.printer_name, api.config(), api.http_client());
+        let cups_state = match backend.job_state(job.cups_job_id).await {
+            Ok(cups_state) => cups_state,
+            Err(e) => {
+                error!(job_id = job.api_job_id, error = %e, "Failed to query CUPS job status");
+                continue;
+            }
+        };
+
+        let Some(actual) = reconciled_status(&job.reported_status, cups_state) else {
+            continue;
+        };
+        warn!(
+            job_id = job.api_job_id,
+            cups_job_id = job.cups_job_id,
+            reported = %job.reported_status,
+            actual = %actual,
+            "CUPS final status differs from the reported one, correcting"
+        );
+        let message = (actual != PrintJobStatus::Completed)
+            .then_some("Job cancelled or aborted by CUPS after it was reported");
+        if let Err(e) = send_job_status(
+            status_update(job.api_job_id, None, actual.clone(), message, true),
+            None,
+            api,
+            state,
+        )
+        .await
+        {
+            error!(job_id = job.api_job_id, error = %e, "Failed to correct job status, queued for retry");
+        }
+
+        let mut finished = state
+            .finished_jobs
+            .lock()
+            .expect("Failed to acquire finished_jobs lock");
+        if let Some(entry) = finished.iter_mut().find(|j| j.api_job_id == job.api_job_id) {
+            entry.reported_status = actual;
+            save_finished_jobs(&finished);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use tracing::warn;
+
+use crate::models::{Config, PrintJob};
+
+/// Largest percentage accepted for `scaling`, matching the CUPS limit
+const MAX_SCALING_PERCENT: u32 = 800;
+
+/// Pages-per-sheet values CUPS accepts for `number-up`
+pub const NUMBER_UP_VALUES: [u32; 6] = [1, 2, 4, 6, 9, 16];
+
+/// CUPS job options derived from the job, the printer overrides and the configuration
+pub fn job_print_properties(
+    job: &PrintJob,
+    printer_system_name: &str,
+    config: &Config,
+) -> Vec<(String, String)> {
+    let mut properties = vec![(
+        "copies".to_string(),
+        config.effective_copies(job.quantity, &job.size).to_string(),
+    )];
+
+    let overrides = config.printer_overrides.get(printer_system_name);
+
+    let orientation = job
+        .orientation
+        .as_deref()
+        .or_else(|| overrides.and_then(|o| o.orientation.as_deref()));
+    if let Some(value) = orientation {
+        match orientation_option(value) {
+            Some(option) => properties.push(option),
+            None => warn!(job_id = job.id, orientation = %value, "Ignoring invalid orientation"),
+        }
+    }
+
+    let scaling = job
+        .scaling
+        .as_deref()
+        .or_else(|| overrides.and_then(|o| o.scaling.as_deref()));
+    if let Some(value) = scaling {
+        match scaling_option(value) {
+            Some(option) => properties.push(option),
+            None => warn!(job_id = job.id, scaling = %value, "Ignoring invalid scaling"),
+        }
+    }
+
+    // CUPS lays the pages out on the sheet according to `orientation-requested`,
+    // so both can be sent together; the orientation applies to each page
+    let number_up = job
+        .number_up
+        .or_else(|| overrides.and_then(|o| o.number_up));
+    if let Some(value) = number_up {
+        match number_up_option(value) {
+            Some(option) => properties.push(option),
+            None => warn!(
+                job_id = job.id,
+                number_up = value,
+                "Ignoring invalid number-up"
+            ),
+        }
+    }
+
+    let media_type = job
+        .media_type
+        .as_deref()
+        .or_else(|| overrides.and_then(|o| o.media_type.as_deref()));
+    if let Some(value) = media_type {
+        match media_type_option(value, &config.allowed_media_types) {
+            Some(option) => properties.push(option),
+            None => warn!(
+                job_id = job.id,
+                media_type = %value,
+                allowed = ?config.allowed_media_types,
+                "Ignoring media type that is not in allowed_media_types"
+            ),
+        }
+    }
+
+    if let Some(value) = &job.page_ranges {
+        match page_ranges_option(value) {
+            Some(option) => properties.push(option),
+            None => warn!(job_id = job.id, page_ranges = %value, "Ignoring invalid page ranges"),
+        }
+    }
+
+    if config.holds_jobs(printer_system_name) {
+        properties.push(("job-hold-until".to_string(), "indefinite".to_string()));
+    }
+
+    properties
+}
+
+/// Map a media type to the CUPS `media-type` option.
+///
+/// The value is matched case-insensitively against `allowed` and sent with
+/// the spelling listed there; an empty `allowed` list accepts any value.
+pub fn media_type_option(value: &str, allowed: &[String]) -> Option<(String, String)> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if allowed.is_empty() {
+        return Some(("media-type".to_string(), value.to_string()));
+    }
+    allowed
+        .iter()
+        .find(|allowed| allowed.eq_ignore_ascii_case(value))
+        .map(|allowed| ("media-type".to_string(), allowed.clone()))
+}
+
+/// Map a page selection such as `1,3-5` to the CUPS `page-ranges` option.
+///
+/// Each comma-separated part is a page or an ascending `first-last` range of
+/// pages counted from 1; whitespace is dropped.
+pub fn page_ranges_option(value: &str) -> Option<(String, String)> {
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let page = |part: &str| part.parse::<u32>().ok().filter(|&n| n > 0);
+    let valid = !value.is_empty()
+        && value.split(',').all(|part| match part.split_once('-') {
+            Some((first, last)) => page(first).zip(page(last)).is_some_and(|(f, l)| f <= l),
+            None => page(part).is_some(),
+        });
+    valid.then(|| ("page-ranges".to_string(), value))
+}
+
+/// Map a pages-per-sheet count to the CUPS `number-up` option
+pub fn number_up_option(value: u32) -> Option<(String, String)> {
+    NUMBER_UP_VALUES
+        .contains(&value)
+        .then(|| ("number-up".to_string(), value.to_string()))
+}
+
+/// Map an orientation name to the CUPS `orientation-requested` option
+pub fn orientation_option(value: &str) -> Option<(String, String)> {
+    let code = match value.trim().to_ascii_lowercase().as_str() {
+        "portrait" => "3",
+        "landscape" => "4",
+        _ => return None,
+    };
+    Some(("orientation-requested".to_string(), code.to_string()))
+}
+
+/// Map a scaling mode to the matching CUPS option.
+///
+/// `fit` becomes `fit-to-page`, `fill` and `none` become `print-scaling`, and a
+/// percentage (`80` or `80%`) becomes `scaling`.
+pub fn scaling_option(value: &str) -> Option<(String, String)> {
+    let value = value.trim().to_ascii_lowercase();
+    match value.as_str() {
+        "fit" => Some(("fit-to-page".to_string(), "true".to_string())),
+        "fill" | "none" => Some(("print-scaling".to_string(), value)),
+        _ => {
+            let percent: u32 = value.trim_end_matches('%').parse().ok()?;
+            (1..=MAX_SCALING_PERCENT)
+                .contains(&percent)
+                .then(|| ("scaling".to_string(), percent.to_string()))
+        }
+    }
+}
@@ -1,6 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::process::Command;
-use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
 use printers::{get_printer_by_name, get_printers};
@@ -10,21 +9,25 @@ use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, trace, warn};
 
 use crate::error::SpoolerResult;
-use crate::models::Printer;
+use crate::models::{Config, Printer};
+use crate::services::flux_client::FluxClient;
+use crate::services::printer_consistency::{check_printer_consistency, log_consistency_report};
 use crate::services::printer_sync::sync_printers_with_api;
-use crate::utils::config::read_config;
-use crate::utils::printer_storage::{load_printers, save_printers_if_changed};
+use crate::state::AppState;
+use crate::utils::config::{SharedConfig, read_config};
+use crate::utils::printer_storage::{load_printers, store_printers};
 
-/// Query CUPS for supported media sizes of a printer via `lpoptions -p <name> -l`
-fn query_media_sizes(printer_name: &str, verbose_debug: bool) -> Vec<String> {
+/// Run `lpoptions -p <name>` with extra arguments, returning stdout on success
+fn run_lpoptions(printer_name: &str, extra_args: &[&str], verbose_debug: bool) -> Option<String> {
     let output = match Command::new("lpoptions")
-        .args(["-p", printer_name, "-l"])
+        .args(["-p", printer_name])
+        .args(extra_args)
         .output()
     {
         Ok(output) => output,
         Err(e) => {
             debug!(printer = %printer_name, error = %e, "Failed to run lpoptions");
-            return Vec::new();
+            return None;
         }
     };
 
@@ -36,42 +39,144 @@ fn query_media_sizes(printer_name: &str, verbose_debug: bool) -> Vec<String> {
                 "lpoptions returned non-zero exit code"
             );
         }
-        return Vec::new();
+        return None;
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
 
-    // Find the PageSize line, e.g.: "PageSize/Media Size: *A4 Env10 EnvC5 Letter"
-    // The default size is prefixed with '*'
-    for line in stdout.lines() {
-        if line.starts_with("PageSize/") || line.starts_with("PageSize:") {
-            let Some(sizes_part) = line.split(':').nth(1) else {
-                continue;
-            };
-            let sizes: Vec<String> = sizes_part
+/// Parse the supported media sizes from `lpoptions -l` output.
+///
+/// Reads the PageSize line, e.g. `PageSize/Media Size: *A4 Env10 EnvC5 Letter`,
+/// where the default size is prefixed with '*'.
+pub fn parse_media_sizes(listing: &str) -> Option<Vec<String>> {
+    listing
+        .lines()
+        .find(|line| line.starts_with("PageSize/") || line.starts_with("PageSize:"))
+        .and_then(|line| line.split(':').nth(1))
+        .map(|sizes_part| {
+            sizes_part
                 .split_whitespace()
                 .map(|s| s.trim_start_matches('*').to_string())
                 .filter(|s| !s.is_empty())
-                .collect();
+                .collect()
+        })
+}
+
+/// Detect color support from `lpoptions -l` output and the printer attributes
+/// printed by plain `lpoptions -p <name>`.
+///
+/// A printer is considered color capable if `print-color-mode-supported`
+/// lists `color`, or a color option (`ColorModel`, `ColorMode`,
+/// `print-color-mode`) offers a color choice such as `RGB` or `CMYK`.
+pub fn parse_color_support(listing: &str, attributes: &str) -> bool {
+    const COLOR_OPTIONS: [&str; 3] = ["ColorModel", "ColorMode", "print-color-mode"];
+    const COLOR_CHOICES: [&str; 7] = ["rgb", "cmyk", "cmy", "color", "colour", "rgbw", "rgbk"];
+
+    let attribute_color = attributes.split_whitespace().any(|attr| {
+        attr.strip_prefix("print-color-mode-supported=")
+            .is_some_and(|modes| modes.split(',').any(|m| m.trim_matches('\'') == "color"))
+    });
+    if attribute_color {
+        return true;
+    }
+
+    listing.lines().any(|line| {
+        let Some((option, choices)) = line.split_once(':') else {
+            return false;
+        };
+        let option = option.split('/').next().unwrap_or_default();
+        COLOR_OPTIONS.contains(&option)
+            && choices.split_whitespace().any(|choice| {
+                let choice = choice.trim_start_matches('*').to_ascii_lowercase();
+                COLOR_CHOICES.contains(&choice.as_str())
+            })
+    })
+}
+
+/// Read `printer-is-accepting-jobs` from the attributes printed by `lpoptions -p <name>`.
+///
+/// Printers that don't report the attribute are assumed to accept jobs.
+pub fn parse_accepting_jobs(attributes: &str) -> bool {
+    attributes
+        .split_whitespace()
+        .find_map(|attr| attr.strip_prefix("printer-is-accepting-jobs="))
+        .is_none_or(|value| !value.trim_matches('\'').eq_ignore_ascii_case("false"))
+}
+
+/// Ask CUPS whether a printer queue currently accepts jobs
+pub fn printer_accepting_jobs(printer_name: &str) -> bool {
+    run_lpoptions(printer_name, &[], false)
+        .is_none_or(|attributes| parse_accepting_jobs(&attributes))
+}
+
+/// CUPS capabilities of a printer as reported by `lpoptions`
+struct PrinterCapabilities {
+    media_sizes: Vec<String>,
+    supports_color: bool,
+    accepting_jobs: bool,
+}
+
+/// Query CUPS for supported media sizes, color support and job acceptance via `lpoptions`
+fn query_printer_capabilities(printer_name: &str, verbose_debug: bool) -> PrinterCapabilities {
+    let attributes = run_lpoptions(printer_name, &[], verbose_debug).unwrap_or_default();
+    let accepting_jobs = parse_accepting_jobs(&attributes);
+    let Some(listing) = run_lpoptions(printer_name, &["-l"], verbose_debug) else {
+        return PrinterCapabilities {
+            media_sizes: Vec::new(),
+            supports_color: false,
+            accepting_jobs,
+        };
+    };
 
+    let media_sizes = match parse_media_sizes(&listing) {
+        Some(sizes) => sizes,
+        None => {
             if verbose_debug {
-                trace!(
-                    printer = %printer_name,
-                    count = sizes.len(),
-                    sizes = ?sizes,
-                    "Queried media sizes from CUPS"
-                );
+                debug!(printer = %printer_name, "No PageSize line found in lpoptions output");
             }
-
-            return sizes;
+            Vec::new()
         }
-    }
+    };
+    let supports_color = parse_color_support(&listing, &attributes);
 
     if verbose_debug {
-        debug!(printer = %printer_name, "No PageSize line found in lpoptions output");
+        trace!(
+            printer = %printer_name,
+            count = media_sizes.len(),
+            sizes = ?media_sizes,
+            supports_color,
+            accepting_jobs,
+            "Queried printer capabilities from CUPS"
+        );
     }
 
-    Vec::new()
+    PrinterCapabilities {
+        media_sizes,
+        supports_color,
+        accepting_jobs,
+    }
+}
+
+/// The printer to use when none is specified.
+///
+/// Uses `default_printer` when it names an existing printer, otherwise the
+/// CUPS printer with the lowest `system_name`, so the choice does not depend
+/// on the order CUPS lists its queues in.
+pub fn default_system_printer(config: &Config) -> Option<printers::common::base::printer::Printer> {
+    if let Some(name) = &config.default_printer {
+        match get_printer_by_name(name) {
+            Some(printer) => return Some(printer),
+            None => warn!(
+                printer = %name,
+                "Configured default_printer not found, using the first system printer"
+            ),
+        }
+    }
+
+    get_printers()
+        .into_iter()
+        .min_by(|a, b| a.system_name.cmp(&b.system_name))
 }
 
 /// Get all available printers from the CUPS system (blocking operation)
@@ -102,7 +207,19 @@ fn get_all_printers_blocking(verbose_debug: bool) -> Vec<Printer> {
 
         let detailed_info = get_printer_by_name(&system_printer.name);
         // Use system_name for lpoptions query (CUPS expects the queue name, not display name)
-        let media_sizes = query_media_sizes(&system_printer.system_name, verbose_debug);
+        let PrinterCapabilities {
+            media_sizes,
+            supports_color,
+            accepting_jobs,
+        } = query_printer_capabilities(&system_printer.system_name, verbose_debug);
+
+        if !accepting_jobs {
+            warn!(
+                printer = %system_printer.name,
+                system_name = %system_printer.system_name,
+                "Printer is not accepting jobs, it will be reported inactive"
+            );
+        }
 
         if media_sizes.is_empty() {
             warn!(
@@ -134,6 +251,9 @@ fn get_all_printers_blocking(verbose_debug: bool) -> Vec<Printer> {
                 .unwrap_or_else(|| system_printer.driver_name.clone()),
             media_sizes,
             printer_id: None,
+            enabled: true,
+            supports_color,
+            accepting_jobs,
         };
 
         printers.push(printer);
@@ -153,43 +273,105 @@ pub async fn get_all_printers(verbose_debug: bool) -> Vec<Printer> {
         .unwrap_or_default()
 }
 
-/// Check for new printers and update the stored printers
-pub async fn check_for_new_printers(
-    printers_data: Arc<Mutex<HashSet<String>>>,
-    http_client: &Client,
-    config: &Arc<RwLock<crate::models::Config>>,
+/// Discover CUPS printers and the configured `ipp_printers` keyed by
+/// `system_name`, carrying over the API id and enabled flag of already known printers
+pub async fn get_local_printers(
+    saved_printers: &HashMap<String, Printer>,
+    config: &Config,
     verbose_debug: bool,
-) -> SpoolerResult<Vec<Printer>> {
-    let current_printers = get_all_printers(verbose_debug).await;
-    let saved_printers = load_printers();
+) -> HashMap<String, Printer> {
+    let mut current_printers = get_all_printers(verbose_debug).await;
+    current_printers.extend(ipp_printers(config));
     let mut current_printers_map: HashMap<String, Printer> =
         HashMap::with_capacity(current_printers.len());
 
     for mut printer in current_printers {
         if let Some(saved_printer) = saved_printers.get(&printer.system_name) {
             printer.printer_id = saved_printer.printer_id;
+            printer.enabled = saved_printer.enabled;
         }
         current_printers_map.insert(printer.system_name.clone(), printer);
     }
 
-    let config_clone = read_config(config);
+    current_printers_map
+}
+
+/// The printers of `ipp_printers`, which CUPS does not know about
+pub fn ipp_printers(config: &Config) -> Vec<Printer> {
+    let mut printers: Vec<Printer> = config
+        .ipp_printers
+        .iter()
+        .map(|(name, uri)| Printer {
+            name: name.clone(),
+            system_name: name.clone(),
+            uri: Some(uri.clone()),
+            description: String::new(),
+            location: String::new(),
+            make_and_model: String::new(),
+            media_sizes: Vec::new(),
+            printer_id: None,
+            enabled: true,
+            supports_color: false,
+            accepting_jobs: true,
+        })
+        .collect();
+    printers.sort_by(|a, b| a.system_name.cmp(&b.system_name));
+    printers
+}
+
+/// Check for new printers and update the stored printers
+pub async fn check_for_new_printers(
+    state: &AppState,
+    http_client: &Client,
+    config: &SharedConfig,
+    verbose_debug: bool,
+) -> SpoolerResult<Vec<Printer>> {
+    let api = FluxClient::new(http_client.clone(), read_config(config).await);
+    sync_known_printers(state, &api, verbose_debug).await
+}
+
+/// Discover local printers, sync them with the API and store the result;
+/// returns the printers that were not known before
+pub async fn sync_known_printers(
+    state: &AppState,
+    api: &FluxClient,
+    verbose_debug: bool,
+) -> SpoolerResult<Vec<Printer>> {
+    let saved_printers = state
+        .printers
+        .lock()
+        .expect("Failed to acquire printers lock")
+        .clone();
+    let mut current_printers_map =
+        get_local_printers(&saved_printers, api.config(), verbose_debug).await;
+
+    // The config TUI edits printers.json directly, so pick up toggles from disk
+    for (system_name, persisted) in load_printers() {
+        if let Some(printer) = current_printers_map.get_mut(&system_name) {
+            printer.enabled = persisted.enabled;
+        }
+    }
+
     let sync_result = sync_printers_with_api(
         &current_printers_map,
         &saved_printers,
-        http_client,
-        &config_clone,
+        api,
+        false,
         verbose_debug,
     )
     .await;
 
     let updated_printers = match sync_result {
-        Ok(printers) => printers,
+        Ok((printers, _)) => {
+            state.stats.record_printer_sync();
+            printers
+        }
         Err(e) => {
             error!(error = %e, "Error syncing printers with API");
             current_printers_map
         }
     };
-    let printers_were_updated = save_printers_if_changed(&updated_printers, &saved_printers);
+    let printers_were_updated = store_printers(&state.printers, &updated_printers, &saved_printers);
     if printers_were_updated {
         info!(
             count = updated_printers.len(),
@@ -197,15 +379,6 @@ pub async fn check_for_new_printers(
         );
     }
 
-    {
-        let mut printers_set = printers_data
-            .lock()
-            .expect("Failed to acquire printers_data lock");
-        printers_set.clear();
-        for printer in updated_printers.keys() {
-            printers_set.insert(printer.clone());
-        }
-    }
     let new_printers: Vec<Printer> = updated_printers
         .values()
         .filter(|p| !saved_printers.contains_key(&p.system_name))
@@ -226,27 +399,32 @@ fn log_new_printers(printers: &[Printer], context: &str) {
     }
 }
 
-/// Background task to periodically check for new printers
+/// Background task to periodically check for new printers.
+///
+/// A sync in progress is abandoned when `cancel_token` fires, so shutdown does
+/// not wait for slow CUPS or API calls; the next start repairs any half-done
+/// sync through the consistency check.
 pub async fn printer_checker_task(
-    printers_data: Arc<Mutex<HashSet<String>>>,
-    config: Arc<RwLock<crate::models::Config>>,
+    state: AppState,
+    config: SharedConfig,
     http_client: Client,
     cancel_token: CancellationToken,
     verbose_debug: bool,
 ) {
-    let interval = read_config(&config).printer_check_interval;
+    let interval = read_config(&config).await.printer_check_interval;
     info!("Starting printer sync (interval: {} minutes)", interval);
 
-    // Initial check at startup
-    match check_for_new_printers(printers_data.clone(), &http_client, &config, verbose_debug).await
-    {
-        Ok(new_printers) => log_new_printers(&new_printers, " at startup"),
-        Err(e) => error!(error = %e, "Error checking for new printers at startup"),
+    tokio::select! {
+        _ = cancel_token.cancelled() => {
+            info!("Printer checker task shutting down");
+            return;
+        }
+        _ = startup_printer_check(&state, &http_client, &config, verbose_debug) => {}
     }
 
     // Periodic checks
     loop {
-        let interval = read_config(&config).printer_check_interval;
+        let interval = read_config(&config).await.printer_check_interval;
 
         tokio::select! {
             _ = cancel_token.cancelled() => {
@@ -256,15 +434,41 @@ pub async fn printer_checker_task(
             _ = time::sleep(Duration::from_secs(interval * 60)) => {}
         }
 
-        if cancel_token.is_cancelled() {
-            return;
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                info!("Printer checker task shutting down during printer sync");
+                return;
+            }
+            result = check_for_new_printers(&state, &http_client, &config, verbose_debug) => {
+                match result {
+                    Ok(new_printers) => log_new_printers(&new_printers, ""),
+                    Err(e) => error!(error = %e, "Error checking for new printers"),
+                }
+            }
         }
+    }
+}
 
-        match check_for_new_printers(printers_data.clone(), &http_client, &config, verbose_debug)
-            .await
-        {
-            Ok(new_printers) => log_new_printers(&new_printers, ""),
-            Err(e) => error!(error = %e, "Error checking for new printers"),
+/// Initial check at startup, repairing any drift between printers.json,
+/// CUPS and the API left by an earlier run
+async fn startup_printer_check(
+    state: &AppState,
+    http_client: &Client,
+    config: &SharedConfig,
+    verbose_debug: bool,
+) {
+    let api = FluxClient::new(http_client.clone(), read_config(config).await);
+    match check_printer_consistency(state, &api, verbose_debug).await {
+        Ok(report) => {
+            log_consistency_report(&report);
+            log_new_printers(&report.new_printers, " at startup");
+        }
+        Err(e) => {
+            warn!(error = %e, "Printer consistency check failed, syncing printers only");
+            match check_for_new_printers(state, http_client, config, verbose_debug).await {
+                Ok(new_printers) => log_new_printers(&new_printers, " at startup"),
+                Err(e) => error!(error = %e, "Error checking for new printers at startup"),
+            }
         }
     }
 }
@@ -0,0 +1,339 @@
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use async_trait::async_trait;
+use ipp::parser::IppParser;
+use ipp::prelude::*;
+use ipp::reader::IppReader;
+use printers::common::base::job::{PrinterJobOptions, PrinterJobState};
+use printers::get_printer_by_name;
+use reqwest::Client;
+use tracing::warn;
+
+use crate::error::{SpoolerError, SpoolerResult};
+use crate::models::Config;
+use crate::utils::http::read_bytes_limited;
+
+/// How a document reaches a printer and how its job is followed afterwards
+#[async_trait]
+pub trait PrinterBackend: Send + Sync {
+    /// Submit the document at `path` and return the job id the printer assigned
+    async fn submit(
+        &self,
+        path: &Path,
+        job_name: &str,
+        properties: &[(String, String)],
+    ) -> SpoolerResult<u64>;
+
+    /// State of a submitted job; `None` if the printer no longer knows it
+    async fn job_state(&self, job_id: u64) -> SpoolerResult<Option<PrinterJobState>>;
+}
+
+/// A queue of the local CUPS, addressed by `system_name` (the default backend)
+pub struct CupsBackend {
+    pub system_name: String,
+}
+
+#[async_trait]
+impl PrinterBackend for CupsBackend {
+    async fn submit(
+        &self,
+        path: &Path,
+        job_name: &str,
+        properties: &[(String, String)],
+    ) -> SpoolerResult<u64> {
+        let printer = get_printer_by_name(&self.system_name).ok_or_else(|| {
+            SpoolerError::Print(format!("Printer '{}' not found in CUPS", self.system_name))
+        })?;
+        let path = path.to_str().ok_or("Invalid temp file path")?;
+        let raw_properties: Vec<(&str, &str)> = properties
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let job_options = PrinterJobOptions {
+            name: Some(job_name),
+            raw_properties: &raw_properties,
+            ..PrinterJobOptions::none()
+        };
+
+        // `Printer::print` is no cheaper for small jobs: on CUPS it writes the bytes to
+        // its own temp file (named by the clock's nanoseconds) and never removes it.
+        // Generated separator and header pages go through `print_file` for the same reason.
+        printer
+            .print_file(path, job_options)
+            .map_err(|e| SpoolerError::Print(format!("Failed to print: {:?}", e)))
+    }
+
+    async fn job_state(&self, job_id: u64) -> SpoolerResult<Option<PrinterJobState>> {
+        let system_name = self.system_name.clone();
+        // CUPS FFI is not async-safe, so query it in a blocking task
+        tokio::task::spawn_blocking(move || query_cups_job_state(&system_name, job_id))
+            .await
+            .map_err(|e| SpoolerError::Print(format!("CUPS job query failed: {}", e)))
+    }
+}
+
+/// Look up a CUPS job among the printer's active jobs, then in its history
+fn query_cups_job_state(printer_name: &str, cups_job_id: u64) -> Option<PrinterJobState> {
+    let printer = get_printer_by_name(printer_name)?;
+
+    let active = printer.get_active_jobs();
+    if let Some(cups_job) = active.iter().find(|j| j.id == cups_job_id) {
+        return Some(cups_job.state.clone());
+    }
+
+    let history = printer.get_job_history();
+    history
+        .iter()
+        .find(|j| j.id == cups_job_id)
+        .map(|j| j.state.clone())
+}
+
+/// Port used when an `ipp://` or `ipps://` URI names none
+pub const IPP_DEFAULT_PORT: u16 = 631;
+
+/// A network printer spoken to over IPP, without a local CUPS queue
+///
+/// Requests are built and replies parsed with the `ipp` crate; they travel over
+/// the shared HTTP client so replies can be capped like API responses.
+pub struct IppBackend {
+    pub http_client: Client,
+    /// `ipp://` or `ipps://` URI of the printer
+    pub uri: String,
+    /// Sent as `requesting-user-name`
+    pub user: String,
+    /// Largest reply read from the printer, in megabytes (0 = unlimited)
+    pub max_response_mb: u64,
+}
+
+impl IppBackend {
+    fn printer_uri(&self) -> SpoolerResult<Uri> {
+        self.uri
+            .parse()
+            .map_err(|e| SpoolerError::Config(format!("'{}' is not an IPP URI: {}", self.uri, e)))
+    }
+
+    /// Send an IPP request (and document) and parse the printer's reply
+    async fn send(&self, request: IppRequestResponse) -> SpoolerResult<IppRequestResponse> {
+        let url = ipp_http_url(&self.uri)
+            .ok_or_else(|| SpoolerError::Config(format!("'{}' is not an IPP URI", self.uri)))?;
+        let mut body = Vec::new();
+        request.into_read().read_to_end(&mut body)?;
+        let response = self
+            .http_client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/ipp")
+            .body(body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(SpoolerError::Print(format!(
+                "IPP printer {} answered HTTP {}",
+                self.uri,
+                response.status()
+            )));
+        }
+        let reply = read_bytes_limited(response, self.max_response_mb).await?;
+        IppParser::new(IppReader::new(Cursor::new(reply)))
+            .parse()
+            .map_err(|e| SpoolerError::Print(format!("Invalid IPP reply from {}: {}", self.uri, e)))
+    }
+}
+
+#[async_trait]
+impl PrinterBackend for IppBackend {
+    async fn submit(
+        &self,
+        path: &Path,
+        job_name: &str,
+        properties: &[(String, String)],
+    ) -> SpoolerResult<u64> {
+        let document = tokio::fs::read(path).await?;
+        let operation = IppOperationBuilder::print_job(
+            self.printer_uri()?,
+            IppPayload::new(Cursor::new(document)),
+        )
+        .user_name(&self.user)
+        .job_title(job_name)
+        .attributes(job_attributes(properties))
+        .build();
+
+        let response = self.send(operation.into_ipp_request()).await?;
+        if !response.header().status_code().is_success() {
+            return Err(SpoolerError::Print(format!(
+                "IPP printer {} refused the job ({})",
+                self.uri,
+                ipp_error_message(&response)
+            )));
+        }
+        match attribute_value(&response, DelimiterTag::JobAttributes, "job-id") {
+            Some(IppValue::Integer(id)) if *id > 0 => Ok(*id as u64),
+            _ => Err(SpoolerError::Print(format!(
+                "IPP printer {} accepted the job but returned no job-id",
+                self.uri
+            ))),
+        }
+    }
+
+    async fn job_state(&self, job_id: u64) -> SpoolerResult<Option<PrinterJobState>> {
+        let operation = IppOperationBuilder::get_job_attributes(
+            self.printer_uri()?,
+            i32::try_from(job_id).unwrap_or(i32::MAX),
+        )
+        .user_name(&self.user)
+        .build();
+
+        let response = self.send(operation.into_ipp_request()).await?;
+        let status = response.header().status_code();
+        if status == StatusCode::ClientErrorNotFound {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(SpoolerError::Print(format!(
+                "IPP printer {} could not report job {} ({})",
+                self.uri,
+                job_id,
+                ipp_error_message(&response)
+            )));
+        }
+        Ok(
+            match attribute_value(&response, DelimiterTag::JobAttributes, "job-state") {
+                Some(IppValue::Enum(state)) | Some(IppValue::Integer(state)) => {
+                    Some(job_state_from_ipp(*state))
+                }
+                _ => None,
+            },
+        )
+    }
+}
+
+/// First value of the attribute `name` in the reply's `group` attributes
+fn attribute_value<'a>(
+    response: &'a IppRequestResponse,
+    group: DelimiterTag,
+    name: &str,
+) -> Option<&'a IppValue> {
+    response
+        .attributes()
+        .groups_of(group)
+        .find_map(|attributes| attributes.attributes().get(name))
+        .map(IppAttribute::value)
+}
+
+/// The reply's status code, with the printer's `status-message` if it sent one
+fn ipp_error_message(response: &IppRequestResponse) -> String {
+    let status = response.header().status_code();
+    match attribute_value(
+        response,
+        DelimiterTag::OperationAttributes,
+        "status-message",
+    ) {
+        Some(message) => format!("{:?}: {}", status, message),
+        None => format!("{:?}", status),
+    }
+}
+
+/// The HTTP URL an `ipp://` or `ipps://` printer URI is reached at,
+/// e.g. `ipp://10.0.0.5/ipp/print` → `http://10.0.0.5:631/ipp/print`
+pub fn ipp_http_url(uri: &str) -> Option<String> {
+    let (scheme, rest) = if let Some(rest) = uri.strip_prefix("ipp://") {
+        ("http", rest)
+    } else if let Some(rest) = uri.strip_prefix("ipps://") {
+        ("https", rest)
+    } else {
+        return None;
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return None;
+    }
+    // A port follows the last ':' unless that is inside an IPv6 literal
+    let has_port = authority
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()));
+    Some(if has_port {
+        format!("{}://{}{}", scheme, authority, path)
+    } else {
+        format!("{}://{}:{}{}", scheme, authority, IPP_DEFAULT_PORT, path)
+    })
+}
+
+/// Translate CUPS-style job options into IPP job attributes.
+///
+/// Each attribute is typed by its name, never by what its value looks like;
+/// names not listed are keywords. A value that does not fit the attribute's
+/// type is left out with a warning; printers ignore attributes they do not support.
+pub fn job_attributes(properties: &[(String, String)]) -> Vec<IppAttribute> {
+    properties
+        .iter()
+        .filter_map(|(name, value)| {
+            let number = value.trim();
+            let typed = match name.as_str() {
+                "copies" | "number-up" | "scaling" => number.parse().ok().map(IppValue::Integer),
+                "orientation-requested" | "print-quality" | "finishings" => {
+                    number.parse().ok().map(IppValue::Enum)
+                }
+                "fit-to-page" => number.parse().ok().map(IppValue::Boolean),
+                "page-ranges" => value
+                    .split(',')
+                    .map(|range| {
+                        let (min, max) = range.split_once('-').unwrap_or((range, range));
+                        Some(IppValue::RangeOfInteger {
+                            min: min.trim().parse().ok()?,
+                            max: max.trim().parse().ok()?,
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .map(IppValue::Array),
+                "job-name" | "job-originating-user-name" => {
+                    Some(IppValue::NameWithoutLanguage(value.clone()))
+                }
+                _ => Some(IppValue::Keyword(value.clone())),
+            };
+            match typed {
+                Some(typed) => Some(IppAttribute::new(name, typed)),
+                None => {
+                    warn!(attribute = %name, value = %value, "Leaving out IPP attribute with a value of the wrong type");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Map an IPP `job-state` to the state reported for CUPS jobs
+pub fn job_state_from_ipp(job_state: i32) -> PrinterJobState {
+    match job_state {
+        3 => PrinterJobState::PENDING,
+        // pending-held and processing-stopped
+        4 | 6 => PrinterJobState::PAUSED,
+        5 => PrinterJobState::PROCESSING,
+        7 => PrinterJobState::CANCELLED,
+        9 => PrinterJobState::COMPLETED,
+        // aborted, or a value outside the standard
+        _ => PrinterJobState::UNKNOWN,
+    }
+}
+
+/// The backend for the printer `system_name`: IPP for printers listed in
+/// `ipp_printers`, CUPS for everything else
+pub fn backend_for(
+    system_name: &str,
+    config: &Config,
+    http_client: &Client,
+) -> Box<dyn PrinterBackend> {
+    match config.ipp_printers.get(system_name) {
+        Some(uri) => Box::new(IppBackend {
+            http_client: http_client.clone(),
+            uri: uri.clone(),
+            user: config.instance_name.clone(),
+            max_response_mb: config.max_api_response_mb,
+        }),
+        None => Box::new(CupsBackend {
+            system_name: system_name.to_string(),
+        }),
+    }
+}
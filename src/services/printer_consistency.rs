@@ -0,0 +1,216 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use tracing::{info, warn};
+
+use crate::error::SpoolerResult;
+use crate::models::Printer;
+use crate::models::api::ApiPrinter;
+use crate::services::flux_client::FluxClient;
+use crate::services::printer::{get_all_printers, ipp_printers, sync_known_printers};
+use crate::state::AppState;
+use crate::utils::printer_storage::{load_printers, printers_have_changed, save_printers};
+
+/// A difference between `printers.json`, the in-memory printers, CUPS and the API
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrinterDiscrepancy {
+    /// `printers.json` does not match the in-memory printers, e.g. after a failed save
+    FileOutOfDate,
+    /// A known printer is no longer installed in CUPS
+    MissingFromCups(String),
+    /// A CUPS printer is not among the known printers
+    NotKnown(String),
+    /// A known printer points at an API printer that no longer exists
+    StaleApiId {
+        system_name: String,
+        printer_id: u32,
+    },
+    /// An API printer of this spooler that no known printer points at
+    NotInPrintersJson { name: String, printer_id: u32 },
+}
+
+impl fmt::Display for PrinterDiscrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrinterDiscrepancy::FileOutOfDate => {
+                write!(f, "printers.json differs from the printers in memory")
+            }
+            PrinterDiscrepancy::MissingFromCups(system_name) => {
+                write!(f, "'{}' is known but no longer in CUPS", system_name)
+            }
+            PrinterDiscrepancy::NotKnown(system_name) => {
+                write!(f, "'{}' is in CUPS but not known", system_name)
+            }
+            PrinterDiscrepancy::StaleApiId {
+                system_name,
+                printer_id,
+            } => write!(
+                f,
+                "'{}' points at API printer {}, which does not exist",
+                system_name, printer_id
+            ),
+            PrinterDiscrepancy::NotInPrintersJson { name, printer_id } => write!(
+                f,
+                "API printer {} ('{}') is not in printers.json",
+                printer_id, name
+            ),
+        }
+    }
+}
+
+/// Outcome of [`check_printer_consistency`]
+#[derive(Debug, Default)]
+pub struct ConsistencyReport {
+    /// Differences found and repaired
+    pub fixed: Vec<PrinterDiscrepancy>,
+    /// Differences still present after the repair
+    pub unresolved: Vec<PrinterDiscrepancy>,
+    /// Printers the sync added that were not known before
+    pub new_printers: Vec<Printer>,
+}
+
+/// Compare the known printers with CUPS and with this spooler's API printers
+pub fn find_printer_discrepancies(
+    known: &HashMap<String, Printer>,
+    cups: &HashSet<String>,
+    api_printers: &[ApiPrinter],
+) -> Vec<PrinterDiscrepancy> {
+    let mut discrepancies = Vec::new();
+    let mut known_names: Vec<&String> = known.keys().collect();
+    known_names.sort();
+
+    for system_name in &known_names {
+        if !cups.contains(*system_name) {
+            discrepancies.push(PrinterDiscrepancy::MissingFromCups(system_name.to_string()));
+        }
+    }
+    let mut cups_names: Vec<&String> = cups.iter().collect();
+    cups_names.sort();
+    for system_name in cups_names {
+        if !known.contains_key(system_name) {
+            discrepancies.push(PrinterDiscrepancy::NotKnown(system_name.clone()));
+        }
+    }
+
+    let api_ids: HashSet<u32> = api_printers.iter().filter_map(|p| p.id).collect();
+    for system_name in &known_names {
+        if let Some(printer_id) = known[*system_name].printer_id
+            && !api_ids.contains(&printer_id)
+        {
+            discrepancies.push(PrinterDiscrepancy::StaleApiId {
+                system_name: system_name.to_string(),
+                printer_id,
+            });
+        }
+    }
+
+    let known_ids: HashSet<u32> = known.values().filter_map(|p| p.printer_id).collect();
+    for api_printer in api_printers {
+        if let Some(printer_id) = api_printer.id
+            && !known_ids.contains(&printer_id)
+        {
+            discrepancies.push(PrinterDiscrepancy::NotInPrintersJson {
+                name: api_printer.name.clone(),
+                printer_id,
+            });
+        }
+    }
+
+    discrepancies
+}
+
+/// Check `printers.json`, the in-memory printers, CUPS and the API against
+/// each other and repair what differs.
+///
+/// The in-memory printers are rewritten to `printers.json`, ids of deleted API
+/// printers are dropped and one printer sync brings CUPS and the API in line.
+/// API printers this spooler cannot account for are only reported.
+pub async fn check_printer_consistency(
+    state: &AppState,
+    api: &FluxClient,
+    verbose_debug: bool,
+) -> SpoolerResult<ConsistencyReport> {
+    let mut report = ConsistencyReport::default();
+
+    {
+        let known = state
+            .printers
+            .lock()
+            .expect("Failed to acquire printers lock");
+        if printers_have_changed(&known, &load_printers()) {
+            save_printers(&known);
+            report.fixed.push(PrinterDiscrepancy::FileOutOfDate);
+        }
+    }
+
+    let before = current_discrepancies(state, api, verbose_debug).await?;
+    {
+        let mut known = state
+            .printers
+            .lock()
+            .expect("Failed to acquire printers lock");
+        let mut cleared = false;
+        for discrepancy in &before {
+            if let PrinterDiscrepancy::StaleApiId { system_name, .. } = discrepancy
+                && let Some(printer) = known.get_mut(system_name)
+            {
+                printer.printer_id = None;
+                cleared = true;
+            }
+        }
+        if cleared {
+            save_printers(&known);
+        }
+    }
+
+    report.new_printers = sync_known_printers(state, api, verbose_debug).await?;
+
+    let after = current_discrepancies(state, api, verbose_debug).await?;
+    report.fixed.extend(
+        before
+            .into_iter()
+            .filter(|discrepancy| !after.contains(discrepancy)),
+    );
+    report.unresolved = after;
+    Ok(report)
+}
+
+async fn current_discrepancies(
+    state: &AppState,
+    api: &FluxClient,
+    verbose_debug: bool,
+) -> SpoolerResult<Vec<PrinterDiscrepancy>> {
+    let cups: HashSet<String> = get_all_printers(verbose_debug)
+        .await
+        .into_iter()
+        .chain(ipp_printers(api.config()))
+        .map(|p| p.system_name)
+        .collect();
+    let instance_name = &api.config().instance_name;
+    let api_printers: Vec<ApiPrinter> = api
+        .list_printers()
+        .await?
+        .into_iter()
+        .filter(|p| &p.spooler_name == instance_name)
+        .collect();
+    let known = state
+        .printers
+        .lock()
+        .expect("Failed to acquire printers lock")
+        .clone();
+    Ok(find_printer_discrepancies(&known, &cups, &api_printers))
+}
+
+/// Log the result of a consistency check
+pub fn log_consistency_report(report: &ConsistencyReport) {
+    if report.fixed.is_empty() && report.unresolved.is_empty() {
+        info!("Printer state is consistent");
+        return;
+    }
+    for discrepancy in &report.fixed {
+        warn!(discrepancy = %discrepancy, "Repaired printer inconsistency");
+    }
+    for discrepancy in &report.unresolved {
+        warn!(discrepancy = %discrepancy, "Printer inconsistency could not be repaired");
+    }
+}
@@ -1,12 +1,11 @@
 use std::collections::{HashMap, HashSet};
 
-use reqwest::{Client, StatusCode};
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
-use crate::error::SpoolerResult;
-use crate::models::api::{ApiPrinter, ApiPrinterResponse};
-use crate::models::{Config, Printer};
-use crate::utils::http::with_auth_header;
+use crate::error::{SpoolerError, SpoolerResult};
+use crate::models::Printer;
+use crate::models::api::{ApiPrinter, api_printer_name};
+use crate::services::flux_client::{CreatePrinterOutcome, FluxClient};
 
 /// Strip mDNS/Bonjour suffix from a CUPS system name.
 ///
@@ -21,14 +20,41 @@ fn strip_mdns_suffix(name: &str) -> &str {
     }
 }
 
+/// Printers created, updated and deleted in the API by a sync (or, in
+/// dry-run mode, the ones that would be), identified by display name.
+///
+/// Writes the API rejected are listed in `failed` instead, with the reason.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+impl SyncReport {
+    /// Whether the sync did (or would do) nothing
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.updated.is_empty()
+            && self.deleted.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
 /// Synchronize printers with the API server following the specified order
+///
+/// With `dry_run` set, the API is only read: the returned report lists the
+/// writes that would have been made and no printer is created, updated or deleted.
 pub async fn sync_printers_with_api(
     local_printers: &HashMap<String, Printer>,
     saved_printers: &HashMap<String, Printer>,
-    http_client: &Client,
-    config: &Config,
+    api: &FluxClient,
+    dry_run: bool,
     verbose_debug: bool,
-) -> SpoolerResult<HashMap<String, Printer>> {
+) -> SpoolerResult<(HashMap<String, Printer>, SyncReport)> {
+    let config = api.config();
+
     // Filter out any mDNS implicit-class printers that slipped through discovery.
     // These have an '@' in the system_name (e.g. "Printer@hostname.local") and are
     // CUPS shadows that cannot be printed to directly.
@@ -46,8 +72,9 @@ pub async fn sync_printers_with_api(
     );
 
     let mut updated_printers = local_printers.clone();
+    let mut report = SyncReport::default();
 
-    let api_printers = fetch_printers_from_api(http_client, config, verbose_debug).await?;
+    let api_printers = api.list_printers().await?;
     info!(api_count = api_printers.len(), "Fetched printers from API");
 
     // Split API printers into two maps for matching:
@@ -73,14 +100,19 @@ pub async fn sync_printers_with_api(
         // Pass 1: Match by system_name (stable identification)
         // Also try stripping the mDNS `@hostname.local` suffix so that e.g.
         // `EPSON_AM_C5000_Series@EPSONFFD7A7.local` matches `EPSON_AM_C5000_Series`
-        let api_match = api_by_system_name.get(system_name.as_str()).or_else(|| {
-            let base = strip_mdns_suffix(system_name);
-            if base != system_name {
-                api_by_system_name.get(base)
-            } else {
-                None
-            }
-        });
+        // Long names are stored in the API shortened, so look those up too
+        let max_name_length = config.max_printer_name_length;
+        let api_match = api_by_system_name
+            .get(system_name.as_str())
+            .or_else(|| {
+                let base = strip_mdns_suffix(system_name);
+                if base != system_name {
+                    api_by_system_name.get(base)
+                } else {
+                    None
+                }
+            })
+            .or_else(|| api_by_system_name.get(&api_printer_name(system_name, max_name_length)));
 
         if let Some(api_printer) = api_match {
             printer.printer_id = api_printer.id;
@@ -94,7 +126,10 @@ pub async fn sync_printers_with_api(
             }
         }
         // Pass 2: Fallback match by display name for legacy printers (system_name is null in API)
-        else if let Some(api_printer) = api_by_name.get(&printer.name) {
+        else if let Some(api_printer) = api_by_name
+            .get(&printer.name)
+            .or_else(|| api_by_name.get(&api_printer_name(&printer.name, max_name_length)))
+        {
             printer.printer_id = api_printer.id;
             legacy_matched.insert(system_name.clone());
             info!(
@@ -112,10 +147,22 @@ pub async fn sync_printers_with_api(
 
     for (_system_name, printer) in updated_printers.iter_mut() {
         if printer.printer_id.is_none() {
+            if !config.auto_create_printers {
+                info!(
+                    printer = %printer.name,
+                    "Printer is not in the API and auto_create_printers is off, leaving it unmatched"
+                );
+                continue;
+            }
+            if dry_run {
+                report.created.push(printer.name.clone());
+                info!(printer = %printer.name, "Dry run: would create printer in API");
+                continue;
+            }
             if verbose_debug {
                 debug!(printer = %printer.name, "Creating new printer in API");
             }
-            match create_printer_in_api(printer, http_client, config, verbose_debug).await {
+            match create_printer_in_api(printer, api).await {
                 Ok(new_printer) => {
                     if verbose_debug {
                         debug!(
@@ -124,10 +171,14 @@ pub async fn sync_printers_with_api(
                             "Created printer in API"
                         );
                     }
-                    *printer = new_printer.clone();
+                    report.created.push(new_printer.name.clone());
+                    *printer = new_printer;
                 }
                 Err(e) => {
                     error!(printer = %printer.name, error = %e, "Failed to create printer in API");
+                    report
+                        .failed
+                        .push((printer.name.clone(), format!("create: {}", e)));
                 }
             }
         }
@@ -145,9 +196,25 @@ pub async fn sync_printers_with_api(
             continue;
         };
 
+        if !config.auto_delete_printers {
+            info!(
+                printer = %printer.name,
+                id,
+                "Printer was removed locally, keeping it in the API since auto_delete_printers is off"
+            );
+            continue;
+        }
+
+        if dry_run {
+            report.deleted.push(printer.name.clone());
+            info!(printer = %printer.name, id, "Dry run: would delete printer from API");
+            continue;
+        }
+
         // Delete from API
-        match delete_printer_from_api(id, http_client, config, verbose_debug).await {
+        match delete_printer_from_api(id, api, verbose_debug).await {
             Ok(_) => {
+                report.deleted.push(printer.name.clone());
                 if verbose_debug {
                     debug!(printer = %printer.name, id, "Deleted printer from API");
                 }
@@ -159,6 +226,9 @@ pub async fn sync_printers_with_api(
                     error = %e,
                     "Failed to delete printer from API"
                 );
+                report
+                    .failed
+                    .push((printer.name.clone(), format!("delete: {}", e)));
             }
         }
     }
@@ -178,6 +248,11 @@ pub async fn sync_printers_with_api(
             && let Some(printer) = updated_printers.get_mut(system_name)
             && printer.printer_id.is_some()
         {
+            if dry_run {
+                report.updated.push(printer.name.clone());
+                info!(printer = %printer.name, is_legacy, "Dry run: would update printer in API");
+                continue;
+            }
             if verbose_debug || is_legacy {
                 debug!(
                     printer = %printer.name,
@@ -185,151 +260,103 @@ pub async fn sync_printers_with_api(
                     "Updating printer in API"
                 );
             }
-            match update_printer_in_api(printer, http_client, config, verbose_debug).await {
+            match update_printer_in_api(printer, api).await {
                 Ok(_) => {
+                    report.updated.push(printer.name.clone());
                     if verbose_debug || is_legacy {
                         debug!(printer = %printer.name, "Updated printer in API");
                     }
                 }
                 Err(e) => {
                     error!(printer = %printer.name, error = %e, "Failed to update printer in API");
+                    report
+                        .failed
+                        .push((printer.name.clone(), format!("update: {}", e)));
                 }
             }
         }
     }
 
-    Ok(updated_printers)
+    Ok((updated_printers, report))
 }
 
-/// Fetch printers from the API
-async fn fetch_printers_from_api(
-    http_client: &Client,
-    config: &Config,
-    verbose_debug: bool,
-) -> SpoolerResult<Vec<ApiPrinter>> {
-    // Fetch active printers for this instance (spooler_name = instance_name)
-    let api_url = format!(
-        "{}/api/printers?filter[is_active]=true&filter[spooler_name]={}",
-        config.flux_url,
-        urlencoding::encode(&config.instance_name)
-    );
-
-    if verbose_debug {
-        trace!(url = %api_url, "Fetching printers from API");
-    }
-
-    let response = with_auth_header(http_client.get(&api_url), config)
-        .header("Accept", "application/json")
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!(
-            "Failed to fetch printers from API: {} - {}",
-            status, error_text
-        )
-        .into());
-    }
-
-    let response_text = response.text().await?;
-    if verbose_debug {
-        trace!(response = %response_text, "API response");
-    }
-
-    let parsed_response: ApiPrinterResponse = serde_json::from_str(&response_text)?;
-    Ok(parsed_response.data.data)
-}
-
-async fn create_printer_in_api(
+/// Id of the API printer for `printer` on `spooler_name`, matched by
+/// `system_name` or, for legacy records without one, by display name
+pub fn find_existing_printer(
+    api_printers: &[ApiPrinter],
     printer: &Printer,
-    http_client: &Client,
-    config: &Config,
-    verbose_debug: bool,
-) -> SpoolerResult<Printer> {
-    let api_url = format!("{}/api/printers", config.flux_url);
+    spooler_name: &str,
+    max_name_length: usize,
+) -> Option<u32> {
+    api_printers
+        .iter()
+        .filter(|api_printer| api_printer.spooler_name == spooler_name)
+        .find(|api_printer| match &api_printer.system_name {
+            Some(system_name) => {
+                *system_name == printer.system_name
+                    || *system_name == api_printer_name(&printer.system_name, max_name_length)
+            }
+            None => {
+                api_printer.name == printer.name
+                    || api_printer.name == api_printer_name(&printer.name, max_name_length)
+            }
+        })
+        .and_then(|api_printer| api_printer.id)
+}
 
+async fn create_printer_in_api(printer: &Printer, api: &FluxClient) -> SpoolerResult<Printer> {
     // Convert to ApiPrinter
     let mut api_printer: ApiPrinter = printer.into();
     // spooler_name is the instance name (identifies which print server this printer belongs to)
-    api_printer.spooler_name = config.instance_name.clone();
-
-    if verbose_debug {
-        trace!(payload = ?api_printer, "Creating printer with payload");
+    api_printer.spooler_name = api.config().instance_name.clone();
+    api_printer.shorten_names(api.config().max_printer_name_length);
+    if api.config().sync_printer_description {
+        api_printer.description = Some(printer.description.clone());
     }
 
-    let response = with_auth_header(http_client.post(&api_url), config)
-        .header("Accept", "application/json")
-        .json(&api_printer)
-        .send()
-        .await?;
-
-    if response.status() != StatusCode::CREATED && !response.status().is_success() {
-        let status = response.status(); // Save the status before consuming the response
-        let error_text = response.text().await?;
-        return Err(format!("Failed to create printer: {} - {}", status, error_text).into());
-    }
-
-    let response_text = response.text().await?;
-    if verbose_debug {
-        trace!(response = %response_text, "API create response");
-    }
-
-    let response_data: serde_json::Value = serde_json::from_str(&response_text)?;
-
-    // Extract the new printer ID
-    let printer_id = response_data["data"]["id"]
-        .as_u64()
-        .ok_or("Failed to get printer ID from response")?;
+    let printer_id = match api.create_printer(&api_printer).await? {
+        CreatePrinterOutcome::Created(id) => id,
+        CreatePrinterOutcome::Conflict(message) => {
+            // Another spooler may have created the printer since we listed them;
+            // adopt its record instead of leaving a duplicate
+            let api_printers = api.list_printers().await?;
+            let Some(id) = find_existing_printer(
+                &api_printers,
+                printer,
+                &api_printer.spooler_name,
+                api.config().max_printer_name_length,
+            ) else {
+                return Err(SpoolerError::Api(message));
+            };
+            warn!(
+                printer = %printer.name,
+                id,
+                "Printer already exists in API, adopting existing record"
+            );
+            id
+        }
+    };
 
     // Create a new printer with the ID
     let mut new_printer = printer.clone();
-    new_printer.printer_id = Some(printer_id as u32);
+    new_printer.printer_id = Some(printer_id);
 
     Ok(new_printer)
 }
 
-async fn update_printer_in_api(
-    printer: &Printer,
-    http_client: &Client,
-    config: &Config,
-    verbose_debug: bool,
-) -> SpoolerResult<Printer> {
-    if printer.printer_id.is_none() {
-        return Err("Cannot update printer without an ID".into());
-    }
-
-    // PUT /api/printers expects ID in the request body, not URL path
-    let api_url = format!("{}/api/printers", config.flux_url);
-
+async fn update_printer_in_api(printer: &Printer, api: &FluxClient) -> SpoolerResult<Printer> {
     // Convert to ApiPrinter - id will be included in the JSON body
     let mut api_printer: ApiPrinter = printer.into();
     // spooler_name is the instance name (identifies which print server this printer belongs to)
-    api_printer.spooler_name = config.instance_name.clone();
+    api_printer.spooler_name = api.config().instance_name.clone();
+    api_printer.shorten_names(api.config().max_printer_name_length);
+    if api.config().sync_printer_description {
+        api_printer.description = Some(printer.description.clone());
+    }
     // Ensure ID is set for update
     api_printer.id = printer.printer_id;
 
-    if verbose_debug {
-        trace!(payload = ?api_printer, "Updating printer with payload");
-    }
-
-    let response = with_auth_header(http_client.put(&api_url), config)
-        .header("Accept", "application/json")
-        .json(&api_printer)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        let status = response.status(); // Save the status before consuming the response
-        let error_text = response.text().await?;
-        return Err(format!("Failed to update printer: {} - {}", status, error_text).into());
-    }
-
-    if verbose_debug {
-        let response_text = response.text().await?;
-        trace!(response = %response_text, "API update response");
-    }
+    api.update_printer(&api_printer).await?;
 
     // Return the updated printer
     Ok(printer.clone())
@@ -337,36 +364,18 @@ async fn update_printer_in_api(
 
 async fn delete_printer_from_api(
     printer_id: u32,
-    http_client: &Client,
-    config: &Config,
+    api: &FluxClient,
     verbose_debug: bool,
 ) -> SpoolerResult<()> {
-    let api_url = format!("{}/api/printers/{}", config.flux_url, printer_id);
-
-    let response = with_auth_header(http_client.delete(&api_url), config)
-        .header("Accept", "application/json")
-        .json(&serde_json::json!({
-            "spooler_name": config.instance_name // Changed from instance_name
-        }))
-        .send()
-        .await?;
-
-    if response.status() == StatusCode::NOT_FOUND {
-        // Printer already gone from API — treat as success
-        if verbose_debug {
-            debug!(id = printer_id, "Printer already deleted from API");
-        }
-        return Ok(());
-    }
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await?;
-        return Err(format!("Failed to delete printer: {} - {}", status, error_text).into());
-    }
+    let existed = api.delete_printer(printer_id).await?;
 
     if verbose_debug {
-        debug!(id = printer_id, "Successfully deleted printer from API");
+        if existed {
+            debug!(id = printer_id, "Successfully deleted printer from API");
+        } else {
+            // Printer already gone from API — treat as success
+            debug!(id = printer_id, "Printer already deleted from API");
+        }
     }
 
     Ok(())
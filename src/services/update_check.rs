@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::error::SpoolerResult;
+use crate::utils::config::{SharedConfig, read_config};
+
+/// GitHub API endpoint for the latest published release
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/Team-Nifty-GmbH/nuxbe-printer-bridge/releases/latest";
+
+/// How often the background task checks for a new release
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The subset of a GitHub release needed for the update check
+#[derive(serde::Deserialize, Debug)]
+pub struct LatestRelease {
+    pub tag_name: String,
+    pub html_url: String,
+}
+
+/// Parse a `major.minor.patch` version, tolerating a leading `v` and pre-release suffixes
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+/// Whether `latest` is a higher version than `current`; unparseable versions never are
+pub fn is_newer_version(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Fetch the latest release from GitHub.
+///
+/// Uses the shared HTTP client, so proxy environment variables and the
+/// configured TLS settings apply.
+pub async fn fetch_latest_release(http_client: &Client) -> SpoolerResult<LatestRelease> {
+    let response = http_client
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .header(
+            "User-Agent",
+            concat!("nuxbe-printer-bridge/", env!("CARGO_PKG_VERSION")),
+        )
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch latest release: {}", response.status()).into());
+    }
+
+    Ok(response.json::<LatestRelease>().await?)
+}
+
+/// Check GitHub for a newer release, returning it if one is available
+pub async fn check_for_update(http_client: &Client) -> SpoolerResult<Option<LatestRelease>> {
+    let release = fetch_latest_release(http_client).await?;
+    if is_newer_version(&release.tag_name, env!("CARGO_PKG_VERSION")) {
+        Ok(Some(release))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Background task that periodically logs when a newer release is available.
+///
+/// Only runs when `update_check_enabled` is set; it never installs anything.
+pub async fn update_check_task(
+    config: SharedConfig,
+    http_client: Client,
+    cancel_token: CancellationToken,
+) {
+    if !read_config(&config).await.update_check_enabled {
+        debug!("Update check is disabled");
+        return;
+    }
+
+    loop {
+        match check_for_update(&http_client).await {
+            Ok(Some(release)) => warn!(
+                current = env!("CARGO_PKG_VERSION"),
+                latest = %release.tag_name,
+                url = %release.html_url,
+                "A newer nuxbe-printer-bridge release is available"
+            ),
+            Ok(None) => debug!("nuxbe-printer-bridge is up to date"),
+            Err(e) => info!(error = %e, "Update check failed"),
+        }
+
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                info!("Update check task shutting down");
+                return;
+            }
+            _ = time::sleep(UPDATE_CHECK_INTERVAL) => {}
+        }
+    }
+}
@@ -1,60 +1,196 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 
 use async_trait::async_trait;
+use hmac::{Hmac, Mac};
 use reqwest::Client;
 use reverb_rs::private_channel;
 use reverb_rs::{EventHandler, ReverbClient};
+use sha2::Sha256;
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::models::Config;
-use crate::services::print_job::{InFlightJobs, fetch_and_print_job_by_id};
-use crate::utils::config::read_config;
+use crate::services::flux_client::FluxClient;
+use crate::services::print_job::{catch_up_pending_jobs, fetch_and_print_announced_job};
+use crate::state::AppState;
+use crate::utils::config::{SharedConfig, read_config};
+
+/// Channel announcing new print jobs, subscribed as `private-print_job.`
+const PRINT_JOB_CHANNEL: &str = "print_job.";
+
+/// Socket id sent with the pre-connect auth check; any `digits.digits` id is signed
+const PING_SOCKET_ID: &str = "1234.5678";
+
+/// Payload of a `PrintJobCreated` event, e.g. `{"model":{"id":20}}`
+#[derive(serde::Deserialize)]
+struct WebsocketMessage {
+    model: WebsocketModel,
+}
+
+#[derive(serde::Deserialize)]
+struct WebsocketModel {
+    id: u32,
+}
+
+/// Host passed to the Reverb client, with `port` appended when set.
+///
+/// The client derives the port from the TLS flag, so a custom port has to be
+/// part of the host. A host that already names a port is left unchanged.
+pub fn reverb_host_with_port(host: &str, port: Option<u16>) -> String {
+    let host = host.trim();
+    match port {
+        Some(port) if host_port(host).is_none() => format!("{}:{}", host, port),
+        _ => host.to_string(),
+    }
+}
+
+/// Port included in a `host:port` string
+pub fn host_port(host: &str) -> Option<u16> {
+    host.rsplit_once(':')
+        .and_then(|(name, port)| (!name.is_empty()).then_some(port))
+        .and_then(|port| port.parse().ok())
+}
+
+/// Job id announced by a `PrintJobCreated` event
+pub fn parse_print_job_event(data: &str) -> serde_json::Result<u32> {
+    serde_json::from_str::<WebsocketMessage>(data).map(|message| message.model.id)
+}
+
+/// HMAC-SHA256 of `message` under `key`, as lowercase hex
+pub fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+/// Check a channel authorization response (`{"auth": "<key>:<signature>"}`)
+/// against the configured Reverb credentials.
+///
+/// The signature is the HMAC-SHA256 of `socket_id:channel_name` under the app
+/// secret, so a mismatch means FLUX and this bridge use different keys or secrets.
+pub fn verify_channel_auth(
+    body: &str,
+    app_key: &str,
+    app_secret: &str,
+    socket_id: &str,
+    channel_name: &str,
+) -> Result<(), String> {
+    let auth = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value.get("auth")?.as_str().map(str::to_string))
+        .ok_or_else(|| "the auth endpoint did not return an \"auth\" signature".to_string())?;
+    let (key, signature) = auth
+        .split_once(':')
+        .ok_or_else(|| format!("unexpected auth value '{}'", auth))?;
+
+    if key != app_key {
+        return Err(format!(
+            "FLUX signs with app key '{}', but reverb_app_key is '{}'",
+            key, app_key
+        ));
+    }
+    let expected = hmac_sha256_hex(
+        app_secret.as_bytes(),
+        format!("{}:{}", socket_id, channel_name).as_bytes(),
+    );
+    if !signature.eq_ignore_ascii_case(&expected) {
+        return Err("the signature does not match reverb_app_secret".to_string());
+    }
+    Ok(())
+}
+
+/// Ask the auth endpoint to sign the print job channel before connecting, so
+/// bad credentials are reported clearly instead of as a failed subscription.
+///
+/// Only logs; the connection is attempted either way.
+async fn ping_reverb_auth(api: &FluxClient) {
+    let config = api.config();
+    let channel_name = format!("private-{}", PRINT_JOB_CHANNEL);
+    let (status, body) = match api.authorize_channel(PING_SOCKET_ID, &channel_name).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!(endpoint = %config.reverb_auth_endpoint, error = %e, "Could not reach the Reverb auth endpoint");
+            return;
+        }
+    };
+    info!(endpoint = %config.reverb_auth_endpoint, status = %status, "Reverb auth endpoint responded");
+
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        error!(
+            status = %status,
+            "The API token may not subscribe to the print job channel; check flux_api_token"
+        );
+    } else if !status.is_success() {
+        error!(status = %status, "Reverb auth endpoint rejected the check; check reverb_auth_endpoint");
+    } else if let Err(problem) = verify_channel_auth(
+        &body,
+        &config.reverb_app_key,
+        &config.reverb_app_secret,
+        PING_SOCKET_ID,
+        &channel_name,
+    ) {
+        error!(problem = %problem, "Reverb credentials do not match FLUX");
+    } else {
+        info!("Reverb credentials verified");
+    }
+}
 
 pub async fn websocket_task(
-    config: Arc<RwLock<Config>>,
+    config: SharedConfig,
     http_client: Client,
     cancel_token: CancellationToken,
-    in_flight_jobs: InFlightJobs,
+    state: AppState,
 ) {
-    let config_snapshot = read_config(&config);
+    let config_snapshot = read_config(&config).await;
 
     if config_snapshot.reverb_disabled {
         info!("WebSocket functionality is disabled. Not connecting to Reverb");
         return;
     }
 
+    ping_reverb_auth(&FluxClient::new(http_client.clone(), config_snapshot)).await;
+
     loop {
         if cancel_token.is_cancelled() {
             info!("WebSocket task shutting down");
             return;
         }
 
-        let config_snapshot = read_config(&config);
+        let config_snapshot = read_config(&config).await;
         let app_key = config_snapshot.reverb_app_key;
         let app_secret = config_snapshot.reverb_app_secret;
         let auth_endpoint = config_snapshot.reverb_auth_endpoint;
         let use_tls = config_snapshot.reverb_use_tls;
-        let host = config_snapshot.reverb_host;
+        let Some(host) = config_snapshot
+            .reverb_host
+            .as_deref()
+            .map(|host| reverb_host_with_port(host, config_snapshot.reverb_port))
+        else {
+            error!("reverb_host is not set, cannot connect to Reverb");
+            return;
+        };
 
-        info!(app_key = %app_key, "Initializing Reverb client");
+        info!(
+            app_key = %app_key,
+            endpoint = %format!("{}://{}", if use_tls { "wss" } else { "ws" }, host),
+            "Initializing Reverb client"
+        );
 
         // Create the client directly
         let reverb_client = ReverbClient::new(
             app_key.as_str(),
             app_secret.as_str(),
             auth_endpoint.as_str(),
-            host.unwrap().as_str(),
+            host.as_str(),
             use_tls,
         );
 
         // Create a handler with cloned client for subscription
         struct PrintJobHandler {
             http_client: Client,
-            config: Arc<RwLock<Config>>,
-            client: Arc<ReverbClient>,
-            in_flight_jobs: InFlightJobs,
+            config: SharedConfig,
+            client: Weak<ReverbClient>,
+            state: AppState,
         }
 
         #[async_trait]
@@ -63,11 +199,15 @@ pub async fn websocket_task(
                 info!(socket_id, "Connection established");
 
                 // Now that we have a socket_id, subscribe to the channel
-                let channel_name = "print_job.";
+                let channel_name = PRINT_JOB_CHANNEL;
                 let channel = private_channel(channel_name);
 
-                // Use the client directly - no mutex lock needed
-                match self.client.subscribe(channel).await {
+                // The handler only holds a weak reference, so the client is
+                // released once the task moves on to a new connection
+                let Some(client) = self.client.upgrade() else {
+                    return;
+                };
+                match client.subscribe(channel).await {
                     Ok(_) => info!(channel = %channel_name, "Subscribed to channel"),
                     Err(e) => {
                         error!(channel = %channel_name, error = %e, "Failed to subscribe to channel");
@@ -80,48 +220,9 @@ pub async fn websocket_task(
 
                 // Fetch any pending jobs that were created while offline
                 info!("Fetching pending print jobs from API...");
-                let client_clone = self.http_client.clone();
-                let config_copy = read_config(&self.config);
-                let in_flight_clone = self.in_flight_jobs.clone();
-
-                tokio::spawn(async move {
-                    // Fetch pending jobs and collect their IDs
-                    let job_ids: Vec<u32> = match crate::services::print_job::fetch_pending_job_ids(
-                        &client_clone,
-                        &config_copy,
-                    )
-                    .await
-                    {
-                        Ok(ids) => ids,
-                        Err(e) => {
-                            error!(error = %e, "Failed to fetch pending print jobs");
-                            return;
-                        }
-                    };
-
-                    if job_ids.is_empty() {
-                        info!("No pending print jobs found");
-                        return;
-                    }
-
-                    info!(
-                        count = job_ids.len(),
-                        "Found pending print jobs, processing..."
-                    );
-                    for job_id in job_ids {
-                        info!(job_id, "Processing pending job");
-                        if let Err(e) = crate::services::print_job::fetch_and_print_job_by_id(
-                            job_id,
-                            &client_clone,
-                            &config_copy,
-                            &in_flight_clone,
-                        )
-                        .await
-                        {
-                            error!(job_id, error = %e, "Failed to process pending job");
-                        }
-                    }
-                });
+                let api =
+                    FluxClient::new(self.http_client.clone(), read_config(&self.config).await);
+                tokio::spawn(catch_up_pending_jobs(api, self.state.clone()));
             }
 
             async fn on_channel_event(&self, channel: &str, event: &str, data: &str) {
@@ -136,36 +237,21 @@ pub async fn websocket_task(
                 if event == "PrintJobCreated" || event == ".PrintJobCreated" {
                     info!(channel, "Received print job event");
 
-                    // Parse the job ID from the WebSocket message
-                    // Format: {"model":{"id":20}}
-                    #[derive(serde::Deserialize)]
-                    struct WebsocketMessage {
-                        model: WebsocketModel,
-                    }
-                    #[derive(serde::Deserialize)]
-                    struct WebsocketModel {
-                        id: u32,
-                    }
-
-                    match serde_json::from_str::<WebsocketMessage>(data) {
-                        Ok(message) => {
-                            let job_id = message.model.id;
+                    match parse_print_job_event(data) {
+                        Ok(job_id) => {
                             info!(job_id, "Received print job creation event");
 
                             // Get references needed to handle the job
-                            let client_clone = self.http_client.clone();
-                            let config_copy = read_config(&self.config);
-                            let in_flight_clone = self.in_flight_jobs.clone();
+                            let api = FluxClient::new(
+                                self.http_client.clone(),
+                                read_config(&self.config).await,
+                            );
+                            let state_clone = self.state.clone();
 
                             // Spawn a new task to fetch and print the job
                             tokio::spawn(async move {
-                                if let Err(e) = fetch_and_print_job_by_id(
-                                    job_id,
-                                    &client_clone,
-                                    &config_copy,
-                                    &in_flight_clone,
-                                )
-                                .await
+                                if let Err(e) =
+                                    fetch_and_print_announced_job(job_id, &api, &state_clone).await
                                 {
                                     error!(job_id, error = %e, "Error handling print job from WebSocket");
                                 } else {
@@ -192,18 +278,19 @@ pub async fn websocket_task(
         let handler = PrintJobHandler {
             http_client: http_client.clone(),
             config: config.clone(),
-            client: client_arc.clone(),
-            in_flight_jobs: in_flight_jobs.clone(),
+            client: Arc::downgrade(&client_arc),
+            state: state.clone(),
         };
 
         // Add the event handler and connect
         client_arc.add_event_handler(handler).await;
 
         // Connect to the server
+        let mut reconnect_now = false;
         match client_arc.connect().await {
             Ok(_) => {
                 info!("Connected to Reverb successfully");
-                // Wait until the connection is closed or cancellation
+                // Wait until the connection is closed, a reconnect is requested or cancellation
                 tokio::select! {
                     _ = cancel_token.cancelled() => {
                         info!("WebSocket task received shutdown signal");
@@ -212,6 +299,10 @@ pub async fn websocket_task(
                     _ = client_arc.wait_for_disconnect() => {
                         info!("WebSocket connection lost");
                     }
+                    _ = state.reverb_reconnect.notified() => {
+                        info!("Reconnect requested, dropping the WebSocket connection");
+                        reconnect_now = true;
+                    }
                 }
             }
             Err(e) => {
@@ -225,17 +316,23 @@ pub async fn websocket_task(
             return;
         }
 
-        // Wait before reconnecting
-        info!("Waiting 5 seconds before reconnecting...");
-        tokio::select! {
-            _ = cancel_token.cancelled() => {
-                info!("WebSocket task shutting down");
-                return;
+        // Wait before reconnecting, unless a reconnect was requested
+        if !reconnect_now {
+            info!("Waiting 5 seconds before reconnecting...");
+            tokio::select! {
+                _ = cancel_token.cancelled() => {
+                    info!("WebSocket task shutting down");
+                    return;
+                }
+                _ = tokio::time::sleep(Duration::from_secs(5)) => {}
+                _ = state.reverb_reconnect.notified() => {
+                    info!("Reconnect requested, skipping the wait");
+                }
             }
-            _ = tokio::time::sleep(Duration::from_secs(5)) => {}
         }
 
         // If we reach here, we'll try to reconnect
         info!("Reconnecting to Reverb server");
+        state.stats.record_websocket_reconnect();
     }
 }
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use tokio::sync::Notify;
+
+use tracing::info;
+
+use crate::services::print_job::{ActiveJobs, InFlightJobs, new_active_jobs, new_in_flight_jobs};
+use crate::utils::consumption::{Consumption, new_consumption};
+use crate::utils::dead_letters::{DeadLetters, new_dead_letters};
+use crate::utils::fetch_queue::{PendingFetches, new_pending_fetches};
+use crate::utils::finished_jobs::{FinishedJobs, new_finished_jobs};
+use crate::utils::printer_storage::{SharedPrinters, new_shared_printers};
+use crate::utils::recent_jobs::{RecentJobs, new_recent_jobs};
+use crate::utils::skipped_jobs::{SkippedJobs, new_skipped_jobs};
+use crate::utils::status_queue::{PendingStatusUpdates, new_pending_status_updates};
+
+/// Shared runtime state handed to the background tasks and job handlers.
+#[derive(Clone)]
+pub struct AppState {
+    /// Jobs submitted to CUPS that are awaiting a final status
+    pub in_flight_jobs: InFlightJobs,
+    /// Jobs currently being downloaded and submitted, for deduplication
+    pub active_jobs: ActiveJobs,
+    /// Known printers keyed by `system_name`, mirrored to `printers.json`
+    pub printers: SharedPrinters,
+    /// Status updates that failed to reach the API, mirrored to `pending_status_updates.json`
+    pub pending_status_updates: PendingStatusUpdates,
+    /// Announced jobs that could not be fetched yet, mirrored to `pending_fetch.json`
+    pub pending_fetches: PendingFetches,
+    /// Jobs recently completed here, mirrored to `recent_jobs.json`
+    pub recent_jobs: RecentJobs,
+    /// Jobs left pending by quiet hours, age limits or deferrals, mirrored to `skipped_jobs.json`
+    pub skipped_jobs: SkippedJobs,
+    /// Recently finished CUPS jobs for the reconciliation pass, mirrored to `finished_jobs.json`
+    pub finished_jobs: FinishedJobs,
+    /// Jobs reported to the API as failed, mirrored to `dead_letters.json`
+    pub dead_letters: DeadLetters,
+    /// Estimated pages printed per printer, mirrored to `consumption.json`
+    pub consumption: Consumption,
+    /// Wakes the WebSocket task to drop its connection and reconnect at once
+    pub reverb_reconnect: Arc<Notify>,
+    /// Counters for the session summary logged at shutdown
+    pub stats: Arc<SessionStats>,
+}
+
+impl AppState {
+    /// Create the runtime state, seeding the printer cache, the status and
+    /// fetch retry queues, the recent, skipped, finished and failed jobs and the
+    /// consumption totals from their files in the config directory.
+    pub fn load() -> Self {
+        AppState {
+            in_flight_jobs: new_in_flight_jobs(),
+            active_jobs: new_active_jobs(),
+            printers: new_shared_printers(),
+            pending_status_updates: new_pending_status_updates(),
+            pending_fetches: new_pending_fetches(),
+            recent_jobs: new_recent_jobs(),
+            skipped_jobs: new_skipped_jobs(),
+            finished_jobs: new_finished_jobs(),
+            dead_letters: new_dead_letters(),
+            consumption: new_consumption(),
+            reverb_reconnect: Arc::new(Notify::new()),
+            stats: Arc::new(SessionStats::new()),
+        }
+    }
+}
+
+/// Counters accumulated over the lifetime of the server process.
+pub struct SessionStats {
+    started_at: Instant,
+    jobs_printed: AtomicU64,
+    jobs_failed: AtomicU64,
+    printer_syncs: AtomicU64,
+    websocket_reconnects: AtomicU64,
+}
+
+impl SessionStats {
+    fn new() -> Self {
+        SessionStats {
+            started_at: Instant::now(),
+            jobs_printed: AtomicU64::new(0),
+            jobs_failed: AtomicU64::new(0),
+            printer_syncs: AtomicU64::new(0),
+            websocket_reconnects: AtomicU64::new(0),
+        }
+    }
+
+    /// A job was reported to CUPS as completed
+    pub fn record_job_printed(&self) {
+        self.jobs_printed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A job ended as failed or cancelled
+    pub fn record_job_failed(&self) {
+        self.jobs_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A printer sync with the API finished without error
+    pub fn record_printer_sync(&self) {
+        self.printer_syncs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The WebSocket connection is being re-established
+    pub fn record_websocket_reconnect(&self) {
+        self.websocket_reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Log a one-line summary of the session
+    pub fn log_summary(&self) {
+        let uptime = self.started_at.elapsed().as_secs();
+        info!(
+            uptime = %format!("{}h {}m {}s", uptime / 3600, (uptime % 3600) / 60, uptime % 60),
+            jobs_printed = self.jobs_printed.load(Ordering::Relaxed),
+            jobs_failed = self.jobs_failed.load(Ordering::Relaxed),
+            printer_syncs = self.printer_syncs.load(Ordering::Relaxed),
+            websocket_reconnects = self.websocket_reconnects.load(Ordering::Relaxed),
+            "Session summary"
+        );
+    }
+}
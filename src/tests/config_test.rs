@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use tempfile::tempdir;
 
+use crate::models::Config;
 use crate::utils::config::load_config;
 
 // Helper to create a test config directory
@@ -39,3 +41,597 @@ fn test_temp_dir_creation() {
     // Just verify it exists now
     assert!(dir.path().exists());
 }
+
+#[test]
+fn test_render_job_name_placeholders() {
+    let config = Config {
+        instance_name: "office".to_string(),
+        job_name_template: "{instance}/{printer}: Job {job_id} ({size})".to_string(),
+        ..Config::default()
+    };
+
+    let name = config.render_job_name(&[("job_id", "42"), ("printer", "Label"), ("size", "A6")]);
+    assert_eq!(name, "office/Label: Job 42 (A6)");
+}
+
+#[test]
+fn test_render_job_name_created_by() {
+    let config = Config {
+        job_name_template: "Job {job_id} by {created_by}".to_string(),
+        ..Config::default()
+    };
+
+    let name = config.render_job_name(&[("job_id", "42"), ("created_by", "17")]);
+    assert_eq!(name, "Job 42 by 17");
+}
+
+#[test]
+fn test_render_job_name_default_template() {
+    let config = Config::default();
+    assert_eq!(config.render_job_name(&[("job_id", "7")]), "Print Job 7");
+}
+
+#[test]
+fn test_tenant_header_and_query_param() {
+    let config = Config {
+        tenant: Some("acme".to_string()),
+        tenant_query_param: Some("tenant".to_string()),
+        ..Config::default()
+    };
+
+    let client = reqwest::Client::new();
+    let request = crate::utils::http::with_auth_header(
+        client.get("http://example.com/api/print-jobs?include=printer"),
+        &config,
+    )
+    .build()
+    .unwrap();
+
+    assert_eq!(request.headers()["X-Tenant"], "acme");
+    assert_eq!(request.url().query(), Some("include=printer&tenant=acme"));
+}
+
+#[test]
+fn test_no_tenant_leaves_request_unchanged() {
+    let client = reqwest::Client::new();
+    let request = crate::utils::http::with_auth_header(
+        client.get("http://example.com/api/printers"),
+        &Config::default(),
+    )
+    .build()
+    .unwrap();
+
+    assert!(request.headers().get("X-Tenant").is_none());
+    assert_eq!(request.url().query(), None);
+}
+
+#[test]
+fn test_update_check_version_comparison() {
+    use crate::services::update_check::{is_newer_version, parse_version};
+
+    assert_eq!(parse_version("v0.1.18"), Some((0, 1, 18)));
+    assert_eq!(parse_version("1.2"), Some((1, 2, 0)));
+    assert_eq!(parse_version("2.0.0-beta.1"), Some((2, 0, 0)));
+    assert_eq!(parse_version("nightly"), None);
+
+    assert!(is_newer_version("v0.1.19", "0.1.18"));
+    assert!(is_newer_version("v0.2.0", "0.1.18"));
+    assert!(!is_newer_version("v0.1.18", "0.1.18"));
+    assert!(!is_newer_version("v0.1.9", "0.1.18"));
+    assert!(!is_newer_version("nightly", "0.1.18"));
+}
+
+#[test]
+fn test_effective_copies_with_size_multipliers() {
+    let mut config = Config::default();
+    config
+        .size_copy_multipliers
+        .insert("label-3up".to_string(), 3);
+
+    assert_eq!(config.effective_copies(1, "label-3up"), 3);
+    assert_eq!(config.effective_copies(2, "label-3up"), 6);
+    assert_eq!(config.effective_copies(2, "A4"), 2);
+    // Missing quantity still prints once
+    assert_eq!(config.effective_copies(0, "A4"), 1);
+}
+
+#[test]
+fn test_env_flag_enabled() {
+    use crate::utils::config::env_flag_enabled;
+
+    assert!(env_flag_enabled("1"));
+    assert!(env_flag_enabled("TRUE"));
+    assert!(env_flag_enabled(" yes "));
+    assert!(!env_flag_enabled("0"));
+    assert!(!env_flag_enabled("false"));
+    assert!(!env_flag_enabled(""));
+}
+
+#[test]
+fn test_redirect_changes_origin() {
+    use crate::utils::http::changes_origin;
+    use reqwest::Url;
+
+    let api = Url::parse("https://flux.example/api/media/private/1").unwrap();
+    let same = Url::parse("https://flux.example:443/storage/1.pdf").unwrap();
+    let bucket = Url::parse("https://bucket.s3.example/1.pdf?signature=abc").unwrap();
+    let plain = Url::parse("http://flux.example/api/media/private/1").unwrap();
+
+    assert!(!changes_origin(&api, &same));
+    assert!(changes_origin(&api, &bucket));
+    assert!(changes_origin(&api, &plain));
+}
+
+#[test]
+fn test_log_level_cycle() {
+    use crate::cli::{log_level_name, next_log_verbosity};
+
+    assert_eq!(log_level_name(0), "info");
+    assert_eq!(log_level_name(next_log_verbosity(0)), "debug");
+    assert_eq!(log_level_name(next_log_verbosity(2)), "trace");
+    assert_eq!(log_level_name(next_log_verbosity(3)), "info");
+    assert_eq!(
+        next_log_verbosity(next_log_verbosity(next_log_verbosity(1))),
+        1
+    );
+}
+
+#[test]
+fn test_redacted_config_hides_secrets() {
+    use crate::models::REDACTED;
+
+    let config = Config {
+        flux_api_token: Some("secret-token".to_string()),
+        reverb_app_secret: "reverb-secret".to_string(),
+        ..Config::default()
+    };
+    let redacted = config.redacted();
+    let logged = format!("{:?}", redacted);
+
+    assert_eq!(redacted.flux_api_token.as_deref(), Some(REDACTED));
+    assert_eq!(redacted.reverb_app_secret, REDACTED);
+    assert!(!logged.contains("secret-token"));
+    assert!(!logged.contains("reverb-secret"));
+    assert_eq!(redacted.flux_url, config.flux_url);
+
+    let unset = Config {
+        reverb_app_secret: String::new(),
+        ..Config::default()
+    }
+    .redacted();
+    assert_eq!(unset.flux_api_token, None);
+    assert_eq!(unset.reverb_app_secret, "");
+}
+
+#[test]
+fn test_config_validate_reports_all_problems() {
+    assert!(Config::default().validate().is_ok());
+
+    let config = Config {
+        instance_name: "  ".to_string(),
+        flux_url: "ftp://example.com".to_string(),
+        printer_check_interval: 0,
+        reverb_disabled: false,
+        reverb_host: None,
+        client_cert_path: Some("/etc/cert.pem".to_string()),
+        ..Config::default()
+    };
+    let problems = config.validate().unwrap_err();
+
+    assert_eq!(problems.len(), 5);
+    assert!(problems.iter().any(|p| p.contains("instance_name")));
+    assert!(problems.iter().any(|p| p.contains("flux_url")));
+    assert!(problems.iter().any(|p| p.contains("reverb_host")));
+}
+
+#[test]
+fn test_import_config_keeps_local_secrets_and_validates() {
+    use crate::utils::config::import_config;
+
+    let source = Config {
+        instance_name: "warehouse-2".to_string(),
+        flux_api_token: Some("source-token".to_string()),
+        ..Config::default()
+    };
+    let current = Config {
+        flux_api_token: Some("local-token".to_string()),
+        reverb_app_secret: "local-secret".to_string(),
+        ..Config::default()
+    };
+
+    let exported = serde_json::to_string(&source.without_secrets()).unwrap();
+    assert!(!exported.contains("source-token"));
+
+    let imported = import_config(&exported, &current).unwrap();
+    assert_eq!(imported.instance_name, "warehouse-2");
+    assert_eq!(imported.flux_api_token.as_deref(), Some("local-token"));
+    assert_eq!(imported.reverb_app_secret, "local-secret");
+
+    let with_secrets = serde_json::to_string(&source).unwrap();
+    let imported = import_config(&with_secrets, &current).unwrap();
+    assert_eq!(imported.flux_api_token.as_deref(), Some("source-token"));
+
+    let invalid = r#"{"flux_url": "not a url"}"#;
+    assert!(import_config(invalid, &current).is_err());
+    assert!(import_config("{ not json", &current).is_err());
+}
+
+#[test]
+fn test_reset_state_files_keep_config_unless_all() {
+    use crate::cli::local_state_files;
+    use crate::utils::config::config_path;
+    use crate::utils::json_store::remove_state_file;
+
+    let state_only = local_state_files(false);
+    assert!(!state_only.contains(&config_path()));
+    assert!(state_only.iter().any(|p| p.ends_with("printers.json")));
+    assert!(state_only.iter().any(|p| p.ends_with("pending_fetch.json")));
+    assert!(state_only.iter().any(|p| p.ends_with("skipped_jobs.json")));
+    assert!(local_state_files(true).contains(&config_path()));
+
+    let (dir, _) = setup_test_config_dir();
+    let file = dir.path().join("state.json");
+    fs::write(&file, "[]").unwrap();
+    assert!(remove_state_file(&file).unwrap());
+    assert!(!remove_state_file(&file).unwrap());
+    assert!(!file.exists());
+}
+
+#[test]
+fn test_reverb_port_is_added_to_host() {
+    use crate::services::websocket::reverb_host_with_port;
+
+    assert_eq!(reverb_host_with_port("reverb.local", None), "reverb.local");
+    assert_eq!(
+        reverb_host_with_port("reverb.local", Some(6001)),
+        "reverb.local:6001"
+    );
+    assert_eq!(
+        reverb_host_with_port("reverb.local:8080", Some(8080)),
+        "reverb.local:8080"
+    );
+
+    let config = |host: &str, port| Config {
+        reverb_disabled: false,
+        reverb_host: Some(host.to_string()),
+        reverb_port: port,
+        ..Config::default()
+    };
+    assert!(config("reverb.local", Some(6001)).validate().is_ok());
+    assert!(config("reverb.local:8080", Some(8080)).validate().is_ok());
+    assert!(config("reverb.local:8080", Some(6001)).validate().is_err());
+    assert!(config("reverb.local", Some(0)).validate().is_err());
+}
+
+#[test]
+fn test_token_source_resolution() {
+    use crate::models::TokenSource;
+
+    assert_eq!(TokenSource::parse("inline"), Ok(TokenSource::Inline));
+    assert_eq!(TokenSource::parse(""), Ok(TokenSource::Inline));
+    assert_eq!(
+        TokenSource::parse("file:/run/secrets/flux"),
+        Ok(TokenSource::File("/run/secrets/flux".to_string()))
+    );
+    assert_eq!(
+        TokenSource::parse("env:FLUX_TOKEN"),
+        Ok(TokenSource::Env("FLUX_TOKEN".to_string()))
+    );
+    assert!(TokenSource::parse("vault:flux").is_err());
+    assert!(TokenSource::parse("file:").is_err());
+
+    let inline = Config {
+        flux_api_token: Some("inline-token".to_string()),
+        ..Config::default()
+    };
+    assert_eq!(inline.api_token().as_deref(), Some("inline-token"));
+
+    // A token file is re-read on every call, so rotation is picked up
+    let (dir, _) = setup_test_config_dir();
+    let token_file = dir.path().join("token");
+    fs::write(&token_file, "first-token\n").unwrap();
+    let from_file = Config {
+        flux_api_token: Some("ignored".to_string()),
+        token_source: format!("file:{}", token_file.display()),
+        ..Config::default()
+    };
+    assert_eq!(from_file.api_token().as_deref(), Some("first-token"));
+    fs::write(&token_file, "rotated-token").unwrap();
+    assert_eq!(from_file.api_token().as_deref(), Some("rotated-token"));
+    fs::remove_file(&token_file).unwrap();
+    assert_eq!(from_file.api_token(), None);
+
+    let from_env = TokenSource::Env("FLUX_TOKEN".to_string());
+    let env = |name: &str| (name == "FLUX_TOKEN").then(|| " env-token\n".to_string());
+    assert_eq!(
+        from_env.resolve_with(None, env).as_deref(),
+        Some("env-token")
+    );
+    assert_eq!(from_env.resolve_with(None, |_| None), None);
+    assert_eq!(
+        from_env.resolve_with(None, |_| Some("  ".to_string())),
+        None
+    );
+
+    assert!(
+        Config {
+            token_source: "vault:x".to_string(),
+            ..Config::default()
+        }
+        .validate()
+        .is_err()
+    );
+}
+
+#[test]
+fn test_printer_ready_wait_override() {
+    let config: Config = serde_json::from_str(
+        r#"{"printer_ready_wait_secs": 10,
+        "printer_overrides": {"USB_Label": {"ready_wait_secs": 30}, "Office": {"scaling": "fit"}}}"#,
+    )
+    .unwrap();
+    assert_eq!(config.ready_wait_secs("USB_Label"), 30);
+    assert_eq!(config.ready_wait_secs("Office"), 10);
+    assert_eq!(config.ready_wait_secs("Unlisted"), 10);
+    assert_eq!(Config::default().ready_wait_secs("USB_Label"), 0);
+}
+
+#[test]
+fn test_verify_channel_auth() {
+    use crate::services::websocket::hmac_sha256_hex;
+    use crate::services::websocket::verify_channel_auth;
+
+    let signature = hmac_sha256_hex(b"secret", b"1234.5678:private-print_job.");
+    let body = format!(r#"{{"auth": "app-key:{}"}}"#, signature);
+    let check = |body: &str, key: &str, secret: &str| {
+        verify_channel_auth(body, key, secret, "1234.5678", "private-print_job.")
+    };
+
+    assert!(check(&body, "app-key", "secret").is_ok());
+    assert!(
+        check(&body, "other-key", "secret")
+            .unwrap_err()
+            .contains("reverb_app_key")
+    );
+    assert!(
+        check(&body, "app-key", "wrong")
+            .unwrap_err()
+            .contains("reverb_app_secret")
+    );
+    assert!(check(r#"{"message": "denied"}"#, "app-key", "secret").is_err());
+    assert!(check(r#"{"auth": "no-colon"}"#, "app-key", "secret").is_err());
+}
+
+#[test]
+fn test_unknown_printer_id_behavior_parses() {
+    use crate::models::UnknownPrinterIdBehavior;
+
+    let parse = |value: &str| {
+        serde_json::from_str::<Config>(&format!(
+            r#"{{"unknown_printer_id_behavior": "{}"}}"#,
+            value
+        ))
+        .map(|config| config.unknown_printer_id_behavior)
+    };
+    assert_eq!(
+        Config::default().unknown_printer_id_behavior,
+        UnknownPrinterIdBehavior::Fallback
+    );
+    assert_eq!(parse("fail").unwrap(), UnknownPrinterIdBehavior::Fail);
+    assert_eq!(
+        parse("resync_then_retry").unwrap(),
+        UnknownPrinterIdBehavior::ResyncThenRetry
+    );
+    assert_eq!(
+        parse("resync-then-retry").unwrap(),
+        UnknownPrinterIdBehavior::ResyncThenRetry
+    );
+    assert!(parse("guess").is_err());
+}
+
+#[test]
+fn test_max_printer_name_length_validation() {
+    let config = |max_printer_name_length| Config {
+        max_printer_name_length,
+        ..Config::default()
+    };
+    assert!(config(0).validate().is_ok());
+    assert!(config(64).validate().is_ok());
+    let problems = config(8).validate().unwrap_err();
+    assert!(
+        problems
+            .iter()
+            .any(|p| p.starts_with("max_printer_name_length")),
+        "{:?}",
+        problems
+    );
+}
+
+#[test]
+fn test_instance_name_from_hostname() {
+    let mut config = Config::default();
+    assert!(!config.derive_instance_name("print-host-07"));
+    assert_eq!(config.instance_name, "default-instance");
+
+    config.instance_name_from_hostname = true;
+    assert!(config.derive_instance_name(" print-host-07\n"));
+    assert_eq!(config.instance_name, "print-host-07");
+
+    // An explicit name stays authoritative, and an empty hostname changes nothing
+    let mut explicit = Config {
+        instance_name: "warehouse-a".to_string(),
+        instance_name_from_hostname: true,
+        ..Config::default()
+    };
+    assert!(!explicit.derive_instance_name("print-host-07"));
+    assert_eq!(explicit.instance_name, "warehouse-a");
+    explicit.instance_name = "default-instance".to_string();
+    assert!(!explicit.derive_instance_name("  "));
+}
+
+#[test]
+fn test_config_dir_without_home_directory() {
+    use crate::utils::config::{SYSTEM_CONFIG_DIR, resolve_config_dir};
+
+    let home = Some(PathBuf::from("/home/print"));
+    let xdg = Some(PathBuf::from("/var/lib/print/config"));
+    assert_eq!(
+        resolve_config_dir(None, home.clone(), xdg.clone()),
+        PathBuf::from("/home/print/.config/nuxbe-printer-bridge")
+    );
+    assert_eq!(
+        resolve_config_dir(Some(PathBuf::from("/srv/bridge")), home, xdg.clone()),
+        PathBuf::from("/srv/bridge")
+    );
+
+    // No home directory, as for a systemd DynamicUser service
+    assert_eq!(
+        resolve_config_dir(None, None, xdg),
+        PathBuf::from("/var/lib/print/config/nuxbe-printer-bridge")
+    );
+    assert_eq!(
+        resolve_config_dir(None, None, None),
+        PathBuf::from(SYSTEM_CONFIG_DIR)
+    );
+}
+
+#[test]
+fn test_system_config_dir_is_read_only() {
+    use crate::utils::config::{SYSTEM_CONFIG_DIR, is_read_only_config_dir};
+
+    assert!(is_read_only_config_dir(&PathBuf::from(SYSTEM_CONFIG_DIR)));
+    assert!(!is_read_only_config_dir(&PathBuf::from(
+        "/var/lib/print/config/nuxbe-printer-bridge"
+    )));
+}
+
+#[test]
+fn test_pre_print_command_validation() {
+    let config = |enabled, command: Option<&str>| Config {
+        pre_print_command_enabled: enabled,
+        pre_print_command: command.map(str::to_string),
+        ..Config::default()
+    };
+    assert!(config(false, None).validate().is_ok());
+    assert!(config(false, Some("rotate")).validate().is_ok());
+    assert!(
+        config(true, Some("rotate {input} {output}"))
+            .validate()
+            .is_ok()
+    );
+    for command in [None, Some(" "), Some("rotate --in place")] {
+        let problems = config(true, command).validate().unwrap_err();
+        assert!(
+            problems.iter().any(|p| p.starts_with("pre_print_command")),
+            "{:?}",
+            problems
+        );
+    }
+}
+
+#[test]
+fn test_take_reverb_settings_copies_connection_only() {
+    let mut current = Config {
+        reverb_disabled: false,
+        reverb_host: Some("old.example.com".to_string()),
+        ..Config::default()
+    };
+    let on_disk = Config {
+        reverb_disabled: true,
+        reverb_host: Some("new.example.com".to_string()),
+        reverb_port: Some(8080),
+        reverb_app_key: "new-key".to_string(),
+        flux_url: "https://other.example.com".to_string(),
+        ..Config::default()
+    };
+
+    assert!(current.take_reverb_settings(&on_disk));
+    assert_eq!(current.reverb_host.as_deref(), Some("new.example.com"));
+    assert_eq!(current.reverb_port, Some(8080));
+    assert_eq!(current.reverb_app_key, "new-key");
+    // Mode switches and unrelated settings are left alone
+    assert!(!current.reverb_disabled);
+    assert_eq!(current.flux_url, Config::default().flux_url);
+
+    assert!(!current.take_reverb_settings(&on_disk));
+}
+
+#[test]
+fn test_ipp_printers_validation() {
+    let config = |name: &str, uri: &str| Config {
+        ipp_printers: HashMap::from([(name.to_string(), uri.to_string())]),
+        ..Config::default()
+    };
+    assert!(
+        config("warehouse", "ipp://10.0.0.42/ipp/print")
+            .validate()
+            .is_ok()
+    );
+    assert!(
+        config("warehouse", "ipps://printer.local/ipp/print")
+            .validate()
+            .is_ok()
+    );
+    let problems = config("warehouse", "http://10.0.0.42/ipp/print")
+        .validate()
+        .unwrap_err();
+    assert!(
+        problems
+            .iter()
+            .any(|p| p.starts_with("ipp_printers.warehouse")),
+        "{:?}",
+        problems
+    );
+    assert!(config("__file__", "ipp://10.0.0.42/").validate().is_err());
+}
+
+#[test]
+fn test_redacted_headers_hide_credentials() {
+    use crate::utils::http::redacted_headers;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    let mut headers = HeaderMap::new();
+    headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+    headers.insert("cookie", HeaderValue::from_static("session=abc"));
+    headers.insert("accept", HeaderValue::from_static("application/json"));
+
+    let redacted = redacted_headers(&headers);
+    let value = |name: &str| {
+        redacted
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+    assert_eq!(value("authorization"), Some("[redacted]"));
+    assert_eq!(value("cookie"), Some("[redacted]"));
+    assert_eq!(value("accept"), Some("application/json"));
+}
+
+#[test]
+fn test_ipp_printers_never_hold_jobs() {
+    let config = Config {
+        hold_jobs: true,
+        ipp_printers: HashMap::from([(
+            "warehouse".to_string(),
+            "ipp://10.0.0.42/ipp/print".to_string(),
+        )]),
+        ..Config::default()
+    };
+    assert!(config.holds_jobs("office"));
+    assert!(!config.holds_jobs("warehouse"));
+}
+
+#[test]
+fn test_worker_threads_setting_reads_only_that_field() {
+    use crate::utils::config::worker_threads_setting;
+
+    assert_eq!(worker_threads_setting(r#"{"worker_threads": 4}"#), Some(4));
+    assert_eq!(worker_threads_setting(r#"{"worker_threads": null}"#), None);
+    assert_eq!(worker_threads_setting("{}"), None);
+    // Other fields may be invalid for Config; the file is still not touched
+    assert_eq!(
+        worker_threads_setting(r#"{"worker_threads": 2, "printer_check_interval": "soon"}"#),
+        Some(2)
+    );
+    assert_eq!(worker_threads_setting("{not json"), None);
+}
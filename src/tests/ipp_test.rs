@@ -0,0 +1,191 @@
+use std::io::Write;
+
+use ipp::prelude::*;
+use printers::common::base::job::PrinterJobState;
+
+use crate::services::printer_backend::{
+    IppBackend, PrinterBackend, ipp_http_url, job_attributes, job_state_from_ipp,
+};
+use crate::tests::{MockServer, http_response};
+
+fn properties(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Encoded IPP reply with the given status and job attributes
+fn ipp_response(status: StatusCode, job_attributes: Vec<IppAttribute>) -> Vec<u8> {
+    let mut response = IppRequestResponse::new_response(IppVersion::v1_1(), status, 1);
+    for attribute in job_attributes {
+        response
+            .attributes_mut()
+            .add(DelimiterTag::JobAttributes, attribute);
+    }
+    response.to_bytes().to_vec()
+}
+
+/// Backend for a printer that answers every request with `reply`
+async fn backend_replying(reply: Vec<u8>, max_response_mb: u64) -> IppBackend {
+    IppBackend {
+        http_client: reqwest::Client::new(),
+        uri: format!(
+            "ipp://{}/ipp/print",
+            MockServer::always(http_response(200, "application/ipp", reply))
+                .start()
+                .await
+        ),
+        user: "spooler".to_string(),
+        max_response_mb,
+    }
+}
+
+#[test]
+fn test_ipp_http_url() {
+    assert_eq!(
+        ipp_http_url("ipp://10.0.0.5/ipp/print").as_deref(),
+        Some("http://10.0.0.5:631/ipp/print")
+    );
+    assert_eq!(
+        ipp_http_url("ipps://printer.local:8631/ipp/print").as_deref(),
+        Some("https://printer.local:8631/ipp/print")
+    );
+    assert_eq!(
+        ipp_http_url("ipp://printer.local").as_deref(),
+        Some("http://printer.local:631/")
+    );
+    assert_eq!(ipp_http_url("http://printer.local/ipp/print"), None);
+}
+
+#[test]
+fn test_job_attributes_types() {
+    let attributes = job_attributes(&properties(&[
+        ("copies", "2"),
+        ("page-ranges", "1-3,5"),
+        ("fit-to-page", "true"),
+        ("media", "iso_a4_210x297mm"),
+        ("orientation-requested", "4"),
+        ("media-type", "2024"),
+        ("copies", "many"),
+    ]));
+    assert_eq!(attributes.len(), 6, "{:?}", attributes);
+    assert_eq!(attributes[0].value(), &IppValue::Integer(2));
+    assert_eq!(
+        attributes[1].value(),
+        &IppValue::Array(vec![
+            IppValue::RangeOfInteger { min: 1, max: 3 },
+            IppValue::RangeOfInteger { min: 5, max: 5 },
+        ])
+    );
+    assert_eq!(attributes[2].value(), &IppValue::Boolean(true));
+    assert_eq!(
+        attributes[3].value(),
+        &IppValue::Keyword("iso_a4_210x297mm".to_string())
+    );
+    // Typed by name, not by what the value looks like
+    assert_eq!(attributes[4].value(), &IppValue::Enum(4));
+    assert_eq!(
+        attributes[5].value(),
+        &IppValue::Keyword("2024".to_string())
+    );
+}
+
+#[test]
+fn test_job_state_from_ipp() {
+    assert_eq!(job_state_from_ipp(3), PrinterJobState::PENDING);
+    assert_eq!(job_state_from_ipp(4), PrinterJobState::PAUSED);
+    assert_eq!(job_state_from_ipp(5), PrinterJobState::PROCESSING);
+    assert_eq!(job_state_from_ipp(6), PrinterJobState::PAUSED);
+    assert_eq!(job_state_from_ipp(7), PrinterJobState::CANCELLED);
+    assert_eq!(job_state_from_ipp(8), PrinterJobState::UNKNOWN);
+    assert_eq!(job_state_from_ipp(9), PrinterJobState::COMPLETED);
+}
+
+#[tokio::test]
+async fn test_submit_returns_printer_job_id() {
+    let backend = backend_replying(
+        ipp_response(
+            StatusCode::SuccessfulOk,
+            vec![IppAttribute::new("job-id", IppValue::Integer(42))],
+        ),
+        16,
+    )
+    .await;
+    let mut document = tempfile::NamedTempFile::new().unwrap();
+    document.write_all(b"%PDF-1.4").unwrap();
+
+    let job_id = backend
+        .submit(
+            document.path(),
+            "invoice.pdf",
+            &properties(&[("copies", "2")]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(job_id, 42);
+}
+
+#[tokio::test]
+async fn test_submit_refused_by_printer() {
+    let backend = backend_replying(
+        ipp_response(StatusCode::ClientErrorBadRequest, Vec::new()),
+        16,
+    )
+    .await;
+    let document = tempfile::NamedTempFile::new().unwrap();
+
+    let error = backend
+        .submit(document.path(), "job", &[])
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("refused the job"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_job_state_reported_by_printer() {
+    let backend = backend_replying(
+        ipp_response(
+            StatusCode::SuccessfulOk,
+            vec![IppAttribute::new("job-state", IppValue::Enum(9))],
+        ),
+        16,
+    )
+    .await;
+    assert_eq!(
+        backend.job_state(42).await.unwrap(),
+        Some(PrinterJobState::COMPLETED)
+    );
+}
+
+#[tokio::test]
+async fn test_job_state_of_unknown_job() {
+    let backend = backend_replying(
+        ipp_response(StatusCode::ClientErrorNotFound, Vec::new()),
+        16,
+    )
+    .await;
+    assert_eq!(backend.job_state(42).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_truncated_reply_is_an_error() {
+    let mut reply = ipp_response(StatusCode::SuccessfulOk, Vec::new());
+    reply.truncate(reply.len() - 1);
+    let backend = backend_replying(reply, 16).await;
+    assert!(backend.job_state(42).await.is_err());
+}
+
+#[tokio::test]
+async fn test_oversized_printer_reply_is_rejected() {
+    let mut reply = ipp_response(StatusCode::SuccessfulOk, Vec::new());
+    reply.resize(2 * 1024 * 1024, 0);
+    let backend = backend_replying(reply, 1).await;
+
+    let error = backend.job_state(42).await.unwrap_err();
+    assert!(
+        error.to_string().contains("max_api_response_mb"),
+        "{}",
+        error
+    );
+}
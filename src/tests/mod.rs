@@ -5,6 +5,137 @@ pub mod config_test;
 #[cfg(test)]
 pub mod integration_test;
 #[cfg(test)]
+pub mod ipp_test;
+#[cfg(all(test, feature = "otlp"))]
+pub mod otlp_test;
+#[cfg(test)]
+pub mod parser_property_test;
+#[cfg(test)]
 pub mod print_job_service_test;
 #[cfg(test)]
+pub mod print_options_test;
+#[cfg(test)]
 pub mod printer_service_test;
+
+/// `(method, path prefix, raw HTTP responses)`; the responses are served in
+/// order and the last one is repeated once the others are used up
+#[cfg(test)]
+type MockRoute = (&'static str, &'static str, Vec<Vec<u8>>);
+
+/// Local HTTP server answering with canned responses, for running the API and
+/// IPP clients against a real socket
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockServer {
+    routes: Vec<MockRoute>,
+}
+
+#[cfg(test)]
+impl MockServer {
+    /// A server answering every request with `response`, which may be
+    /// malformed or cut short
+    pub fn always(response: impl Into<Vec<u8>>) -> Self {
+        MockServer::default().route("", "", vec![response.into()])
+    }
+
+    /// Answer `method` requests whose path starts with `path_prefix`; an empty
+    /// method matches any. Requests matching no route get a 404.
+    pub fn route(
+        mut self,
+        method: &'static str,
+        path_prefix: &'static str,
+        responses: Vec<Vec<u8>>,
+    ) -> Self {
+        assert!(!responses.is_empty(), "a route needs at least one response");
+        self.routes.push((method, path_prefix, responses));
+        self
+    }
+
+    /// Serve on a local port, one connection at a time
+    pub async fn start(mut self) -> std::net::SocketAddr {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let Some((method, path)) = read_request(&mut stream).await else {
+                    continue;
+                };
+                let response = self.next_response(&method, &path);
+                let _ = stream.write_all(&response).await;
+            }
+        });
+        addr
+    }
+
+    /// Like [`MockServer::start`], returning the base URL `http://127.0.0.1:<port>`
+    pub async fn start_url(self) -> String {
+        format!("http://{}", self.start().await)
+    }
+
+    fn next_response(&mut self, method: &str, path: &str) -> Vec<u8> {
+        self.routes
+            .iter_mut()
+            .find(|(m, prefix, _)| (m.is_empty() || *m == method) && path.starts_with(prefix))
+            .map(|(_, _, responses)| {
+                if responses.len() > 1 {
+                    responses.remove(0)
+                } else {
+                    responses[0].clone()
+                }
+            })
+            .unwrap_or_else(|| http_response(404, "application/json", ""))
+    }
+}
+
+/// Read one request and return its method and path; the body is read but ignored
+#[cfg(test)]
+async fn read_request(stream: &mut tokio::net::TcpStream) -> Option<(String, String)> {
+    use tokio::io::AsyncReadExt;
+
+    let mut request = Vec::new();
+    let mut buf = [0u8; 8192];
+    let head_len = loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        request.extend_from_slice(&buf[..n]);
+    };
+    let head = String::from_utf8_lossy(&request[..head_len]).into_owned();
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut remaining = (head_len + content_length).saturating_sub(request.len());
+    while remaining > 0 {
+        match stream.read(&mut buf).await.ok()? {
+            0 => break,
+            n => remaining = remaining.saturating_sub(n),
+        }
+    }
+
+    let mut parts = head.split_whitespace();
+    Some((parts.next()?.to_string(), parts.next()?.to_string()))
+}
+
+/// A complete HTTP response carrying `body`, closing the connection after it
+#[cfg(test)]
+pub fn http_response(status: u16, content_type: &str, body: impl AsRef<[u8]>) -> Vec<u8> {
+    let body = body.as_ref();
+    let mut response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    response
+}
@@ -0,0 +1,44 @@
+use crate::utils::otlp::{inject_trace_context, otlp_traces_url};
+
+#[test]
+fn test_otlp_traces_url() {
+    assert_eq!(
+        otlp_traces_url("http://collector:4318"),
+        "http://collector:4318/v1/traces"
+    );
+    assert_eq!(
+        otlp_traces_url("http://collector:4318/"),
+        "http://collector:4318/v1/traces"
+    );
+    assert_eq!(
+        otlp_traces_url("https://otel.example.com/v1/traces"),
+        "https://otel.example.com/v1/traces"
+    );
+}
+
+#[test]
+fn test_api_requests_carry_the_current_span() {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use reqwest::header::HeaderMap;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let provider = SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    tracing::subscriber::with_default(subscriber, || {
+        let mut outside = HeaderMap::new();
+        inject_trace_context(&mut outside);
+        assert!(outside.get("traceparent").is_none());
+
+        let span = tracing::info_span!("api_request");
+        let _entered = span.enter();
+        let mut headers = HeaderMap::new();
+        inject_trace_context(&mut headers);
+        let traceparent = headers["traceparent"].to_str().unwrap();
+        // version-traceid-spanid-flags, sampled
+        assert_eq!(traceparent.len(), 55, "{}", traceparent);
+        assert!(traceparent.starts_with("00-"), "{}", traceparent);
+        assert!(traceparent.ends_with("-01"), "{}", traceparent);
+    });
+}
@@ -0,0 +1,213 @@
+//! Property tests for the API and WebSocket parsers.
+//!
+//! Strategies generate arbitrary JSON and mutations of valid responses; the
+//! parsers must never panic and must keep every job that was left intact.
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+use serde_json::{Value, json};
+
+use crate::models::api::ApiPrinterResponse;
+use crate::services::print_job::{parse_pending_job_refs, parse_print_jobs};
+use crate::services::websocket::parse_print_job_event;
+
+/// Cases generated per property
+const CASES: u32 = 500;
+
+/// Arbitrary JSON value, nesting at most `depth` levels
+fn arbitrary_value(depth: u32) -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        (0..u64::from(u32::MAX) + 10).prop_map(Value::from),
+        (-1000i64..=0).prop_map(Value::from),
+        (0u32..1000).prop_map(|n| json!(f64::from(n) / 7.0)),
+        prop::sample::select(vec!["", "A4", "20", "completed", "ünïcødé", "{\"id\":1}"])
+            .prop_map(Value::from),
+    ];
+    leaf.prop_recursive(depth, 64, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::btree_map(
+                prop::sample::select(vec!["id", "data", "model", "media_id", "printer", "x"])
+                    .prop_map(String::from),
+                inner,
+                0..4,
+            )
+            .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+/// What a mutation does with the field it picked at one level
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Remove,
+    Descend,
+    Replace,
+}
+
+/// Replace or remove one field of a value, following `path` into objects and arrays
+#[derive(Debug, Clone)]
+struct Mutation {
+    path: Vec<(Index, Step)>,
+    replacement: Value,
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    let step = prop_oneof![Just(Step::Remove), Just(Step::Descend), Just(Step::Replace)];
+    (
+        prop::collection::vec((any::<Index>(), step), 0..5),
+        arbitrary_value(2),
+    )
+        .prop_map(|(path, replacement)| Mutation { path, replacement })
+}
+
+impl Mutation {
+    fn apply(&self, value: &mut Value) {
+        apply_steps(value, &self.path, self.replacement.clone());
+    }
+}
+
+fn apply_steps(value: &mut Value, steps: &[(Index, Step)], replacement: Value) {
+    let Some(((index, step), rest)) = steps.split_first() else {
+        *value = replacement;
+        return;
+    };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            let key = map.keys().nth(index.index(map.len())).cloned().unwrap();
+            match step {
+                Step::Remove => {
+                    map.remove(&key);
+                }
+                Step::Descend => apply_steps(map.get_mut(&key).unwrap(), rest, replacement),
+                Step::Replace => {
+                    map.insert(key, replacement);
+                }
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            let position = index.index(items.len());
+            apply_steps(&mut items[position], rest, replacement);
+        }
+        _ => *value = replacement,
+    }
+}
+
+fn valid_job(id: u32) -> Value {
+    json!({
+        "id": id,
+        "media_id": id * 10,
+        "printer_id": 3,
+        "user_id": null,
+        "quantity": 1,
+        "size": "A4",
+        "is_completed": false,
+        "cups_job_id": null,
+        "status": null,
+        "error_message": null,
+        "printed_at": null,
+        "created_at": "2024-01-01 10:00:00",
+        "created_by": null,
+        "updated_at": "2024-01-01 10:00:00",
+        "updated_by": null,
+        "printer": {"id": 3, "name": "Office"}
+    })
+}
+
+fn job_list(jobs: Vec<Value>) -> Value {
+    json!({
+        "status": 200,
+        "data": {
+            "current_page": 1, "data": jobs, "first_page_url": "", "from": 1,
+            "last_page": 1, "last_page_url": "", "links": [], "next_page_url": null,
+            "path": "", "per_page": 15, "prev_page_url": null, "to": 1, "total": 3
+        }
+    })
+}
+
+fn valid_printer_list() -> Value {
+    json!({
+        "status": 200,
+        "data": {"data": [{
+            "id": 1, "name": "Office", "system_name": "Office", "uri": null,
+            "spooler_name": "spooler", "location": null, "make_and_model": null,
+            "media_sizes": ["A4"], "is_active": true, "is_visible": true,
+            "supports_color": false
+        }]}
+    })
+}
+
+/// Feed `body` and its truncations to every parser; none may panic
+fn parse_everything(body: &str) {
+    let _ = parse_print_jobs(body);
+    let _ = parse_pending_job_refs(body);
+    let _ = serde_json::from_str::<ApiPrinterResponse>(body);
+    let _ = parse_print_job_event(body);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn test_parsers_never_panic_on_arbitrary_json(
+        value in arbitrary_value(4),
+        cut in any::<Index>()
+    ) {
+        let body = value.to_string();
+        parse_everything(&body);
+
+        let cut = body
+            .char_indices()
+            .map(|(i, _)| i)
+            .nth(cut.index(body.chars().count() + 1))
+            .unwrap_or(body.len());
+        parse_everything(&body[..cut]);
+    }
+
+    #[test]
+    fn test_parsers_never_panic_on_mutated_responses(
+        mutations in prop::collection::vec(mutation(), 3)
+    ) {
+        let samples = [
+            job_list(vec![valid_job(1), valid_job(2)]),
+            valid_printer_list(),
+            json!({"model": {"id": 20}}),
+        ];
+        for (mut sample, mutation) in samples.into_iter().zip(&mutations) {
+            mutation.apply(&mut sample);
+            parse_everything(&sample.to_string());
+        }
+    }
+
+    #[test]
+    fn test_mutated_job_does_not_drop_other_jobs(mutation in mutation()) {
+        let mut broken = valid_job(1);
+        mutation.apply(&mut broken);
+        let body = job_list(vec![broken, valid_job(2), valid_job(3)]).to_string();
+
+        let ids: Vec<u32> = parse_print_jobs(&body)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .iter()
+            .map(|job| job.id)
+            .collect();
+        prop_assert!(ids.ends_with(&[2, 3]), "intact jobs missing from {:?}", ids);
+
+        let refs: Vec<u32> = parse_pending_job_refs(&body)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .iter()
+            .map(|job| job.id)
+            .collect();
+        prop_assert!(refs.ends_with(&[2, 3]), "{:?}", refs);
+    }
+}
+
+#[test]
+fn test_print_job_event_parses_only_valid_ids() {
+    assert_eq!(parse_print_job_event(r#"{"model":{"id":20}}"#).unwrap(), 20);
+    assert!(parse_print_job_event(r#"{"model":{"id":"20"}}"#).is_err());
+    assert!(parse_print_job_event(r#"{"model":{"id":-1}}"#).is_err());
+    assert!(parse_print_job_event(r#"{"model":{"id":4294967296}}"#).is_err());
+    assert!(parse_print_job_event(r#"{"model":null}"#).is_err());
+}
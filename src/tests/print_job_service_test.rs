@@ -1,4 +1,18 @@
-use crate::models::Config;
+use std::collections::HashMap;
+
+use crate::models::{Config, PrintJobStatus, StatusFieldMapping};
+use crate::services::print_job::{PendingJobRef, parse_pending_job_refs, parse_print_jobs};
+use crate::tests::MockServer;
+use crate::utils::status_queue::{PendingStatusUpdate, enqueue_status_update, retry_delay_secs};
+
+fn paginated(jobs: &str) -> String {
+    format!(
+        r#"{{"status": 200, "data": {{"current_page": 1, "data": [{}], "first_page_url": "",
+        "from": 1, "last_page": 1, "last_page_url": "", "links": [], "next_page_url": null,
+        "path": "", "per_page": 15, "prev_page_url": null, "to": 1, "total": 1}}}}"#,
+        jobs
+    )
+}
 
 #[tokio::test]
 async fn test_config_setup() {
@@ -29,3 +43,1205 @@ async fn test_fetch_jobs_setup() {
     assert_eq!(config.flux_url, "http://test-server");
     assert!(config.flux_api_token.is_some());
 }
+
+#[test]
+fn test_parse_print_jobs_strict() {
+    let body = paginated(
+        r#"{"id": 1, "media_id": 10, "quantity": 1, "size": "A4", "is_completed": false,
+            "created_at": "2024-01-01 10:00:00", "updated_at": "2024-01-01 10:00:00"}"#,
+    );
+
+    let jobs = parse_print_jobs(&body).unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].media_id, 10);
+}
+
+#[test]
+fn test_parse_print_jobs_skips_malformed_job() {
+    // Job 2 has a wrong field type, job 3 omits non-critical fields
+    let body = paginated(
+        r#"{"id": 2, "media_id": "not-a-number", "is_completed": false},
+           {"id": 3, "media_id": 30, "is_completed": false}"#,
+    );
+
+    let jobs = parse_print_jobs(&body).unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].id, 3);
+    let ids: Vec<u32> = parse_pending_job_refs(&body)
+        .unwrap()
+        .iter()
+        .map(|r| r.id)
+        .collect();
+    assert_eq!(ids, vec![2, 3]);
+}
+
+#[test]
+fn test_parse_print_jobs_rejects_unknown_envelope() {
+    assert!(parse_print_jobs(r#"{"message": "Unauthenticated."}"#).is_err());
+    assert!(parse_pending_job_refs("<html>502 Bad Gateway</html>").is_err());
+}
+
+#[test]
+fn test_status_update_retry_backoff() {
+    assert_eq!(retry_delay_secs(1), 15);
+    assert_eq!(retry_delay_secs(2), 30);
+    assert_eq!(retry_delay_secs(3), 60);
+    assert_eq!(retry_delay_secs(100), 3600);
+}
+
+#[test]
+fn test_enqueue_status_update_replaces_older_update() {
+    let update = |status, cups_job_id| PendingStatusUpdate {
+        job_id: 5,
+        cups_job_id,
+        status,
+        error_message: None,
+        printed_at: None,
+        attempts: 1,
+        next_attempt_at: 0,
+        correction: false,
+    };
+
+    let mut queue = Vec::new();
+    enqueue_status_update(&mut queue, update(PrintJobStatus::Queued, Some(42)));
+    enqueue_status_update(&mut queue, update(PrintJobStatus::Completed, None));
+
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].status, PrintJobStatus::Completed);
+    assert_eq!(queue[0].cups_job_id, Some(42));
+}
+
+#[test]
+fn test_parse_print_jobs_with_partial_printer_relation() {
+    let body = paginated(
+        r#"{"id": 4, "media_id": 40, "printer_id": 9, "is_completed": false,
+            "printer": {"id": 9}}"#,
+    );
+
+    let jobs = parse_print_jobs(&body).unwrap();
+    assert_eq!(jobs.len(), 1);
+    let printer = jobs[0].printer.as_ref().unwrap();
+    assert_eq!(printer.id, Some(9));
+    assert!(printer.name.is_none());
+    assert!(printer.is_active.is_none());
+}
+
+#[test]
+fn test_parse_pending_job_refs_reads_printer() {
+    // Job 6 is malformed (lenient path) and only carries the included printer
+    let body = paginated(
+        r#"{"id": 5, "media_id": 50, "printer_id": 1, "is_completed": false},
+           {"id": 6, "media_id": "x", "is_completed": false, "printer": {"id": 2}}"#,
+    );
+
+    assert_eq!(
+        parse_pending_job_refs(&body).unwrap(),
+        vec![
+            PendingJobRef {
+                id: 5,
+                printer_id: Some(1)
+            },
+            PendingJobRef {
+                id: 6,
+                printer_id: Some(2)
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_text_page_pdf_structure() {
+    let pdf = crate::utils::pdf::text_page_pdf(&["Job (1) \\ test".to_string()]);
+    let text = String::from_utf8(pdf).unwrap();
+
+    assert!(text.starts_with("%PDF-1.4\n"));
+    assert!(text.ends_with("%%EOF\n"));
+    assert!(text.contains("(Job \\(1\\) \\\\ test) Tj"));
+
+    // The xref table must point at the start of each object
+    let xref_start: usize = text
+        .rsplit("startxref\n")
+        .next()
+        .and_then(|tail| tail.lines().next())
+        .and_then(|n| n.parse().ok())
+        .unwrap();
+    assert!(text[xref_start..].starts_with("xref\n"));
+    let first_offset: usize = text[xref_start..].lines().nth(3).unwrap()[..10]
+        .parse()
+        .unwrap();
+    assert!(text[first_offset..].starts_with("1 0 obj"));
+}
+
+#[test]
+fn test_pdf_page_count_counts_page_objects_only() {
+    use crate::utils::pdf::pdf_page_count;
+
+    let pdf = crate::utils::pdf::text_page_pdf(&["One page".to_string()]);
+    assert_eq!(pdf_page_count(&pdf), Some(1));
+
+    let three_pages = b"<< /Type /Pages /Count 3 >> << /Type /Page >> \
+        << /Type/Page /Parent 2 0 R >> << /Type\n/Page>>";
+    assert_eq!(pdf_page_count(three_pages), Some(3));
+
+    assert_eq!(pdf_page_count(b"plain text, no pages"), None);
+    assert_eq!(pdf_page_count(b"<< /Type /PageLabel >>"), None);
+}
+
+#[test]
+fn test_consumption_totals_per_printer() {
+    use crate::utils::consumption::{PrinterConsumption, estimate_pages, record_consumption};
+
+    let pdf = crate::utils::pdf::text_page_pdf(&["One page".to_string()]);
+    assert_eq!(estimate_pages(&pdf, 3), 3);
+    // Documents without page objects count as one page per copy
+    assert_eq!(estimate_pages(b"%!PS-Adobe-3.0", 2), 2);
+    assert_eq!(estimate_pages(b"%!PS-Adobe-3.0", 0), 1);
+
+    let mut totals = HashMap::new();
+    record_consumption(&mut totals, "Office_Laser", 3);
+    record_consumption(&mut totals, "Office_Laser", 2);
+    record_consumption(&mut totals, "Label_Printer", 1);
+    assert_eq!(
+        totals["Office_Laser"],
+        PrinterConsumption { jobs: 2, pages: 5 }
+    );
+    assert_eq!(
+        totals["Label_Printer"],
+        PrinterConsumption { jobs: 1, pages: 1 }
+    );
+}
+
+#[test]
+fn test_sweep_stale_temp_files_only_removes_prefixed_files() {
+    use crate::utils::temp_files::{TEMP_FILE_PREFIX, sweep_stale_temp_files_in};
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let stale = dir.path().join(format!("{}job.pdf", TEMP_FILE_PREFIX));
+    let foreign = dir.path().join("other-app.tmp");
+    std::fs::write(&stale, b"%PDF").unwrap();
+    std::fs::write(&foreign, b"keep").unwrap();
+
+    // Nothing is old enough yet
+    assert_eq!(
+        sweep_stale_temp_files_in(dir.path(), Duration::from_secs(3600)),
+        0
+    );
+    assert_eq!(sweep_stale_temp_files_in(dir.path(), Duration::ZERO), 1);
+    assert!(!stale.exists());
+    assert!(foreign.exists());
+}
+
+#[test]
+fn test_print_jobs_url_is_scoped_to_spooler() {
+    let config = Config {
+        flux_url: "https://flux.example".to_string(),
+        instance_name: "office 1".to_string(),
+        ..Config::default()
+    };
+
+    assert_eq!(
+        crate::services::flux_client::print_jobs_url(&config, false),
+        "https://flux.example/api/print-jobs?include=printer&filter[is_completed]=false\
+         &filter[printer.spooler_name]=office%201"
+    );
+
+    let unscoped = Config {
+        job_spooler_filter: String::new(),
+        ..config
+    };
+    assert_eq!(
+        crate::services::flux_client::print_jobs_url(&unscoped, true),
+        "https://flux.example/api/print-jobs?include=printer"
+    );
+}
+
+#[test]
+fn test_flux_client_url_ignores_trailing_slash() {
+    use crate::services::flux_client::FluxClient;
+
+    let config = Config {
+        flux_url: "https://flux.example/".to_string(),
+        ..Config::default()
+    };
+    let api = FluxClient::new(reqwest::Client::new(), config);
+
+    assert_eq!(
+        api.url("/api/printers"),
+        "https://flux.example/api/printers"
+    );
+}
+
+#[test]
+fn test_parse_print_jobs_null_heavy_empty_response() {
+    let body = r#"{"status": 200, "data": {"current_page": null, "data": null,
+        "first_page_url": null, "from": null, "last_page": null, "last_page_url": null,
+        "links": null, "next_page_url": null, "path": null, "per_page": null,
+        "prev_page_url": null, "to": null, "total": null}}"#;
+
+    assert!(parse_print_jobs(body).unwrap().is_empty());
+    assert!(parse_pending_job_refs(body).unwrap().is_empty());
+    assert!(
+        parse_print_jobs(r#"{"status": 200, "data": {"data": []}}"#)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_printer_for_location() {
+    use crate::models::{LocationMatch, Printer};
+    use crate::services::print_job::printer_for_location;
+
+    let printer = |system_name: &str, location: &str, enabled: bool| Printer {
+        name: system_name.to_string(),
+        system_name: system_name.to_string(),
+        uri: None,
+        description: String::new(),
+        location: location.to_string(),
+        make_and_model: String::new(),
+        media_sizes: Vec::new(),
+        printer_id: None,
+        enabled,
+        supports_color: false,
+        accepting_jobs: true,
+    };
+    let printers: HashMap<String, Printer> = [
+        printer("berlin_b", "Berlin Office", true),
+        printer("berlin_a", "Berlin Office", true),
+        printer("munich", "Munich Warehouse", false),
+    ]
+    .into_iter()
+    .map(|p| (p.system_name.clone(), p))
+    .collect();
+
+    assert_eq!(
+        printer_for_location(&printers, "berlin office", LocationMatch::Exact),
+        Some("berlin_a".to_string())
+    );
+    assert_eq!(
+        printer_for_location(&printers, "Berlin", LocationMatch::Exact),
+        None
+    );
+    assert_eq!(
+        printer_for_location(&printers, "Berlin", LocationMatch::Contains),
+        Some("berlin_a".to_string())
+    );
+    // Disabled printers are never routed to
+    assert_eq!(
+        printer_for_location(&printers, "Munich", LocationMatch::Contains),
+        None
+    );
+    assert_eq!(
+        printer_for_location(&printers, "", LocationMatch::Contains),
+        None
+    );
+}
+
+#[test]
+fn test_next_poll_interval_backs_off_when_idle() {
+    use crate::services::print_job::next_poll_interval;
+
+    assert_eq!(next_poll_interval(2, false, 2, 10), 4);
+    assert_eq!(next_poll_interval(4, false, 2, 10), 8);
+    assert_eq!(next_poll_interval(8, false, 2, 10), 10);
+    assert_eq!(next_poll_interval(10, false, 2, 10), 10);
+    assert_eq!(next_poll_interval(10, true, 2, 10), 2);
+    // A zero minimum still backs off
+    assert_eq!(next_poll_interval(0, false, 0, 10), 2);
+    // Fixed interval when the cap is not above the minimum
+    assert_eq!(next_poll_interval(2, false, 2, 2), 2);
+    assert_eq!(next_poll_interval(2, false, 5, 1), 5);
+}
+
+#[test]
+fn test_zero_cups_job_id_is_rejected() {
+    use crate::error::SpoolerError;
+    use crate::services::print_job::validate_cups_job_id;
+
+    assert_eq!(validate_cups_job_id(42, "office").unwrap(), 42);
+    assert!(matches!(
+        validate_cups_job_id(0, "office"),
+        Err(SpoolerError::Rejected(_))
+    ));
+}
+
+#[test]
+fn test_metadata_header_line() {
+    use crate::services::print_job::metadata_header_line;
+
+    let jobs = parse_print_jobs(&paginated(
+        r#"{"id": 7, "media_id": 1, "user_id": 42, "quantity": 3, "is_completed": false,
+            "created_at": "2026-03-01 09:30:00"},
+           {"id": 8, "media_id": 1, "is_completed": false}"#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+        metadata_header_line(&jobs[0]),
+        "Job 7 | User 42 | Quantity 3 | Created 2026-03-01 09:30:00"
+    );
+    assert_eq!(
+        metadata_header_line(&jobs[1]),
+        "Job 8 | User - | Quantity 1 | Created -"
+    );
+}
+
+#[test]
+fn test_conditional_status_update_payload() {
+    use crate::services::flux_client::status_update_payload;
+
+    let update = PendingStatusUpdate {
+        job_id: 5,
+        cups_job_id: Some(12),
+        status: PrintJobStatus::Completed,
+        error_message: None,
+        printed_at: Some("2026-03-01 09:30:00".to_string()),
+        attempts: 0,
+        next_attempt_at: 0,
+        correction: false,
+    };
+
+    let plain = status_update_payload(&update, &StatusFieldMapping::default(), false);
+    assert_eq!(plain["id"], 5);
+    assert_eq!(plain["is_completed"], true);
+    assert_eq!(plain["status"], "completed");
+    assert_eq!(plain["cups_job_id"], 12);
+    assert!(plain.get("expected_is_completed").is_none());
+    assert!(plain.get("error_message").is_none());
+
+    let conditional = status_update_payload(&update, &StatusFieldMapping::default(), true);
+    assert_eq!(conditional["expected_is_completed"], false);
+}
+
+#[test]
+fn test_only_pending_to_completed_updates_are_conditional() {
+    let update = |status, correction| PendingStatusUpdate {
+        job_id: 5,
+        cups_job_id: Some(12),
+        status,
+        error_message: None,
+        printed_at: None,
+        attempts: 0,
+        next_attempt_at: 0,
+        correction,
+    };
+
+    assert!(update(PrintJobStatus::Completed, false).is_conditional(true));
+    assert!(!update(PrintJobStatus::Completed, false).is_conditional(false));
+    assert!(!update(PrintJobStatus::Queued, false).is_conditional(true));
+    assert!(!update(PrintJobStatus::Failed, false).is_conditional(true));
+    // Reconciliation corrections and dead-letter retries of finished jobs
+    assert!(!update(PrintJobStatus::Completed, true).is_conditional(true));
+}
+
+#[test]
+fn test_status_update_payload_uses_field_mapping() {
+    use crate::services::flux_client::status_update_payload;
+
+    let update = |status| PendingStatusUpdate {
+        job_id: 9,
+        cups_job_id: None,
+        status,
+        error_message: None,
+        printed_at: None,
+        attempts: 0,
+        next_attempt_at: 0,
+        correction: false,
+    };
+    let fields = StatusFieldMapping {
+        completed_field: "done".to_string(),
+        status_field: "state".to_string(),
+        status_values: HashMap::from([
+            ("completed".to_string(), "printed".to_string()),
+            ("queued".to_string(), "pending".to_string()),
+        ]),
+    };
+
+    let completed = status_update_payload(&update(PrintJobStatus::Completed), &fields, false);
+    assert_eq!(completed["done"], true);
+    assert_eq!(completed["state"], "printed");
+    assert!(completed.get("is_completed").is_none());
+    assert!(completed.get("status").is_none());
+
+    let queued = status_update_payload(&update(PrintJobStatus::Queued), &fields, false);
+    assert_eq!(queued["done"], false);
+    assert_eq!(queued["state"], "pending");
+
+    let failed = status_update_payload(&update(PrintJobStatus::Failed), &fields, false);
+    assert_eq!(failed["state"], "failed");
+
+    let flag_only = StatusFieldMapping {
+        status_field: String::new(),
+        ..StatusFieldMapping::default()
+    };
+    let payload = status_update_payload(&update(PrintJobStatus::Failed), &flag_only, false);
+    assert_eq!(payload, serde_json::json!({"id": 9, "is_completed": true}));
+}
+
+#[test]
+fn test_status_field_mapping_validation() {
+    let config = |fields| Config {
+        status_fields: fields,
+        ..Config::default()
+    };
+    assert!(config(StatusFieldMapping::default()).validate().is_ok());
+
+    let invalid = [
+        StatusFieldMapping {
+            completed_field: String::new(),
+            status_field: String::new(),
+            ..StatusFieldMapping::default()
+        },
+        StatusFieldMapping {
+            status_field: "is_completed".to_string(),
+            ..StatusFieldMapping::default()
+        },
+        StatusFieldMapping {
+            completed_field: "id".to_string(),
+            ..StatusFieldMapping::default()
+        },
+        StatusFieldMapping {
+            status_values: HashMap::from([("done".to_string(), "x".to_string())]),
+            ..StatusFieldMapping::default()
+        },
+        StatusFieldMapping {
+            status_values: HashMap::from([("failed".to_string(), " ".to_string())]),
+            ..StatusFieldMapping::default()
+        },
+    ];
+    for fields in invalid {
+        assert!(config(fields.clone()).validate().is_err(), "{:?}", fields);
+    }
+}
+
+#[test]
+fn test_dead_letters_count_attempts_and_rotate() {
+    use std::collections::VecDeque;
+
+    use crate::models::PrintJob;
+    use crate::utils::dead_letters::{MAX_DEAD_LETTERS, forget_dead_letter, record_dead_letter};
+
+    let job: PrintJob = serde_json::from_str(
+        r#"{"id": 1, "media_id": 0, "printer_id": 3, "user_id": null, "quantity": 2,
+        "size": "A4", "is_completed": false, "cups_job_id": null, "status": null,
+        "error_message": null, "printed_at": null, "created_by": null, "updated_by": null,
+        "printer": null, "content_base64": "JVBERg==", "orientation": "landscape"}"#,
+    )
+    .unwrap();
+
+    let mut dead_letters = VecDeque::new();
+    record_dead_letter(
+        &mut dead_letters,
+        1,
+        Some(&job),
+        None,
+        "printer not found",
+        100,
+    );
+    record_dead_letter(&mut dead_letters, 2, None, Some(55), "aborted", 150);
+    record_dead_letter(&mut dead_letters, 1, None, Some(60), "aborted", 200);
+
+    assert_eq!(dead_letters.len(), 2);
+    let retried = dead_letters.back().unwrap();
+    assert_eq!(retried.job_id, 1);
+    assert_eq!(retried.attempts, 2);
+    assert_eq!(retried.cups_job_id, Some(60));
+    assert_eq!(retried.error, "aborted");
+    assert_eq!(retried.failed_at, 200);
+    // The snapshot survives a failure reported without the job, minus the inline content
+    let snapshot = retried.job.as_ref().unwrap();
+    assert_eq!(snapshot.size, "A4");
+    assert_eq!(snapshot.content_base64, None);
+    assert_eq!(dead_letters.front().unwrap().job, None);
+
+    assert!(forget_dead_letter(&mut dead_letters, 1));
+    assert!(!forget_dead_letter(&mut dead_letters, 1));
+
+    for job_id in 100..100 + MAX_DEAD_LETTERS as u32 {
+        record_dead_letter(&mut dead_letters, job_id, None, None, "failed", 300);
+    }
+    assert_eq!(dead_letters.len(), MAX_DEAD_LETTERS);
+    assert_eq!(dead_letters.front().unwrap().job_id, 100);
+}
+
+#[test]
+fn test_dead_letter_job_summary() {
+    use crate::cli::dead_letter_job_summary;
+    use crate::models::PrintJob;
+
+    let job: PrintJob = serde_json::from_str(
+        r#"{"id": 1, "media_id": 12, "printer_id": 3, "user_id": null, "quantity": 2,
+        "size": "A4", "is_completed": false, "cups_job_id": null, "status": null,
+        "error_message": null, "printed_at": null, "created_by": null, "updated_by": null,
+        "printer": {"id": 3, "name": "Office"}, "orientation": "landscape", "page_ranges": "1-3"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        dead_letter_job_summary(&job),
+        "printer Office, media 12, size A4, quantity 2, orientation landscape, pages 1-3"
+    );
+
+    let inline = PrintJob {
+        media_id: 0,
+        printer: None,
+        size: String::new(),
+        quantity: 0,
+        orientation: None,
+        page_ranges: None,
+        ..job
+    };
+    assert_eq!(
+        dead_letter_job_summary(&inline),
+        "printer #3, media inline, size -, quantity 1"
+    );
+}
+
+#[test]
+fn test_pending_fetch_queue_backoff_and_limit() {
+    use crate::error::SpoolerError;
+    use crate::utils::fetch_queue::{
+        MAX_FETCH_ATTEMPTS, enqueue_pending_fetch, is_retryable_fetch_error,
+    };
+
+    let mut queue = Vec::new();
+    enqueue_pending_fetch(&mut queue, 7, 1000);
+    enqueue_pending_fetch(&mut queue, 7, 2000);
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].attempts, 1);
+    assert_eq!(queue[0].next_attempt_at, 1015);
+    assert!(!queue[0].is_due(1014));
+    assert!(queue[0].is_due(1015));
+
+    let mut fetch = queue.remove(0);
+    let mut kept = 1;
+    while fetch.schedule_retry(0) {
+        kept += 1;
+    }
+    assert_eq!(kept, MAX_FETCH_ATTEMPTS - 1);
+
+    assert!(is_retryable_fetch_error(&SpoolerError::Api(
+        "503".to_string()
+    )));
+    assert!(!is_retryable_fetch_error(&SpoolerError::Config(
+        "x".to_string()
+    )));
+}
+
+#[test]
+fn test_remember_completed_job_keeps_newest_ids() {
+    use crate::utils::recent_jobs::remember_completed_job;
+    use std::collections::VecDeque;
+
+    let mut recent = VecDeque::new();
+    for id in [1, 2, 3] {
+        remember_completed_job(&mut recent, id, 3);
+    }
+    remember_completed_job(&mut recent, 1, 3);
+    assert_eq!(recent, VecDeque::from([2, 3, 1]));
+
+    remember_completed_job(&mut recent, 4, 3);
+    assert_eq!(recent, VecDeque::from([3, 1, 4]));
+
+    remember_completed_job(&mut recent, 5, 0);
+    assert!(recent.is_empty());
+}
+
+#[test]
+fn test_file_printer_file_name() {
+    use crate::services::print_job::file_printer_file_name;
+
+    assert_eq!(
+        file_printer_file_name(12, 1700000000, b"%PDF", None),
+        "job-12-1700000000.pdf"
+    );
+    assert_eq!(
+        file_printer_file_name(12, 5, b"\x89PNG", None),
+        "job-12-5.bin"
+    );
+    assert_eq!(file_printer_file_name(12, 5, b"", None), "job-12-5.bin");
+    assert_eq!(
+        file_printer_file_name(12, 5, b"%PDF", Some("invoice.pdf")),
+        "job-12-5-invoice.pdf"
+    );
+}
+
+#[test]
+fn test_min_media_size_rejects_small_documents() {
+    use crate::error::SpoolerError;
+    use crate::services::print_job::check_min_media_size;
+
+    assert!(check_min_media_size(1, 1, "Media 3").is_ok());
+    assert!(check_min_media_size(0, 0, "Media 3").is_ok());
+    assert!(check_min_media_size(2048, 100, "Media 3").is_ok());
+
+    match check_min_media_size(0, 1, "Media 3") {
+        Err(SpoolerError::Rejected(reason)) => {
+            assert!(reason.contains("Media 3 is 0 bytes"), "{}", reason)
+        }
+        other => panic!("expected a rejection, got {:?}", other),
+    }
+    assert!(matches!(
+        check_min_media_size(99, 100, "Media 3"),
+        Err(SpoolerError::Rejected(_))
+    ));
+}
+
+#[test]
+fn test_correlation_ids_are_unique_hex() {
+    use crate::services::print_job::new_correlation_id;
+
+    let first = new_correlation_id();
+    let second = new_correlation_id();
+    assert_ne!(first, second);
+    for id in [&first, &second] {
+        assert_eq!(id.len(), 12, "{}", id);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()), "{}", id);
+    }
+}
+
+#[test]
+fn test_quiet_hours_window() {
+    use crate::models::QuietHours;
+
+    // 2024-01-01 23:30 UTC
+    let late_evening = 1_704_151_800;
+    let overnight = QuietHours::default();
+    assert!(overnight.is_quiet_at(late_evening));
+    assert!(overnight.is_quiet_at(late_evening + 6 * 3600)); // 05:30
+    assert!(!overnight.is_quiet_at(late_evening + 6 * 3600 + 1800)); // 06:00, end is exclusive
+    assert!(!overnight.is_quiet_at(late_evening - 12 * 3600)); // 11:30
+
+    let daytime = QuietHours {
+        start: "12:00".to_string(),
+        end: "13:00".to_string(),
+        timezone: "+01:00".into(),
+    };
+    assert!(daytime.is_quiet_at(late_evening - 12 * 3600)); // 11:30 UTC = 12:30 local
+    assert!(!daytime.is_quiet_at(late_evening - 11 * 3600)); // 13:30 local
+
+    let western = QuietHours {
+        timezone: "-05:00".into(),
+        ..QuietHours::default()
+    };
+    assert!(!western.is_quiet_at(late_evening)); // 18:30 local
+
+    let invalid = QuietHours {
+        start: "25:00".to_string(),
+        ..QuietHours::default()
+    };
+    assert!(!invalid.is_quiet_at(late_evening));
+}
+
+#[test]
+fn test_quiet_hours_follow_iana_timezone() {
+    use crate::models::QuietHours;
+
+    let berlin = QuietHours {
+        timezone: "Europe/Berlin".into(),
+        ..QuietHours::default()
+    };
+    assert!(!berlin.is_quiet_at(1_704_141_000)); // 2024-01-01 20:30 UTC = 21:30 CET
+    assert!(berlin.is_quiet_at(1_719_865_800)); // 2024-07-01 20:30 UTC = 22:30 CEST
+    assert!(berlin.is_quiet_at(2_224_787_400)); // 2040-07-01 20:30 UTC
+
+    let unknown = QuietHours {
+        timezone: "Europe/Atlantis".into(),
+        ..QuietHours::default()
+    };
+    assert!(!unknown.is_quiet_at(1_719_865_800));
+
+    // Configs written before the field was renamed
+    let renamed: QuietHours = serde_json::from_str(r#"{"utc_offset": "Europe/Berlin"}"#).unwrap();
+    assert!(renamed.is_quiet_at(1_719_865_800));
+    assert_eq!(
+        serde_json::to_value(&renamed).unwrap()["timezone"],
+        "Europe/Berlin"
+    );
+}
+
+#[test]
+fn test_quiet_hours_validation() {
+    use crate::models::QuietHours;
+
+    let config = Config {
+        quiet_hours: Some(QuietHours {
+            start: "22".to_string(),
+            end: "06:60".to_string(),
+            timezone: "01:00".into(),
+        }),
+        ..Config::default()
+    };
+    let problems = config.validate().unwrap_err();
+    assert_eq!(
+        problems
+            .iter()
+            .filter(|p| p.starts_with("quiet_hours."))
+            .count(),
+        3,
+        "{:?}",
+        problems
+    );
+}
+
+#[test]
+fn test_priority_bypasses_quiet_hours() {
+    let jobs = parse_print_jobs(&paginated(
+        r#"{"id": 1, "media_id": 2, "printer_id": null, "user_id": null, "is_completed": false,
+        "cups_job_id": null, "status": null, "error_message": null, "printed_at": null,
+        "created_by": null, "updated_by": null, "printer": null, "priority": "URGENT"},
+        {"id": 2, "media_id": 3, "printer_id": null, "user_id": null, "is_completed": false,
+        "cups_job_id": null, "status": null, "error_message": null, "printed_at": null,
+        "created_by": null, "updated_by": null, "printer": null, "priority": "low"}"#,
+    ))
+    .unwrap();
+    assert!(jobs[0].is_urgent());
+    assert!(!jobs[1].is_urgent());
+}
+
+async fn download_from(
+    response: String,
+) -> crate::error::SpoolerResult<crate::services::print_job::DownloadedMedia> {
+    use crate::services::flux_client::FluxClient;
+    use crate::services::print_job::download_file;
+
+    let config = Config {
+        flux_url: MockServer::always(response).start_url().await,
+        ..Config::default()
+    };
+    download_file(&FluxClient::new(reqwest::Client::new(), config), 7).await
+}
+
+#[tokio::test]
+async fn test_truncated_download_is_rejected() {
+    use crate::error::SpoolerError;
+
+    let result = download_from(
+        "HTTP/1.1 200 OK\r\nContent-Length: 100\r\nConnection: close\r\n\r\n%PDF-1.4 cut short"
+            .to_string(),
+    )
+    .await;
+    assert!(
+        matches!(&result, Err(SpoolerError::Rejected(reason)) if reason.contains("of 100 bytes")),
+        "{:?}",
+        result.map(|_| ())
+    );
+}
+
+#[tokio::test]
+async fn test_download_checksum_is_verified() {
+    use crate::error::SpoolerError;
+
+    let body = "abc";
+    let respond = |sha256: &str| {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nX-Content-SHA256: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            sha256,
+            body
+        )
+    };
+
+    let matching = download_from(respond(
+        "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD",
+    ))
+    .await;
+    assert!(matching.is_ok(), "{:?}", matching.map(|_| ()));
+
+    let mismatched = download_from(respond(&"0".repeat(64))).await;
+    assert!(
+        matches!(&mismatched, Err(SpoolerError::Rejected(reason)) if reason.contains("SHA-256")),
+        "{:?}",
+        mismatched.map(|_| ())
+    );
+}
+
+#[test]
+fn test_parse_api_timestamp() {
+    use crate::services::print_job::parse_api_timestamp;
+
+    assert_eq!(parse_api_timestamp("1970-01-01 00:00:00"), Some(0));
+    assert_eq!(
+        parse_api_timestamp("2024-01-01 23:30:00"),
+        Some(1_704_151_800)
+    );
+    assert_eq!(
+        parse_api_timestamp("2024-01-01T23:30:00.000000Z"),
+        Some(1_704_151_800)
+    );
+    assert_eq!(
+        parse_api_timestamp("2024-01-02T00:30:00+01:00"),
+        Some(1_704_151_800)
+    );
+    assert_eq!(
+        parse_api_timestamp("2024-02-29 12:00:00"),
+        Some(1_709_208_000)
+    );
+    assert_eq!(parse_api_timestamp(""), None);
+    assert_eq!(parse_api_timestamp("2024-13-01 00:00:00"), None);
+    assert_eq!(parse_api_timestamp("yesterday"), None);
+}
+
+#[test]
+fn test_is_job_stale() {
+    use crate::services::print_job::is_job_stale;
+
+    let now = 1_704_151_800; // 2024-01-01 23:30 UTC
+    assert!(is_job_stale("2024-01-01 10:00:00", 12, now));
+    assert!(!is_job_stale("2024-01-01 12:00:00", 12, now));
+    assert!(!is_job_stale("2023-01-01 00:00:00", 0, now));
+    assert!(!is_job_stale("not a date", 1, now));
+    assert!(!is_job_stale("", 1, now));
+}
+
+#[test]
+fn test_skipped_jobs_list() {
+    use crate::utils::skipped_jobs::{MAX_SKIPPED_JOBS, forget_skipped_job, record_skipped_job};
+    use std::collections::VecDeque;
+
+    let mut skipped = VecDeque::new();
+    record_skipped_job(&mut skipped, 1, "quiet hours", 100);
+    record_skipped_job(&mut skipped, 2, "quiet hours", 110);
+    record_skipped_job(&mut skipped, 1, "deferred: printer stopped", 120);
+
+    let ids: Vec<u32> = skipped.iter().map(|job| job.job_id).collect();
+    assert_eq!(ids, vec![2, 1]);
+    assert_eq!(skipped[1].reason, "deferred: printer stopped");
+    assert_eq!(skipped[1].skipped_at, 120);
+
+    assert!(forget_skipped_job(&mut skipped, 2));
+    assert!(!forget_skipped_job(&mut skipped, 2));
+
+    for id in 0..(MAX_SKIPPED_JOBS as u32 + 5) {
+        record_skipped_job(&mut skipped, 1000 + id, "quiet hours", 200);
+    }
+    assert_eq!(skipped.len(), MAX_SKIPPED_JOBS);
+    assert_eq!(skipped.front().unwrap().job_id, 1005);
+}
+
+#[test]
+fn test_format_unix_utc() {
+    use crate::services::print_job::format_unix_utc;
+
+    assert_eq!(format_unix_utc(0), "1970-01-01 00:00:00");
+    assert_eq!(format_unix_utc(1_709_208_000), "2024-02-29 12:00:00");
+}
+
+#[test]
+fn test_reconciled_status() {
+    use crate::services::print_job::reconciled_status;
+    use printers::common::base::job::PrinterJobState;
+
+    assert_eq!(
+        reconciled_status(&PrintJobStatus::Failed, Some(PrinterJobState::COMPLETED)),
+        Some(PrintJobStatus::Completed)
+    );
+    assert_eq!(
+        reconciled_status(&PrintJobStatus::Completed, Some(PrinterJobState::CANCELLED)),
+        Some(PrintJobStatus::Cancelled)
+    );
+    assert_eq!(
+        reconciled_status(&PrintJobStatus::Completed, Some(PrinterJobState::COMPLETED)),
+        None
+    );
+    // Not final yet, or no longer known to CUPS: leave the reported status alone
+    assert_eq!(
+        reconciled_status(&PrintJobStatus::Failed, Some(PrinterJobState::PROCESSING)),
+        None
+    );
+    assert_eq!(reconciled_status(&PrintJobStatus::Failed, None), None);
+}
+
+#[test]
+fn test_held_jobs_stay_held_until_released() {
+    use crate::services::print_job::cups_job_status;
+    use printers::common::base::job::PrinterJobState;
+
+    assert_eq!(
+        cups_job_status(PrinterJobState::PAUSED, &PrintJobStatus::Held),
+        PrintJobStatus::Held
+    );
+    assert_eq!(
+        cups_job_status(PrinterJobState::PROCESSING, &PrintJobStatus::Held),
+        PrintJobStatus::Processing
+    );
+    // A job paused in CUPS without `hold_jobs` still counts as queued
+    assert_eq!(
+        cups_job_status(PrinterJobState::PAUSED, &PrintJobStatus::Queued),
+        PrintJobStatus::Queued
+    );
+}
+
+#[test]
+fn test_finished_jobs_are_capped_and_pruned() {
+    use crate::utils::finished_jobs::{
+        FinishedJob, MAX_FINISHED_JOBS, RECONCILE_WINDOW_SECS, prune_finished_jobs,
+        record_finished_job,
+    };
+
+    let finished = |api_job_id: u32, finished_at: u64| FinishedJob {
+        api_job_id,
+        cups_job_id: u64::from(api_job_id) + 100,
+        printer_name: "Office".to_string(),
+        reported_status: PrintJobStatus::Completed,
+        finished_at,
+    };
+
+    let mut jobs = Vec::new();
+    record_finished_job(&mut jobs, finished(1, 10));
+    record_finished_job(&mut jobs, finished(1, 20));
+    assert_eq!(jobs, vec![finished(1, 20)]);
+
+    for id in 0..(MAX_FINISHED_JOBS as u32 + 3) {
+        record_finished_job(&mut jobs, finished(1000 + id, 50));
+    }
+    assert_eq!(jobs.len(), MAX_FINISHED_JOBS);
+    assert_eq!(jobs[0].api_job_id, 1003);
+
+    record_finished_job(&mut jobs, finished(1, 100 + RECONCILE_WINDOW_SECS));
+    assert!(prune_finished_jobs(&mut jobs, 100 + RECONCILE_WINDOW_SECS));
+    assert_eq!(jobs, vec![finished(1, 100 + RECONCILE_WINDOW_SECS)]);
+    assert!(!prune_finished_jobs(&mut jobs, 100 + RECONCILE_WINDOW_SECS));
+}
+
+#[test]
+fn test_hmac_sha256_known_vectors() {
+    use crate::services::websocket::hmac_sha256_hex;
+
+    // RFC 4231 test cases 2 and 6 (key longer than a block)
+    assert_eq!(
+        hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    assert_eq!(
+        hmac_sha256_hex(
+            &[0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First"
+        ),
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+    );
+}
+
+#[test]
+fn test_bench_latency_percentiles() {
+    use crate::bench::percentile;
+    use std::time::Duration;
+
+    let latencies: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+    assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(5));
+    assert_eq!(percentile(&latencies, 90.0), Duration::from_millis(9));
+    assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(10));
+    assert_eq!(percentile(&latencies, 0.0), Duration::from_millis(1));
+    assert_eq!(percentile(&latencies[..1], 99.0), Duration::from_millis(1));
+}
+
+#[test]
+fn test_bench_counts_captured_jobs() {
+    use crate::bench::captured_job_ids;
+    use std::collections::HashSet;
+
+    let dir = tempfile::tempdir().unwrap();
+    for name in [
+        "job-1-1700000000.pdf",
+        "job-12-1700000000-invoice.pdf",
+        "notes.txt",
+    ] {
+        std::fs::write(dir.path().join(name), b"%PDF").unwrap();
+    }
+    assert_eq!(captured_job_ids(dir.path()), HashSet::from([1, 12]));
+    assert!(captured_job_ids(&dir.path().join("missing")).is_empty());
+}
+
+#[test]
+fn test_pre_print_args() {
+    use crate::services::pre_print::pre_print_args;
+
+    assert_eq!(
+        pre_print_args(
+            "rotate --angle 90 {input} {output}",
+            "/tmp/in file",
+            "/tmp/out"
+        ),
+        Some(vec![
+            "rotate".to_string(),
+            "--angle".to_string(),
+            "90".to_string(),
+            "/tmp/in file".to_string(),
+            "/tmp/out".to_string(),
+        ])
+    );
+    assert_eq!(
+        pre_print_args("convert --input={input}", "/tmp/a", "/tmp/b"),
+        Some(vec!["convert".to_string(), "--input=/tmp/a".to_string()])
+    );
+    assert_eq!(pre_print_args("   ", "/tmp/a", "/tmp/b"), None);
+}
+
+#[tokio::test]
+async fn test_pre_print_command_replaces_document() {
+    use crate::error::SpoolerError;
+    use crate::services::pre_print::run_pre_print_command;
+    use std::io::Write;
+
+    let mut input = tempfile::NamedTempFile::new().unwrap();
+    input.write_all(b"%PDF-1.4 original").unwrap();
+    let config = |command: &str| Config {
+        pre_print_command_enabled: true,
+        pre_print_command: Some(command.to_string()),
+        ..Config::default()
+    };
+
+    let from_stdout = run_pre_print_command(&config("cat {input}"), 1, input.path())
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read(from_stdout.path()).unwrap(),
+        b"%PDF-1.4 original"
+    );
+
+    let from_file = run_pre_print_command(&config("cp {input} {output}"), 1, input.path())
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read(from_file.path()).unwrap(),
+        b"%PDF-1.4 original"
+    );
+
+    let failed = run_pre_print_command(&config("false {input}"), 1, input.path()).await;
+    assert!(matches!(failed, Err(SpoolerError::Rejected(_))));
+    let missing =
+        run_pre_print_command(&config("no-such-command-xyz {input}"), 1, input.path()).await;
+    assert!(matches!(missing, Err(SpoolerError::Rejected(_))));
+}
+
+#[tokio::test]
+async fn test_download_keeps_content_disposition_filename() {
+    let body = "%PDF-1.4 invoice";
+    let media = download_from(format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+         Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(media.filename.as_deref(), Some("invoice.pdf"));
+    assert!(media.file.path().to_string_lossy().ends_with(".pdf"));
+
+    let media = download_from(format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ))
+    .await
+    .unwrap();
+    assert_eq!(media.filename, None);
+}
+
+#[test]
+fn test_content_disposition_filename() {
+    use crate::utils::http::content_disposition_filename;
+
+    assert_eq!(
+        content_disposition_filename("attachment; filename=\"invoice.pdf\"").as_deref(),
+        Some("invoice.pdf")
+    );
+    assert_eq!(
+        content_disposition_filename("inline; filename=label.zpl").as_deref(),
+        Some("label.zpl")
+    );
+    // The RFC 5987 form wins and is percent-decoded
+    assert_eq!(
+        content_disposition_filename(
+            "attachment; filename=\"fallback.pdf\"; filename*=UTF-8''Rechnung%20M%C3%A4rz.pdf"
+        )
+        .as_deref(),
+        Some("Rechnung März.pdf")
+    );
+    // Directory parts are never kept
+    assert_eq!(
+        content_disposition_filename("attachment; filename=\"../../etc/passwd\"").as_deref(),
+        Some("passwd")
+    );
+    assert_eq!(content_disposition_filename("attachment"), None);
+    assert_eq!(
+        content_disposition_filename("attachment; filename=\"\""),
+        None
+    );
+}
+
+#[test]
+fn test_zero_media_id_is_rejected_as_missing_media() {
+    use crate::error::SpoolerError;
+    use crate::models::PrintJob;
+    use crate::services::print_job::check_job_has_media;
+
+    let job = |extra: &str| -> PrintJob {
+        serde_json::from_str(&format!(
+            r#"{{"id": 7, "media_id": 0, "printer_id": null, "user_id": null,
+            "is_completed": false, "cups_job_id": null, "status": null,
+            "error_message": null, "printed_at": null, "created_by": null,
+            "updated_by": null, "printer": null{}}}"#,
+            extra
+        ))
+        .unwrap()
+    };
+
+    assert!(matches!(
+        check_job_has_media(&job("")),
+        Err(SpoolerError::Rejected(reason)) if reason.starts_with("Missing media")
+    ));
+    assert!(check_job_has_media(&job(r#", "content_base64": "JVBERg==""#)).is_ok());
+    let mut with_media = job("");
+    with_media.media_id = 12;
+    assert!(check_job_has_media(&with_media).is_ok());
+}
+
+#[tokio::test]
+async fn test_api_response_over_size_limit_is_rejected() {
+    use crate::services::flux_client::FluxClient;
+
+    let list_jobs = |response: String| async move {
+        let config = Config {
+            flux_url: MockServer::always(response).start_url().await,
+            max_api_response_mb: 1,
+            ..Config::default()
+        };
+        FluxClient::new(reqwest::Client::new(), config)
+            .list_jobs(false)
+            .await
+    };
+
+    // Announced as too large
+    let result = list_jobs(
+        "HTTP/1.1 200 OK\r\nContent-Length: 2000000\r\nConnection: close\r\n\r\n{}".to_string(),
+    )
+    .await;
+    assert!(
+        matches!(&result, Err(e) if e.to_string().contains("max_api_response_mb")),
+        "{:?}",
+        result.map(|jobs| jobs.len())
+    );
+
+    // No length announced, the body just keeps going
+    let result = list_jobs(format!(
+        "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}",
+        " ".repeat(1_100_000)
+    ))
+    .await;
+    assert!(
+        matches!(&result, Err(e) if e.to_string().contains("max_api_response_mb")),
+        "{:?}",
+        result.map(|jobs| jobs.len())
+    );
+
+    let jobs = list_jobs(format!(
+        "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}",
+        paginated("")
+    ))
+    .await
+    .unwrap();
+    assert!(jobs.is_empty());
+}
@@ -0,0 +1,193 @@
+use crate::models::{Config, PrintJob, PrinterOverrides};
+use crate::services::print_options::{
+    job_print_properties, media_type_option, number_up_option, orientation_option,
+    page_ranges_option, scaling_option,
+};
+
+fn job(json: &str) -> PrintJob {
+    serde_json::from_str(json).unwrap()
+}
+
+fn option(key: &str, value: &str) -> Option<(String, String)> {
+    Some((key.to_string(), value.to_string()))
+}
+
+#[test]
+fn test_orientation_option_mapping() {
+    assert_eq!(
+        orientation_option("portrait"),
+        option("orientation-requested", "3")
+    );
+    assert_eq!(
+        orientation_option("Landscape"),
+        option("orientation-requested", "4")
+    );
+    assert_eq!(orientation_option("sideways"), None);
+}
+
+#[test]
+fn test_scaling_option_mapping() {
+    assert_eq!(scaling_option("fit"), option("fit-to-page", "true"));
+    assert_eq!(scaling_option("fill"), option("print-scaling", "fill"));
+    assert_eq!(scaling_option("none"), option("print-scaling", "none"));
+    assert_eq!(scaling_option("80%"), option("scaling", "80"));
+    assert_eq!(scaling_option("120"), option("scaling", "120"));
+    assert_eq!(scaling_option("0%"), None);
+    assert_eq!(scaling_option("huge"), None);
+}
+
+#[test]
+fn test_number_up_option_mapping() {
+    assert_eq!(number_up_option(1), option("number-up", "1"));
+    assert_eq!(number_up_option(2), option("number-up", "2"));
+    assert_eq!(number_up_option(16), option("number-up", "16"));
+    assert_eq!(number_up_option(3), None);
+    assert_eq!(number_up_option(0), None);
+}
+
+#[test]
+fn test_page_ranges_option_mapping() {
+    assert_eq!(page_ranges_option("3"), option("page-ranges", "3"));
+    assert_eq!(page_ranges_option("1,3-5"), option("page-ranges", "1,3-5"));
+    assert_eq!(
+        page_ranges_option(" 1, 3 - 5 "),
+        option("page-ranges", "1,3-5")
+    );
+    assert_eq!(page_ranges_option("2-2"), option("page-ranges", "2-2"));
+    assert_eq!(page_ranges_option(""), None);
+    assert_eq!(page_ranges_option("0"), None);
+    assert_eq!(page_ranges_option("5-3"), None);
+    assert_eq!(page_ranges_option("1,,3"), None);
+    assert_eq!(page_ranges_option("1-"), None);
+    assert_eq!(page_ranges_option("1-3-5"), None);
+    assert_eq!(page_ranges_option("odd"), None);
+
+    let job = job(
+        r#"{"id": 1, "media_id": 1, "quantity": 1, "is_completed": false,
+        "page_ranges": "3"}"#,
+    );
+    assert!(
+        job_print_properties(&job, "Office", &Config::default())
+            .contains(&("page-ranges".to_string(), "3".to_string()))
+    );
+}
+
+#[test]
+fn test_job_options_override_printer_defaults() {
+    let mut config = Config::default();
+    config.printer_overrides.insert(
+        "label_printer".to_string(),
+        PrinterOverrides {
+            orientation: Some("landscape".to_string()),
+            scaling: Some("fit".to_string()),
+            number_up: Some(4),
+            media_type: None,
+            ready_wait_secs: None,
+            hold_jobs: None,
+        },
+    );
+
+    let defaults_only = job(r#"{"id": 1, "media_id": 1, "quantity": 2, "is_completed": false}"#);
+    assert_eq!(
+        job_print_properties(&defaults_only, "label_printer", &config),
+        vec![
+            ("copies".to_string(), "2".to_string()),
+            ("orientation-requested".to_string(), "4".to_string()),
+            ("fit-to-page".to_string(), "true".to_string()),
+            ("number-up".to_string(), "4".to_string()),
+        ]
+    );
+
+    // Job values win, invalid ones are dropped instead of falling back to the override
+    let with_job_values = job(r#"{"id": 2, "media_id": 1, "is_completed": false,
+            "orientation": "portrait", "scaling": "bogus", "number_up": 3}"#);
+    assert_eq!(
+        job_print_properties(&with_job_values, "label_printer", &config),
+        vec![
+            ("copies".to_string(), "1".to_string()),
+            ("orientation-requested".to_string(), "3".to_string()),
+        ]
+    );
+
+    assert_eq!(
+        job_print_properties(&defaults_only, "office", &config),
+        vec![("copies".to_string(), "2".to_string())]
+    );
+}
+
+#[test]
+fn test_media_type_option_mapping() {
+    let allowed = Config::default().allowed_media_types;
+    assert_eq!(
+        media_type_option("labels", &allowed),
+        option("media-type", "labels")
+    );
+    assert_eq!(
+        media_type_option(" Photographic-Glossy ", &allowed),
+        option("media-type", "photographic-glossy")
+    );
+    assert_eq!(media_type_option("sandpaper", &allowed), None);
+    assert_eq!(media_type_option("", &allowed), None);
+
+    let custom = vec!["Thermal-Labels".to_string()];
+    assert_eq!(
+        media_type_option("thermal-labels", &custom),
+        option("media-type", "Thermal-Labels")
+    );
+    assert_eq!(media_type_option("labels", &custom), None);
+    assert_eq!(
+        media_type_option("anything", &[]),
+        option("media-type", "anything")
+    );
+}
+
+#[test]
+fn test_job_media_type_overrides_printer_default() {
+    let mut config = Config::default();
+    config.printer_overrides.insert(
+        "zebra".to_string(),
+        PrinterOverrides {
+            media_type: Some("labels".to_string()),
+            ..PrinterOverrides::default()
+        },
+    );
+
+    let from_override = job(r#"{"id": 1, "media_id": 1, "is_completed": false}"#);
+    assert_eq!(
+        job_print_properties(&from_override, "zebra", &config),
+        vec![
+            ("copies".to_string(), "1".to_string()),
+            ("media-type".to_string(), "labels".to_string()),
+        ]
+    );
+
+    let from_job =
+        job(r#"{"id": 2, "media_id": 1, "is_completed": false, "media_type": "cardstock"}"#);
+    assert_eq!(
+        job_print_properties(&from_job, "zebra", &config)[1],
+        ("media-type".to_string(), "cardstock".to_string())
+    );
+
+    let unknown = job(r#"{"id": 3, "media_id": 1, "is_completed": false, "media_type": "foil"}"#);
+    assert_eq!(job_print_properties(&unknown, "zebra", &config).len(), 1);
+}
+
+#[test]
+fn test_hold_jobs_adds_job_hold_until() {
+    let mut config = Config {
+        hold_jobs: true,
+        ..Config::default()
+    };
+    config.printer_overrides.insert(
+        "Office".to_string(),
+        PrinterOverrides {
+            hold_jobs: Some(false),
+            ..PrinterOverrides::default()
+        },
+    );
+    let job = job(r#"{"id": 1, "media_id": 1, "quantity": 1, "is_completed": false}"#);
+    let hold = ("job-hold-until".to_string(), "indefinite".to_string());
+
+    assert!(job_print_properties(&job, "Label", &config).contains(&hold));
+    assert!(!job_print_properties(&job, "Office", &config).contains(&hold));
+}
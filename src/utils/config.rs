@@ -1,51 +1,216 @@
-use crate::models::Config;
+use crate::error::{SpoolerError, SpoolerResult};
+use crate::models::{Config, REDACTED};
 use std::fs;
-use std::sync::{Arc, RwLock};
-use tracing::{debug, warn};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::RwLock;
+use tracing::{debug, error, warn};
 
-/// Clone config from a shared RwLock
-pub fn read_config(config: &Arc<RwLock<Config>>) -> Config {
-    config
-        .read()
-        .expect("Failed to acquire config read lock")
-        .clone()
+/// Environment variable that enables strict config loading like `--strict-config`
+pub const STRICT_CONFIG_ENV: &str = "NUXBE_STRICT_CONFIG";
+
+/// Environment variable naming the config directory, taking precedence over the home directory
+pub const CONFIG_DIR_ENV: &str = "NUXBE_CONFIG_DIR";
+
+/// Config directory when there is neither a home directory nor `XDG_CONFIG_HOME`
+pub const SYSTEM_CONFIG_DIR: &str = "/etc/nuxbe-printer-bridge";
+
+/// Set from `--strict-config` at startup
+static STRICT_CONFIG: AtomicBool = AtomicBool::new(false);
+
+/// Directory used instead of `~/.config/nuxbe-printer-bridge` once set
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keep the config and every state file in `dir` for the rest of the process.
+///
+/// Only the first call has an effect.
+pub fn set_config_dir(dir: PathBuf) {
+    let _ = CONFIG_DIR_OVERRIDE.set(dir);
+}
+
+/// Make [`load_config`] exit instead of falling back to defaults on a corrupt file
+pub fn set_strict_config(strict: bool) {
+    STRICT_CONFIG.store(strict, Ordering::Relaxed);
+}
+
+/// Whether an environment flag value means "enabled" (`1`, `true`, `yes`, `on`)
+pub fn env_flag_enabled(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// Whether strict config loading is enabled by flag or environment
+fn strict_config() -> bool {
+    STRICT_CONFIG.load(Ordering::Relaxed)
+        || std::env::var(STRICT_CONFIG_ENV).is_ok_and(|v| env_flag_enabled(&v))
 }
 
-/// Path to the config directory
+/// Configuration shared by the background tasks.
+///
+/// Uses the async `tokio` lock so a guard can never block a runtime thread;
+/// take a snapshot with [`read_config`] instead of holding it across `.await`.
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+/// Clone config from the shared lock
+pub async fn read_config(config: &SharedConfig) -> Config {
+    config.read().await.clone()
+}
+
+/// Read and parse `config.json` without falling back to defaults or writing it
+pub fn read_config_file() -> SpoolerResult<Config> {
+    let contents = fs::read_to_string(config_path())?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// `worker_threads` from `config.json`, read before the runtime starts.
+///
+/// Only that field is looked at and nothing is written, so a broken file is
+/// left for the command itself (and `--strict-config`) to deal with.
+pub fn configured_worker_threads() -> Option<usize> {
+    worker_threads_setting(&fs::read_to_string(config_path()).ok()?)
+}
+
+/// The `worker_threads` value of a config file's contents, if it has a valid one
+pub fn worker_threads_setting(contents: &str) -> Option<usize> {
+    let value: serde_json::Value = serde_json::from_str(contents).ok()?;
+    usize::try_from(value.get("worker_threads")?.as_u64()?).ok()
+}
+
+/// Path to the config directory, see [`resolve_config_dir`]
 pub fn config_dir() -> std::path::PathBuf {
-    let home_dir = dirs::home_dir().expect("Failed to get home directory");
-    home_dir.join(".config/nuxbe-printer-bridge")
+    if let Some(dir) = CONFIG_DIR_OVERRIDE.get() {
+        return dir.clone();
+    }
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    resolve_config_dir(
+        env_dir(CONFIG_DIR_ENV),
+        dirs::home_dir(),
+        env_dir("XDG_CONFIG_HOME"),
+    )
+}
+
+/// Pick the config directory: `NUXBE_CONFIG_DIR` if set, else
+/// `~/.config/nuxbe-printer-bridge`. Without a home directory (service
+/// accounts such as systemd `DynamicUser`) `$XDG_CONFIG_HOME/nuxbe-printer-bridge`
+/// is used, then [`SYSTEM_CONFIG_DIR`].
+pub fn resolve_config_dir(
+    env_dir: Option<PathBuf>,
+    home_dir: Option<PathBuf>,
+    xdg_config_home: Option<PathBuf>,
+) -> PathBuf {
+    if let Some(dir) = env_dir {
+        return dir;
+    }
+    if let Some(home_dir) = home_dir {
+        return home_dir.join(".config/nuxbe-printer-bridge");
+    }
+    match xdg_config_home {
+        Some(dir) => dir.join("nuxbe-printer-bridge"),
+        None => PathBuf::from(SYSTEM_CONFIG_DIR),
+    }
+}
+
+/// Whether `dir` is the [`SYSTEM_CONFIG_DIR`] fallback, which is only read:
+/// a service account without a home directory usually cannot write to it
+pub fn is_read_only_config_dir(dir: &std::path::Path) -> bool {
+    dir == std::path::Path::new(SYSTEM_CONFIG_DIR)
 }
 
 /// Path to the config file
-fn config_path() -> std::path::PathBuf {
+pub fn config_path() -> std::path::PathBuf {
     config_dir().join("config.json")
 }
 
 /// Load configuration from file or create default if it doesn't exist
+///
+/// A file that fails to parse is replaced with the defaults, unless strict
+/// config loading is enabled: then the process exits and the file is left untouched.
+/// In the read-only [`SYSTEM_CONFIG_DIR`] nothing is created or written; a
+/// config directory that cannot be created ends the process with an error.
 pub fn load_config() -> Config {
     let config_dir = config_dir();
     let config_path = config_path();
+    let read_only = is_read_only_config_dir(&config_dir);
 
     // create_dir_all is idempotent - no need to check existence first
-    fs::create_dir_all(&config_dir).expect("Failed to create config directory");
+    if !read_only && let Err(e) = fs::create_dir_all(&config_dir) {
+        error!(error = %e, path = %config_dir.display(), "Failed to create config directory");
+        eprintln!(
+            "Error: Failed to create config directory {}: {} (set {} to a writable directory)",
+            config_dir.display(),
+            e,
+            CONFIG_DIR_ENV
+        );
+        std::process::exit(1);
+    }
+    let save_defaults = |config: &Config| {
+        if read_only {
+            debug!(path = %config_path.display(), "Read-only config directory, not writing defaults");
+        } else {
+            save_config(config);
+        }
+    };
 
     match fs::read_to_string(&config_path) {
         Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            if strict_config() {
+                error!(
+                    error = %e,
+                    path = %config_path.display(),
+                    "Error parsing config file, refusing to start with defaults (strict config)"
+                );
+                eprintln!("Error: Failed to parse {}: {}", config_path.display(), e);
+                std::process::exit(1);
+            }
             warn!(error = %e, "Error parsing config file, using default configuration");
             let default_config = Config::default();
-            save_config(&default_config);
+            save_defaults(&default_config);
             default_config
         }),
         Err(_) => {
             debug!("Config file not found, creating with default values");
             let default_config = Config::default();
-            save_config(&default_config);
+            save_defaults(&default_config);
             default_config
         }
     }
 }
 
+/// Hostname of this machine, or `None` if it cannot be determined
+pub fn system_hostname() -> Option<String> {
+    let hostname = hostname::get().ok()?;
+    let hostname = hostname.to_string_lossy();
+    let hostname = hostname.trim();
+    (!hostname.is_empty()).then(|| hostname.to_string())
+}
+
+/// Apply `instance_name_from_hostname` to a loaded configuration.
+///
+/// The derived name is only used in memory; config.json keeps `default-instance`.
+pub fn apply_hostname_instance_name(config: &mut Config) {
+    if !config.instance_name_from_hostname {
+        return;
+    }
+    match system_hostname() {
+        Some(hostname) => {
+            if config.derive_instance_name(&hostname) {
+                warn!(
+                    instance = %config.instance_name,
+                    "instance_name not set, using the hostname as instance_name"
+                );
+            }
+        }
+        None => warn!("instance_name_from_hostname is set but the hostname could not be read"),
+    }
+}
+
 /// Save configuration to file
 pub fn save_config(config: &Config) {
     let config_dir = config_dir();
@@ -66,3 +231,28 @@ pub fn save_config(config: &Config) {
         Err(e) => warn!(error = %e, "Failed to serialize config"),
     }
 }
+
+/// Parse an exported configuration for import and validate it.
+///
+/// Secrets missing from the export (or redacted) keep their `current` values,
+/// so a config exported without `--with-secrets` can be imported onto a spooler
+/// that already has its own token.
+pub fn import_config(contents: &str, current: &Config) -> SpoolerResult<Config> {
+    let mut config: Config = serde_json::from_str(contents)?;
+
+    if config
+        .flux_api_token
+        .as_deref()
+        .is_none_or(|token| token.is_empty() || token == REDACTED)
+    {
+        config.flux_api_token = current.flux_api_token.clone();
+    }
+    if config.reverb_app_secret.is_empty() || config.reverb_app_secret == REDACTED {
+        config.reverb_app_secret = current.reverb_app_secret.clone();
+    }
+
+    config
+        .validate()
+        .map_err(|problems| SpoolerError::Config(problems.join("; ")))?;
+    Ok(config)
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::utils::json_store::{load_json, save_json};
+use crate::utils::pdf::pdf_page_count;
+
+pub const CONSUMPTION_FILE: &str = "consumption.json";
+
+/// Estimated media used by one printer since the totals were last reset
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PrinterConsumption {
+    /// Jobs submitted to CUPS
+    pub jobs: u64,
+    /// Estimated sheets: pages found in each document times its copies
+    pub pages: u64,
+}
+
+/// Totals keyed by printer `system_name`, mirrored to `consumption.json`.
+pub type Consumption = Arc<Mutex<HashMap<String, PrinterConsumption>>>;
+
+/// Create the shared totals, seeded from `consumption.json`
+pub fn new_consumption() -> Consumption {
+    Arc::new(Mutex::new(load_json(CONSUMPTION_FILE)))
+}
+
+/// Persist the totals to `consumption.json`
+pub fn save_consumption(totals: &HashMap<String, PrinterConsumption>) {
+    save_json(CONSUMPTION_FILE, totals);
+}
+
+/// Estimate the pages a job uses: the document's page count (1 when it cannot
+/// be determined, e.g. for non-PDF documents) times `copies`
+pub fn estimate_pages(document: &[u8], copies: u32) -> u64 {
+    u64::from(pdf_page_count(document).unwrap_or(1)) * u64::from(copies.max(1))
+}
+
+/// Add one job of `pages` estimated pages to the totals of `system_name`
+pub fn record_consumption(
+    totals: &mut HashMap<String, PrinterConsumption>,
+    system_name: &str,
+    pages: u64,
+) {
+    let entry = totals.entry(system_name.to_string()).or_default();
+    entry.jobs += 1;
+    entry.pages = entry.pages.saturating_add(pages);
+}
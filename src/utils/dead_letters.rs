@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::models::PrintJob;
+use crate::utils::json_store::{load_json, save_json};
+
+pub const DEAD_LETTERS_FILE: &str = "dead_letters.json";
+
+/// Most jobs kept in the dead-letter store; older entries are dropped first
+pub const MAX_DEAD_LETTERS: usize = 200;
+
+/// A job reported to the API as failed, kept so it can be inspected and retried.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeadLetter {
+    pub job_id: u32,
+    /// CUPS job of the failed print, if it got that far
+    pub cups_job_id: Option<u64>,
+    /// Error message reported to the API
+    pub error: String,
+    /// How often the job has failed, counting failed retries
+    pub attempts: u32,
+    /// Unix timestamp (seconds) of the latest failure
+    pub failed_at: u64,
+    /// The job as fetched from the API, without inline content; absent for
+    /// entries written by older versions and failures found by reconciliation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<PrintJob>,
+}
+
+/// Failed jobs, oldest first, mirrored to `dead_letters.json` for
+/// `list-jobs --dead-letters` and `reprint --dead-letter`.
+pub type DeadLetters = Arc<Mutex<VecDeque<DeadLetter>>>;
+
+/// Create the shared store, seeded from `dead_letters.json`
+pub fn new_dead_letters() -> DeadLetters {
+    Arc::new(Mutex::new(load_json(DEAD_LETTERS_FILE)))
+}
+
+/// Persist the store to `dead_letters.json`
+pub fn save_dead_letters(dead_letters: &VecDeque<DeadLetter>) {
+    save_json(DEAD_LETTERS_FILE, dead_letters);
+}
+
+/// `job` as kept in the dead-letter store: inline content is left out, a
+/// retry fetches the job again anyway
+pub fn job_snapshot(job: &PrintJob) -> PrintJob {
+    PrintJob {
+        content_base64: None,
+        ..job.clone()
+    }
+}
+
+/// Record a failed job, keeping at most [`MAX_DEAD_LETTERS`] entries.
+///
+/// A job that fails again moves to the newest position with the new error
+/// and one more attempt; without `job` the previous snapshot is kept.
+pub fn record_dead_letter(
+    dead_letters: &mut VecDeque<DeadLetter>,
+    job_id: u32,
+    job: Option<&PrintJob>,
+    cups_job_id: Option<u64>,
+    error: &str,
+    now: u64,
+) {
+    let previous = dead_letters
+        .iter()
+        .position(|letter| letter.job_id == job_id)
+        .and_then(|index| dead_letters.remove(index));
+    let attempts = previous.as_ref().map_or(0, |letter| letter.attempts);
+    let job = job
+        .map(job_snapshot)
+        .or_else(|| previous.and_then(|letter| letter.job));
+    dead_letters.push_back(DeadLetter {
+        job_id,
+        cups_job_id,
+        error: error.to_string(),
+        attempts: attempts + 1,
+        failed_at: now,
+        job,
+    });
+    while dead_letters.len() > MAX_DEAD_LETTERS {
+        dead_letters.pop_front();
+    }
+}
+
+/// Drop a job from the store once it has printed; returns whether it was listed
+pub fn forget_dead_letter(dead_letters: &mut VecDeque<DeadLetter>, job_id: u32) -> bool {
+    let before = dead_letters.len();
+    dead_letters.retain(|letter| letter.job_id != job_id);
+    dead_letters.len() != before
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::error::SpoolerError;
+use crate::utils::json_store::{load_json, save_json};
+use crate::utils::status_queue::retry_delay_secs;
+
+pub const PENDING_FETCH_FILE: &str = "pending_fetch.json";
+
+/// Fetch attempts after which a job id is dropped (about three hours of backoff)
+pub const MAX_FETCH_ATTEMPTS: u32 = 10;
+
+/// A job announced over the WebSocket that could not be fetched from the API yet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingFetch {
+    pub job_id: u32,
+    pub attempts: u32,
+    /// Unix timestamp (seconds) before which the fetch is not retried
+    pub next_attempt_at: u64,
+}
+
+impl PendingFetch {
+    /// Whether the backoff delay for this fetch has elapsed
+    pub fn is_due(&self, now: u64) -> bool {
+        self.next_attempt_at <= now
+    }
+
+    /// Record a failed fetch and schedule the next attempt with exponential backoff.
+    ///
+    /// Returns `false` once [`MAX_FETCH_ATTEMPTS`] is reached and the id should be dropped.
+    pub fn schedule_retry(&mut self, now: u64) -> bool {
+        self.attempts = self.attempts.saturating_add(1);
+        self.next_attempt_at = now + retry_delay_secs(self.attempts);
+        self.attempts < MAX_FETCH_ATTEMPTS
+    }
+}
+
+/// Shared queue of job ids awaiting a fetch, mirrored to `pending_fetch.json`.
+pub type PendingFetches = Arc<Mutex<Vec<PendingFetch>>>;
+
+/// Create the shared queue, seeded from `pending_fetch.json`
+pub fn new_pending_fetches() -> PendingFetches {
+    Arc::new(Mutex::new(load_json(PENDING_FETCH_FILE)))
+}
+
+/// Persist the queue to `pending_fetch.json`
+pub fn save_pending_fetches(fetches: &[PendingFetch]) {
+    save_json(PENDING_FETCH_FILE, &fetches);
+}
+
+/// Queue a job id after its first failed fetch; an id already queued is kept as-is
+pub fn enqueue_pending_fetch(queue: &mut Vec<PendingFetch>, job_id: u32, now: u64) {
+    if queue.iter().any(|f| f.job_id == job_id) {
+        return;
+    }
+    let mut fetch = PendingFetch {
+        job_id,
+        attempts: 0,
+        next_attempt_at: now,
+    };
+    fetch.schedule_retry(now);
+    queue.push(fetch);
+}
+
+/// Whether a failed fetch is worth retrying: the API could not be reached or
+/// answered with an error, as opposed to a response that cannot be parsed
+pub fn is_retryable_fetch_error(error: &SpoolerError) -> bool {
+    matches!(error, SpoolerError::Network(_) | SpoolerError::Api(_))
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::models::PrintJobStatus;
+use crate::utils::json_store::{load_json, save_json};
+
+pub const FINISHED_JOBS_FILE: &str = "finished_jobs.json";
+
+/// Most finished jobs kept for reconciliation; older entries are dropped first
+pub const MAX_FINISHED_JOBS: usize = 500;
+
+/// How long after finishing a job is still compared with CUPS (one day)
+pub const RECONCILE_WINDOW_SECS: u64 = 24 * 3600;
+
+/// A job whose final status was reported to the API, kept so a later look at
+/// the CUPS job history can correct it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FinishedJob {
+    pub api_job_id: u32,
+    pub cups_job_id: u64,
+    pub printer_name: String,
+    /// Status last reported to the API
+    pub reported_status: PrintJobStatus,
+    /// Unix timestamp (seconds) when the job finished
+    pub finished_at: u64,
+}
+
+/// Shared list of finished jobs, oldest first, mirrored to `finished_jobs.json`.
+pub type FinishedJobs = Arc<Mutex<Vec<FinishedJob>>>;
+
+/// Create the shared list, seeded from `finished_jobs.json`
+pub fn new_finished_jobs() -> FinishedJobs {
+    Arc::new(Mutex::new(load_json(FINISHED_JOBS_FILE)))
+}
+
+/// Persist the list to `finished_jobs.json`
+pub fn save_finished_jobs(jobs: &[FinishedJob]) {
+    save_json(FINISHED_JOBS_FILE, &jobs);
+}
+
+/// Record a finished job, replacing an earlier entry for the same API job and
+/// keeping at most [`MAX_FINISHED_JOBS`] entries
+pub fn record_finished_job(jobs: &mut Vec<FinishedJob>, job: FinishedJob) {
+    jobs.retain(|j| j.api_job_id != job.api_job_id);
+    jobs.push(job);
+    if jobs.len() > MAX_FINISHED_JOBS {
+        let excess = jobs.len() - MAX_FINISHED_JOBS;
+        jobs.drain(..excess);
+    }
+}
+
+/// Drop jobs that finished more than [`RECONCILE_WINDOW_SECS`] before `now`;
+/// returns whether any were removed
+pub fn prune_finished_jobs(jobs: &mut Vec<FinishedJob>, now: u64) -> bool {
+    let before = jobs.len();
+    jobs.retain(|j| now.saturating_sub(j.finished_at) <= RECONCILE_WINDOW_SECS);
+    jobs.len() != before
+}
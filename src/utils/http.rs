@@ -1,13 +1,236 @@
-use crate::models::Config;
-use reqwest::RequestBuilder;
+use crate::error::{SpoolerError, SpoolerResult};
+use crate::models::{Config, MediaRedirectAuth};
+use reqwest::header::HeaderMap;
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client, Identity, RequestBuilder, Response, Url};
+use std::fs;
+use tracing::{info, warn};
+
+/// Maximum number of redirects followed for a single request
+pub const MAX_REDIRECTS: usize = 10;
 
 /// Add authorization header to a request using the API token from config
+///
+/// The token is resolved through `token_source` for every request, so a
+/// token file or variable that changes is picked up without a restart.
+///
+/// When a `tenant` is configured it is sent as the `tenant_header` header and,
+/// if `tenant_query_param` is set, also appended as that query parameter.
 pub fn with_auth_header(request: RequestBuilder, config: &Config) -> RequestBuilder {
-    request.header(
+    let mut request = request.header(
         "Authorization",
-        format!(
-            "Bearer {}",
-            config.flux_api_token.as_ref().unwrap_or(&String::new())
-        ),
-    )
+        format!("Bearer {}", config.api_token().unwrap_or_default()),
+    );
+
+    if let Some(tenant) = &config.tenant {
+        request = request.header(config.tenant_header.as_str(), tenant.as_str());
+        if let Some(param) = &config.tenant_query_param {
+            request = request.query(&[(param.as_str(), tenant.as_str())]);
+        }
+    }
+
+    request
+}
+
+/// Read a response body as text, giving up once it exceeds `max_mb` megabytes (0 = unlimited).
+///
+/// An announced `Content-Length` over the cap fails before anything is read.
+pub async fn read_text_limited(response: Response, max_mb: u64) -> SpoolerResult<String> {
+    if max_mb == 0 {
+        return Ok(response.text().await?);
+    }
+    let body = read_bytes_limited(response, max_mb).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Read a binary response body, with the same cap as [`read_text_limited`]
+pub async fn read_bytes_limited(mut response: Response, max_mb: u64) -> SpoolerResult<Vec<u8>> {
+    let limit_bytes = max_mb.saturating_mul(1024 * 1024);
+    if limit_bytes == 0 {
+        return Ok(response.bytes().await?.to_vec());
+    }
+    let too_large = |url: &Url| {
+        SpoolerError::Api(format!(
+            "Response from {} exceeds the {} MB limit (max_api_response_mb)",
+            url, max_mb
+        ))
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit_bytes)
+    {
+        return Err(too_large(response.url()));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (body.len() + chunk.len()) as u64 > limit_bytes {
+            return Err(too_large(response.url()));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Shorten a response body for logging, respecting UTF-8 character boundaries
+pub fn truncate_for_log(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}... ({} bytes total)", &text[..end], text.len()),
+        None => text.to_string(),
+    }
+}
+
+/// Headers whose values are replaced by [`redacted_headers`]
+const SENSITIVE_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// Header names and values for logging, with credentials replaced by `[redacted]`
+pub fn redacted_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if SENSITIVE_HEADERS.contains(&name.as_str()) {
+                "[redacted]".to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.as_str().to_string(), value)
+        })
+        .collect()
+}
+
+/// The file name from a `Content-Disposition` header value.
+///
+/// `filename*` (RFC 5987, percent-encoded) wins over `filename`. Directory
+/// parts and control characters are dropped; returns `None` if nothing is left.
+pub fn content_disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in value.split(';').skip(1) {
+        let Some((key, raw)) = param.split_once('=') else {
+            continue;
+        };
+        let raw = raw.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename" => plain = Some(raw.trim_matches('"').to_string()),
+            "filename*" => {
+                // charset'language'percent-encoded-value
+                let encoded = raw.splitn(3, '\'').nth(2).unwrap_or(raw);
+                let decoded = urlencoding::decode_binary(encoded.trim_matches('"').as_bytes());
+                extended = Some(String::from_utf8_lossy(&decoded).into_owned());
+            }
+            _ => {}
+        }
+    }
+    let name = extended.or(plain)?;
+    let name: String = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect();
+    let name = name.trim();
+    (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+}
+
+/// Whether a redirect from `from` to `to` leaves the origin (scheme, host or port).
+///
+/// reqwest drops the `Authorization` header on such hops.
+pub fn changes_origin(from: &Url, to: &Url) -> bool {
+    from.scheme() != to.scheme()
+        || from.host_str() != to.host_str()
+        || from.port_or_known_default() != to.port_or_known_default()
+}
+
+/// Redirect policy that logs hops to another host.
+///
+/// With [`MediaRedirectAuth::Forward`] such hops are not followed, so the
+/// caller gets the redirect response and can re-issue it with credentials.
+fn redirect_policy(redirect_auth: MediaRedirectAuth) -> Policy {
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+        let Some(previous) = attempt.previous().last() else {
+            return attempt.follow();
+        };
+        if !changes_origin(previous, attempt.url()) {
+            return attempt.follow();
+        }
+
+        info!(
+            from = previous.host_str().unwrap_or_default(),
+            to = attempt.url().host_str().unwrap_or_default(),
+            forward_auth = redirect_auth == MediaRedirectAuth::Forward,
+            "Request redirected to a different host"
+        );
+        match redirect_auth {
+            MediaRedirectAuth::Strip => attempt.follow(),
+            MediaRedirectAuth::Forward => attempt.stop(),
+        }
+    })
+}
+
+/// Build the shared HTTP client used for all API and media requests.
+///
+/// When a client certificate and key are configured they are loaded as a TLS
+/// identity for mutual TLS, and `tls_ca_cert` is added as an extra trusted
+/// root. Unreadable or malformed files are reported as a configuration error
+/// so startup fails instead of silently falling back to the defaults.
+pub fn build_http_client(config: &Config) -> SpoolerResult<Client> {
+    let mut builder = Client::builder().redirect(redirect_policy(config.media_redirect_auth));
+
+    if let Some(ca_path) = &config.tls_ca_cert {
+        let pem = fs::read(ca_path).map_err(|e| {
+            SpoolerError::Config(format!(
+                "Failed to read CA certificate '{}': {}",
+                ca_path, e
+            ))
+        })?;
+        let certificate = Certificate::from_pem(&pem).map_err(|e| {
+            SpoolerError::Config(format!("Invalid CA certificate '{}': {}", ca_path, e))
+        })?;
+        info!(ca_cert = %ca_path, "Trusting additional CA certificate for API requests");
+        builder = builder.add_root_certificate(certificate);
+    }
+
+    if config.tls_accept_invalid_certs {
+        warn!(
+            "TLS certificate validation is DISABLED (tls_accept_invalid_certs = true); \
+             API traffic can be intercepted. Use tls_ca_cert instead where possible"
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    match (&config.client_cert_path, &config.client_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = fs::read(cert_path).map_err(|e| {
+                SpoolerError::Config(format!(
+                    "Failed to read client certificate '{}': {}",
+                    cert_path, e
+                ))
+            })?;
+            let key = fs::read(key_path).map_err(|e| {
+                SpoolerError::Config(format!("Failed to read client key '{}': {}", key_path, e))
+            })?;
+            let identity = Identity::from_pkcs8_pem(&cert, &key).map_err(|e| {
+                SpoolerError::Config(format!("Invalid client certificate or key: {}", e))
+            })?;
+            info!(cert = %cert_path, "Using client certificate for API requests");
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err(SpoolerError::Config(
+                "client_cert_path and client_key_path must be set together".to_string(),
+            ));
+        }
+    }
+
+    Ok(builder.build()?)
 }
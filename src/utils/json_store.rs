@@ -0,0 +1,58 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Path to a state file inside the config directory
+pub fn state_file_path(file_name: &str) -> PathBuf {
+    crate::utils::config::config_dir().join(file_name)
+}
+
+/// Load a JSON state file, falling back to the default value if it is missing or invalid
+pub fn load_json<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    let path = state_file_path(file_name);
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            warn!(error = %e, path = %path.display(), "Error parsing state file, starting empty");
+            T::default()
+        }),
+        Err(_) => {
+            debug!(path = %path.display(), "State file not found, starting empty");
+            T::default()
+        }
+    }
+}
+
+/// Save a JSON state file, logging (not returning) failures
+pub fn save_json<T: Serialize>(file_name: &str, value: &T) {
+    let path = state_file_path(file_name);
+    let config_dir = crate::utils::config::config_dir();
+
+    // create_dir_all is idempotent - no need to check existence first
+    if let Err(e) = fs::create_dir_all(&config_dir) {
+        warn!(error = %e, "Failed to create config directory");
+        return;
+    }
+
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => {
+            if let Err(e) = fs::write(&path, json) {
+                warn!(error = %e, path = %path.display(), "Failed to save state file");
+            } else {
+                debug!(path = %path.display(), "Saved state file");
+            }
+        }
+        Err(e) => warn!(error = %e, "Failed to serialize state file"),
+    }
+}
+
+/// Delete a state file; returns `false` if it did not exist
+pub fn remove_state_file(path: &Path) -> std::io::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
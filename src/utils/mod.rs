@@ -1,4 +1,16 @@
 pub mod config;
+pub mod consumption;
+pub mod dead_letters;
+pub mod fetch_queue;
+pub mod finished_jobs;
 pub mod http;
+pub mod json_store;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+pub mod pdf;
 pub mod printer_storage;
+pub mod recent_jobs;
+pub mod skipped_jobs;
+pub mod status_queue;
+pub mod temp_files;
 pub mod tui;
@@ -0,0 +1,128 @@
+//! Span export to an OTLP collector (cargo feature `otlp`).
+//!
+//! The bridge's spans reach OpenTelemetry through `tracing-opentelemetry` and
+//! are sent by the SDK's batch exporter over OTLP/HTTP to
+//! `<otlp_endpoint>/v1/traces`. FLUX requests carry the current span as a W3C
+//! `traceparent` header, so the bridge's spans join FLUX's traces.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use opentelemetry::propagation::{Injector, TextMapPropagator};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tracing::{Level, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::utils::config::config_path;
+
+/// Only spans from the bridge itself are exported, not those of its dependencies
+const EXPORTED_TARGET: &str = "nuxbe_printer_bridge";
+
+/// How long one export request may take before it is abandoned
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Kept so [`shutdown`] can send the spans still queued on exit
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Tracing layer exporting spans to the `otlp_endpoint` of `config.json`;
+/// `None` when it is unset.
+///
+/// Logging is not set up yet when this runs, so the file is read directly,
+/// without the side effects of `load_config`, and problems go to stderr.
+pub fn layer<S>() -> Option<impl Layer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let contents = std::fs::read_to_string(config_path()).ok()?;
+    let config: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    let setting = |field: &str| config.get(field)?.as_str().map(str::trim);
+    let endpoint = setting("otlp_endpoint").filter(|e| !e.is_empty())?;
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(otlp_traces_url(endpoint))
+        .with_timeout(EXPORT_TIMEOUT)
+        .build();
+    let exporter = match exporter {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!(
+                "Spans are not exported, the OTLP exporter for {} failed: {}",
+                endpoint, e
+            );
+            return None;
+        }
+    };
+
+    let mut attributes = vec![KeyValue::new("service.version", env!("CARGO_PKG_VERSION"))];
+    if let Some(instance_name) = setting("instance_name").filter(|name| !name.is_empty()) {
+        attributes.push(KeyValue::new(
+            "service.instance.id",
+            instance_name.to_string(),
+        ));
+    }
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .with_attributes(attributes)
+                .build(),
+        )
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    let _ = TRACER_PROVIDER.set(provider);
+
+    Some(
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(Targets::new().with_target(EXPORTED_TARGET, Level::TRACE)),
+    )
+}
+
+/// Send the spans still queued; call once before the process exits
+pub fn shutdown() {
+    if let Some(provider) = TRACER_PROVIDER.get()
+        && let Err(e) = provider.shutdown()
+    {
+        eprintln!("Failed to send the last spans to the OTLP collector: {}", e);
+    }
+}
+
+/// The OTLP/HTTP traces URL for an endpoint such as `http://collector:4318`
+pub fn otlp_traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim().trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    }
+}
+
+/// Add the current span as a W3C `traceparent` header; nothing is added
+/// when the span is not exported
+pub fn inject_trace_context(headers: &mut HeaderMap) {
+    let context = tracing::Span::current().context();
+    TraceContextPropagator::new().inject_context(&context, &mut HeaderInjector(headers));
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
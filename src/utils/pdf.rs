@@ -0,0 +1,97 @@
+/// A4 page size in PDF points (1/72 inch)
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+
+const FONT_SIZE: u32 = 14;
+const LINE_HEIGHT: u32 = 22;
+const MARGIN: u32 = 72;
+
+/// Build a single-page A4 PDF showing the given lines in Helvetica.
+///
+/// This is intentionally minimal (one page, standard font, no compression) and
+/// meant for generated banner pages rather than arbitrary documents. Non-ASCII
+/// characters are replaced with `?` since the standard font encoding is used.
+pub fn text_page_pdf(lines: &[String]) -> Vec<u8> {
+    let mut content = format!("BT\n/F1 {} Tf\n{} TL\n", FONT_SIZE, LINE_HEIGHT);
+    content.push_str(&format!("{} {} Td\n", MARGIN, PAGE_HEIGHT - MARGIN));
+    for line in lines {
+        content.push_str(&format!("({}) Tj T*\n", escape_pdf_text(line)));
+    }
+    content.push_str("ET\n");
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 4 0 R >> >> /Contents 5 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", index + 1, object));
+    }
+
+    let xref_offset = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    ));
+
+    pdf.into_bytes()
+}
+
+/// Escape a line for use inside a PDF literal string
+fn escape_pdf_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// Count the page objects (`/Type /Page`, not `/Pages`) in a PDF.
+///
+/// This is a byte scan rather than a parse: pages inside compressed object
+/// streams are not seen. Returns `None` when no page object is found.
+pub fn pdf_page_count(document: &[u8]) -> Option<u32> {
+    let mut count = 0u32;
+    let mut rest = document;
+    while let Some(position) = rest.windows(5).position(|w| w == b"/Type") {
+        rest = &rest[position + 5..];
+        let after_space = rest
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let value = &rest[after_space..];
+        if value.starts_with(b"/Page") && !value.get(5).is_some_and(|b| b.is_ascii_alphanumeric()) {
+            count += 1;
+        }
+    }
+    (count > 0).then_some(count)
+}
@@ -2,8 +2,35 @@ use crate::models::Printer;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tracing::{debug, warn};
 
+/// In-memory view of the known printers keyed by `system_name`.
+///
+/// This is the authoritative copy at runtime; `printers.json` is only read once
+/// to seed it and is rewritten (via [`store_printers`]) whenever it changes.
+pub type SharedPrinters = Arc<Mutex<HashMap<String, Printer>>>;
+
+/// Create the shared printer cache, seeded from `printers.json`
+pub fn new_shared_printers() -> SharedPrinters {
+    Arc::new(Mutex::new(load_printers()))
+}
+
+/// Persist printers if they changed and replace the in-memory view.
+///
+/// The cache lock is held while writing the file so readers never observe the
+/// cache and `printers.json` out of step. Returns whether the file was written.
+pub fn store_printers(
+    shared: &SharedPrinters,
+    printers: &HashMap<String, Printer>,
+    saved_printers: &HashMap<String, Printer>,
+) -> bool {
+    let mut cache = shared.lock().expect("Failed to acquire printers lock");
+    let changed = save_printers_if_changed(printers, saved_printers);
+    *cache = printers.clone();
+    changed
+}
+
 /// Path to the printers JSON file
 pub fn printers_file_path() -> PathBuf {
     let config_dir = crate::utils::config::config_dir();
@@ -52,6 +79,9 @@ pub fn printers_have_changed(
                     || current_printer.make_and_model != saved_printer.make_and_model
                     || current_printer.media_sizes != saved_printer.media_sizes
                     || current_printer.printer_id != saved_printer.printer_id
+                    || current_printer.enabled != saved_printer.enabled
+                    || current_printer.supports_color != saved_printer.supports_color
+                    || current_printer.accepting_jobs != saved_printer.accepting_jobs
                 {
                     return true;
                 }
@@ -0,0 +1,32 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::utils::json_store::{load_json, save_json};
+
+pub const RECENT_JOBS_FILE: &str = "recent_jobs.json";
+
+/// Ids of jobs recently completed by this spooler, oldest first, mirrored to
+/// `recent_jobs.json` so a restart does not reprint a job the API still lists
+/// as pending.
+pub type RecentJobs = Arc<Mutex<VecDeque<u32>>>;
+
+/// Create the shared list, seeded from `recent_jobs.json`
+pub fn new_recent_jobs() -> RecentJobs {
+    Arc::new(Mutex::new(load_json(RECENT_JOBS_FILE)))
+}
+
+/// Persist the list to `recent_jobs.json`
+pub fn save_recent_jobs(recent: &VecDeque<u32>) {
+    save_json(RECENT_JOBS_FILE, recent);
+}
+
+/// Remember a completed job, keeping at most `limit` ids (0 = remember none).
+///
+/// An id that is already listed moves to the newest position.
+pub fn remember_completed_job(recent: &mut VecDeque<u32>, job_id: u32, limit: usize) {
+    recent.retain(|&id| id != job_id);
+    recent.push_back(job_id);
+    while recent.len() > limit {
+        recent.pop_front();
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::utils::json_store::{load_json, save_json};
+
+pub const SKIPPED_JOBS_FILE: &str = "skipped_jobs.json";
+
+/// Most jobs kept in the skipped list; older entries are dropped first
+pub const MAX_SKIPPED_JOBS: usize = 200;
+
+/// A job that was left pending instead of printed, and why.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SkippedJob {
+    pub job_id: u32,
+    pub reason: String,
+    /// Unix timestamp (seconds) of the latest skip
+    pub skipped_at: u64,
+}
+
+/// Jobs skipped by quiet hours, `max_job_age_hours` or a deferral, oldest
+/// first, mirrored to `skipped_jobs.json` for `list-jobs --skipped` and
+/// `reprint --skipped`.
+pub type SkippedJobs = Arc<Mutex<VecDeque<SkippedJob>>>;
+
+/// Create the shared list, seeded from `skipped_jobs.json`
+pub fn new_skipped_jobs() -> SkippedJobs {
+    Arc::new(Mutex::new(load_json(SKIPPED_JOBS_FILE)))
+}
+
+/// Persist the list to `skipped_jobs.json`
+pub fn save_skipped_jobs(skipped: &VecDeque<SkippedJob>) {
+    save_json(SKIPPED_JOBS_FILE, skipped);
+}
+
+/// Record a skip, keeping at most [`MAX_SKIPPED_JOBS`] entries.
+///
+/// A job skipped again moves to the newest position with the new reason.
+pub fn record_skipped_job(skipped: &mut VecDeque<SkippedJob>, job_id: u32, reason: &str, now: u64) {
+    skipped.retain(|job| job.job_id != job_id);
+    skipped.push_back(SkippedJob {
+        job_id,
+        reason: reason.to_string(),
+        skipped_at: now,
+    });
+    while skipped.len() > MAX_SKIPPED_JOBS {
+        skipped.pop_front();
+    }
+}
+
+/// Drop a job from the list once it has printed; returns whether it was listed
+pub fn forget_skipped_job(skipped: &mut VecDeque<SkippedJob>, job_id: u32) -> bool {
+    let before = skipped.len();
+    skipped.retain(|job| job.job_id != job_id);
+    skipped.len() != before
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::PrintJobStatus;
+use crate::utils::json_store::{load_json, save_json};
+
+pub const PENDING_STATUS_FILE: &str = "pending_status_updates.json";
+
+/// First retry delay for a failed status update; doubled per attempt.
+const RETRY_BASE_SECS: u64 = 15;
+
+/// Upper bound for the retry delay so updates keep being attempted hourly.
+const RETRY_MAX_SECS: u64 = 3600;
+
+/// A job status update that could not be delivered to the API yet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingStatusUpdate {
+    pub job_id: u32,
+    pub cups_job_id: Option<u64>,
+    pub status: PrintJobStatus,
+    pub error_message: Option<String>,
+    /// Timestamp captured when the job completed, so retries keep the real print time
+    pub printed_at: Option<String>,
+    pub attempts: u32,
+    /// Unix timestamp (seconds) before which the update is not retried
+    pub next_attempt_at: u64,
+    /// Corrects a job the API already finished (reconciliation, dead-letter retry),
+    /// so it is never sent on the condition that the job is not completed yet
+    #[serde(default)]
+    pub correction: bool,
+}
+
+impl PendingStatusUpdate {
+    /// Whether the update is sent with `expected_is_completed: false`: only the
+    /// pending→completed transition, and only with `conditional_status_updates`
+    pub fn is_conditional(&self, conditional_status_updates: bool) -> bool {
+        conditional_status_updates && self.status == PrintJobStatus::Completed && !self.correction
+    }
+
+    /// Whether the backoff delay for this update has elapsed
+    pub fn is_due(&self, now: u64) -> bool {
+        self.next_attempt_at <= now
+    }
+
+    /// Record a failed delivery and push the next attempt out with exponential backoff
+    pub fn schedule_retry(&mut self, now: u64) {
+        self.attempts = self.attempts.saturating_add(1);
+        self.next_attempt_at = now + retry_delay_secs(self.attempts);
+    }
+}
+
+/// Shared queue of undelivered status updates, mirrored to `pending_status_updates.json`.
+pub type PendingStatusUpdates = Arc<Mutex<Vec<PendingStatusUpdate>>>;
+
+/// Create the shared queue, seeded from `pending_status_updates.json`
+pub fn new_pending_status_updates() -> PendingStatusUpdates {
+    Arc::new(Mutex::new(load_json(PENDING_STATUS_FILE)))
+}
+
+/// Persist the queue to `pending_status_updates.json`
+pub fn save_pending_status_updates(updates: &[PendingStatusUpdate]) {
+    save_json(PENDING_STATUS_FILE, &updates);
+}
+
+/// Add an update to the queue, replacing any older update for the same job.
+///
+/// The CUPS job id from the replaced entry is kept so a later terminal status
+/// does not drop the id reported with the earlier `queued` update.
+pub fn enqueue_status_update(
+    queue: &mut Vec<PendingStatusUpdate>,
+    mut update: PendingStatusUpdate,
+) {
+    if let Some(pos) = queue.iter().position(|u| u.job_id == update.job_id) {
+        let previous = queue.remove(pos);
+        if update.cups_job_id.is_none() {
+            update.cups_job_id = previous.cups_job_id;
+        }
+    }
+    queue.push(update);
+}
+
+/// Delay before the given retry attempt, doubling up to [`RETRY_MAX_SECS`]
+pub fn retry_delay_secs(attempts: u32) -> u64 {
+    let shift = attempts.saturating_sub(1).min(16);
+    (RETRY_BASE_SECS << shift).min(RETRY_MAX_SECS)
+}
+
+/// Current Unix time in seconds
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
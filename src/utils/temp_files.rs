@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use tempfile::NamedTempFile;
+use tracing::{debug, info, warn};
+
+/// Prefix of every temp file the bridge creates, used by the startup sweep
+pub const TEMP_FILE_PREFIX: &str = "nuxbe-";
+
+/// Temp files older than this are considered leaked by a previous run
+pub const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Create a named temp file carrying the bridge prefix
+pub fn new_temp_file() -> std::io::Result<NamedTempFile> {
+    tempfile::Builder::new().prefix(TEMP_FILE_PREFIX).tempfile()
+}
+
+/// Create a named temp file ending in the extension of `file_name` (e.g. `.pdf`).
+///
+/// Extensions that are empty, longer than 10 characters or not alphanumeric
+/// are left out.
+pub fn new_temp_file_for(file_name: &str) -> std::io::Result<NamedTempFile> {
+    let suffix = match Path::new(file_name).extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.len() <= 10 && ext.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            format!(".{}", ext)
+        }
+        _ => String::new(),
+    };
+    tempfile::Builder::new()
+        .prefix(TEMP_FILE_PREFIX)
+        .suffix(&suffix)
+        .tempfile()
+}
+
+/// Remove a temp file now instead of waiting for it to be dropped, logging failures
+pub fn remove_temp_file(temp_file: NamedTempFile) {
+    let path = temp_file.path().to_path_buf();
+    if let Err(e) = temp_file.close() {
+        warn!(path = %path.display(), error = %e, "Failed to remove temp file");
+    }
+}
+
+/// Delete stale bridge temp files from the system temp directory.
+///
+/// Returns the number of files removed.
+pub fn sweep_stale_temp_files() -> usize {
+    let removed = sweep_stale_temp_files_in(&std::env::temp_dir(), STALE_TEMP_FILE_AGE);
+    if removed > 0 {
+        info!(removed, "Removed stale temp files left by a previous run");
+    }
+    removed
+}
+
+/// Delete files starting with [`TEMP_FILE_PREFIX`] in `dir` that were last
+/// modified at least `max_age` ago.
+pub fn sweep_stale_temp_files_in(dir: &Path, max_age: Duration) -> usize {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(dir = %dir.display(), error = %e, "Failed to read temp directory");
+            return 0;
+        }
+    };
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+
+    for entry in entries.flatten() {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(TEMP_FILE_PREFIX)
+        {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if age < max_age {
+            continue;
+        }
+
+        match fs::remove_file(entry.path()) {
+            Ok(()) => {
+                debug!(path = %entry.path().display(), "Removed stale temp file");
+                removed += 1;
+            }
+            Err(e) => {
+                warn!(path = %entry.path().display(), error = %e, "Failed to remove stale temp file")
+            }
+        }
+    }
+
+    removed
+}
@@ -7,6 +7,7 @@ use std::sync::{Arc, Mutex};
 
 use crate::models::Config;
 use crate::utils::config::{load_config, save_config};
+use crate::utils::printer_storage::{load_printers, save_printers};
 
 /// Start the TUI editor for application settings
 pub fn run_tui() {
@@ -35,6 +36,7 @@ fn create_config_dialog(siv: &mut Cursive, config: Arc<Mutex<Config>>) {
     let interval_settings = create_interval_settings(&current_config);
     let api_settings = create_api_settings(&current_config);
     let reverb_settings = create_reverb_settings(&current_config);
+    let printer_settings = create_printer_settings();
 
     // Create the main dialog
     siv.add_layer(
@@ -46,6 +48,7 @@ fn create_config_dialog(siv: &mut Cursive, config: Arc<Mutex<Config>>) {
                     .child(interval_settings)
                     .child(api_settings)
                     .child(reverb_settings)
+                    .child(printer_settings)
                     .child(
                         TextView::new(
                             "Changes will be applied after saving and restarting the server.",
@@ -102,6 +105,12 @@ fn create_interval_settings(config: &Config) -> impl View {
                     EditView::new()
                         .content(config.job_check_interval.to_string())
                         .with_name("job_check_interval"),
+                )
+                .child(TextView::new("Max Job Check Interval when idle (minutes):"))
+                .child(
+                    EditView::new()
+                        .content(config.job_check_max_interval.to_string())
+                        .with_name("job_check_max_interval"),
                 ),
         )
         .title("Polling Intervals"),
@@ -195,6 +204,53 @@ fn create_reverb_settings(config: &Config) -> impl View {
     )
 }
 
+/// Create printers section with an enable toggle per known printer
+fn create_printer_settings() -> impl View {
+    let mut printers: Vec<_> = load_printers().into_values().collect();
+    printers.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut layout = LinearLayout::vertical();
+    if printers.is_empty() {
+        layout.add_child(TextView::new("No printers discovered yet."));
+    }
+    for printer in printers {
+        layout.add_child(
+            LinearLayout::horizontal()
+                .child(
+                    Checkbox::new()
+                        .with_checked(printer.enabled)
+                        .with_name(format!("printer_enabled:{}", printer.system_name)),
+                )
+                .child(TextView::new(format!(" {}", printer.name))),
+        );
+    }
+
+    PaddedView::new(
+        Margins::lrtb(1, 1, 0, 1),
+        Dialog::around(layout).title("Enabled Printers"),
+    )
+}
+
+/// Save printer enable toggles back to printers.json
+fn save_printers_from_ui(s: &mut Cursive) {
+    let mut printers = load_printers();
+    let mut changed = false;
+
+    for printer in printers.values_mut() {
+        let name = format!("printer_enabled:{}", printer.system_name);
+        if let Some(enabled) = s.call_on_name(&name, |view: &mut Checkbox| view.is_checked())
+            && enabled != printer.enabled
+        {
+            printer.enabled = enabled;
+            changed = true;
+        }
+    }
+
+    if changed {
+        save_printers(&printers);
+    }
+}
+
 /// Save configuration from UI values
 fn save_config_from_ui(s: &mut Cursive, config: Arc<Mutex<Config>>) {
     // Get a mutable reference to the config
@@ -226,6 +282,12 @@ fn save_config_from_ui(s: &mut Cursive, config: Arc<Mutex<Config>>) {
         })
         .unwrap_or(2);
 
+    config_guard.job_check_max_interval = s
+        .call_on_name("job_check_max_interval", |view: &mut EditView| {
+            view.get_content().parse::<u64>().unwrap_or(10)
+        })
+        .unwrap_or(10);
+
     config_guard.flux_url = s
         .call_on_name("flux_url", |view: &mut EditView| {
             view.get_content().to_string()
@@ -291,6 +353,7 @@ fn save_config_from_ui(s: &mut Cursive, config: Arc<Mutex<Config>>) {
 
     // Save the updated configuration
     save_config(&config_guard);
+    save_printers_from_ui(s);
 
     // Show success dialog
     s.add_layer(